@@ -0,0 +1,137 @@
+//! Measures how fast the lexer and parsers get through a generated document
+//! of records like an API might return. Run it with `cargo bench`.
+
+use json_parser::lexer::Lexer;
+use json_parser::{
+    EventParser, ParseOptions, parse_borrowed, parse_json, parse_reader, parse_slice,
+};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// About how many bytes of JSON to generate.
+const SIZE: usize = 32 << 20;
+const RUNS: usize = 10;
+
+fn main() {
+    let input = payload(SIZE);
+    println!("{} MB of records\n", input.len() >> 20);
+
+    measure("Lexer::next_token", &input, |input| {
+        let mut lexer = Lexer::new(input);
+        let mut tokens = 0;
+        while lexer.next_token().unwrap().is_some() {
+            tokens += 1;
+        }
+        tokens
+    });
+    measure("Lexer::from_slice", &input, |input| {
+        let mut lexer = Lexer::from_slice(input.as_bytes(), ParseOptions::default());
+        let mut tokens = 0;
+        while lexer.next_token().unwrap().is_some() {
+            tokens += 1;
+        }
+        tokens
+    });
+    measure("EventParser", &input, |input| {
+        let mut events = EventParser::new(input);
+        let mut count = 0;
+        while events.next_event().unwrap().is_some() {
+            count += 1;
+        }
+        count
+    });
+    measure("parse_borrowed", &input, |input| {
+        parse_borrowed(input).map(drop).unwrap();
+        0
+    });
+    measure("parse_json", &input, |input| {
+        parse_json(input).map(drop).unwrap();
+        0
+    });
+    measure("parse_slice", &input, |input| {
+        parse_slice(input.as_bytes()).map(drop).unwrap();
+        0
+    });
+    measure("parse_reader", &input, |input| {
+        parse_reader(input.as_bytes()).map(drop).unwrap();
+        0
+    });
+}
+
+/// Run `f` over `input` a few times and print its best throughput.
+fn measure(name: &str, input: &str, f: impl Fn(&str) -> usize) {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        black_box(f(black_box(input)));
+        best = best.min(start.elapsed());
+    }
+    let rate = input.len() as f64 / best.as_secs_f64() / (1 << 20) as f64;
+    println!("{:<20} {:>8.0} MB/s", name, rate);
+}
+
+/// Records with short and long strings, a few escapes and non-ASCII
+/// characters, integers, floats, booleans and nested arrays and objects.
+fn payload(size: usize) -> String {
+    let cities = [
+        "Zürich",
+        "London",
+        "São Paulo",
+        "東京",
+        "New York",
+        "Kraków",
+    ];
+    let words = ["alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta"];
+    // A small linear congruential generator keeps the document the same
+    // from run to run
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) as usize
+    };
+
+    let mut out = String::from("[\n");
+    let mut id = 0;
+    while out.len() < size {
+        if id > 0 {
+            out.push_str(",\n");
+        }
+        let tags: Vec<_> = (0..next() % 5)
+            .map(|_| format!("\"{}\"", words[next() % words.len()]))
+            .collect();
+        let history: Vec<_> = (0..next() % 12)
+            .map(|_| (next() % 100_000).to_string())
+            .collect();
+        out.push_str(&format!(
+            concat!(
+                "  {{\"id\": {}, \"name\": \"user_{}\", \"email\": \"user{}@example.com\", ",
+                "\"active\": {}, \"score\": {}.{:02}, \"ratio\": {}e-{}, ",
+                "\"tags\": [{}], \"address\": {{\"street\": \"{} {} Street\", ",
+                "\"city\": \"{}\", \"zip\": \"{:05}\"}}, \"history\": [{}], ",
+                "\"note\": \"{} said \\\"{}\\\" at\\t{}\\n\", \"parent\": null}}"
+            ),
+            id,
+            id,
+            id,
+            next() % 2 == 0,
+            next() % 1000,
+            next() % 100,
+            next() % 10_000,
+            next() % 9 + 1,
+            tags.join(", "),
+            next() % 500,
+            words[next() % words.len()],
+            cities[next() % cities.len()],
+            next() % 100_000,
+            history.join(", "),
+            words[next() % words.len()],
+            words[next() % words.len()],
+            next() % 24,
+        ));
+        id += 1;
+    }
+    out.push_str("\n]\n");
+    out
+}
@@ -0,0 +1,222 @@
+use crate::json::JsonValue;
+use crate::map::{self, Map};
+use crate::number::Number;
+use std::borrow::Cow;
+
+/// A JSON value whose strings and keys borrow from the text it was parsed
+/// from, made by [`parse_borrowed`](crate::parse_borrowed).
+///
+/// Strings without escapes are slices of the input, so reading a large
+/// document copies little of it. Strings with escapes have to be decoded,
+/// and are owned.
+///
+/// Like `JsonValue`, values are cloned, compared and converted without
+/// recursion, however deeply they nest, and `drop_iteratively` drops them
+/// that way.
+#[derive(Debug)]
+pub enum BorrowedValue<'a> {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Array(Vec<BorrowedValue<'a>>),
+    Object(Map<Cow<'a, str>, BorrowedValue<'a>>),
+}
+
+impl BorrowedValue<'_> {
+    /// Copy out everything still borrowed, making a `JsonValue`.
+    pub fn into_owned(mut self) -> JsonValue {
+        /// An owned array or object being filled in, with the members still
+        /// to convert and the key of the one being converted.
+        enum Converting<'a> {
+            Array(Vec<JsonValue>, std::vec::IntoIter<BorrowedValue<'a>>),
+            Object(
+                Map,
+                std::vec::IntoIter<(Cow<'a, str>, BorrowedValue<'a>)>,
+                Option<String>,
+            ),
+        }
+
+        let mut converting = Vec::new();
+        loop {
+            let mut converted = match &mut self {
+                BorrowedValue::Null => Some(JsonValue::Null),
+                BorrowedValue::Boolean(b) => Some(JsonValue::Boolean(*b)),
+                BorrowedValue::Number(n) => Some(JsonValue::Number(n.clone())),
+                BorrowedValue::String(s) => Some(JsonValue::String(std::mem::take(s).into_owned())),
+                BorrowedValue::Array(values) => {
+                    let values = std::mem::take(values);
+                    let owned = Vec::with_capacity(values.len());
+                    converting.push(Converting::Array(owned, values.into_iter()));
+                    None
+                }
+                BorrowedValue::Object(members) => {
+                    let members = std::mem::take(members);
+                    let owned = Map::with_capacity(members.len());
+                    converting.push(Converting::Object(owned, members.into_iter(), None));
+                    None
+                }
+            };
+
+            // Hand finished values to their containers, up to one with more
+            // members to convert
+            loop {
+                let found = match converting.last_mut() {
+                    None => return converted.expect("the outermost value is done"),
+                    Some(Converting::Array(owned, rest)) => {
+                        owned.extend(converted.take());
+                        rest.next()
+                    }
+                    Some(Converting::Object(owned, rest, key)) => {
+                        if let Some(value) = converted.take() {
+                            owned.insert(key.take().expect("a key"), value);
+                        }
+                        rest.next().map(|(k, value)| {
+                            *key = Some(k.into_owned());
+                            value
+                        })
+                    }
+                };
+                if let Some(value) = found {
+                    self = value;
+                    break;
+                }
+                converted = match converting.pop() {
+                    Some(Converting::Array(owned, _)) => Some(JsonValue::Array(owned)),
+                    Some(Converting::Object(owned, _, _)) => Some(JsonValue::Object(owned)),
+                    None => unreachable!("there is a container to finish"),
+                };
+            }
+        }
+    }
+
+    /// Drop the value a level at a time, as `JsonValue::drop_iteratively`
+    /// does.
+    pub fn drop_iteratively(mut self) {
+        let mut nested = Vec::new();
+        take_nested(&mut self, &mut nested);
+        while let Some(mut value) = nested.pop() {
+            take_nested(&mut value, &mut nested);
+        }
+    }
+}
+
+impl<'a> Clone for BorrowedValue<'a> {
+    fn clone(&self) -> Self {
+        /// A copy of an array or object being filled in, with the members
+        /// still to copy and the key of the one being copied.
+        enum Copying<'v, 'a> {
+            Array(
+                Vec<BorrowedValue<'a>>,
+                std::slice::Iter<'v, BorrowedValue<'a>>,
+            ),
+            Object(
+                Map<Cow<'a, str>, BorrowedValue<'a>>,
+                map::Iter<'v, Cow<'a, str>, BorrowedValue<'a>>,
+                Option<&'v Cow<'a, str>>,
+            ),
+        }
+
+        let mut copying = Vec::new();
+        let mut next = self;
+        loop {
+            let mut copied = match next {
+                BorrowedValue::Null => Some(BorrowedValue::Null),
+                BorrowedValue::Boolean(b) => Some(BorrowedValue::Boolean(*b)),
+                BorrowedValue::Number(n) => Some(BorrowedValue::Number(n.clone())),
+                BorrowedValue::String(s) => Some(BorrowedValue::String(s.clone())),
+                BorrowedValue::Array(values) => {
+                    let copy = Vec::with_capacity(values.len());
+                    copying.push(Copying::Array(copy, values.iter()));
+                    None
+                }
+                BorrowedValue::Object(members) => {
+                    let copy = Map::with_capacity(members.len());
+                    copying.push(Copying::Object(copy, members.iter(), None));
+                    None
+                }
+            };
+
+            // Hand finished copies to their containers, up to one with more
+            // members to copy
+            loop {
+                let found = match copying.last_mut() {
+                    None => return copied.expect("the outermost value is done"),
+                    Some(Copying::Array(copy, rest)) => {
+                        copy.extend(copied.take());
+                        rest.next()
+                    }
+                    Some(Copying::Object(copy, rest, key)) => {
+                        if let Some(value) = copied.take() {
+                            copy.insert(key.take().expect("a key").clone(), value);
+                        }
+                        rest.next().map(|(k, value)| {
+                            *key = Some(k);
+                            value
+                        })
+                    }
+                };
+                if let Some(value) = found {
+                    next = value;
+                    break;
+                }
+                copied = match copying.pop() {
+                    Some(Copying::Array(copy, _)) => Some(BorrowedValue::Array(copy)),
+                    Some(Copying::Object(copy, _, _)) => Some(BorrowedValue::Object(copy)),
+                    None => unreachable!("there is a container to finish"),
+                };
+            }
+        }
+    }
+}
+
+/// Values are equal when they have the same type and contents, as for
+/// `JsonValue`.
+impl PartialEq for BorrowedValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let mut pending = vec![(self, other)];
+        while let Some(pair) = pending.pop() {
+            match pair {
+                (BorrowedValue::Null, BorrowedValue::Null) => {}
+                (BorrowedValue::Boolean(a), BorrowedValue::Boolean(b)) if a == b => {}
+                (BorrowedValue::Number(a), BorrowedValue::Number(b)) if a == b => {}
+                (BorrowedValue::String(a), BorrowedValue::String(b)) if a == b => {}
+                (BorrowedValue::Array(a), BorrowedValue::Array(b)) if a.len() == b.len() => {
+                    pending.extend(a.iter().zip(b));
+                }
+                (BorrowedValue::Object(a), BorrowedValue::Object(b)) if a.len() == b.len() => {
+                    for (key, a) in a {
+                        match b.get(key) {
+                            Some(b) => pending.push((a, b)),
+                            None => return false,
+                        }
+                    }
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Move the arrays and objects directly inside `value` onto `nested`.
+fn take_nested<'a>(value: &mut BorrowedValue<'a>, nested: &mut Vec<BorrowedValue<'a>>) {
+    let is_nested =
+        |value: &BorrowedValue| matches!(value, BorrowedValue::Array(_) | BorrowedValue::Object(_));
+    match value {
+        BorrowedValue::Array(values) => nested.extend(values.drain(..).filter(is_nested)),
+        BorrowedValue::Object(members) => nested.extend(
+            std::mem::take(members)
+                .into_iter()
+                .map(|(_, value)| value)
+                .filter(is_nested),
+        ),
+        _ => {}
+    }
+}
+
+impl From<BorrowedValue<'_>> for JsonValue {
+    fn from(value: BorrowedValue<'_>) -> Self {
+        value.into_owned()
+    }
+}
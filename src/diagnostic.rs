@@ -0,0 +1,224 @@
+use crate::error::{JsonError, NumberError, Unexpected};
+use std::fmt;
+
+/// Renders a `JsonError` against the source it came from, rustc-style:
+///
+/// ```text
+/// error: expected ',' or '}' after object value, got string "b"
+///  --> config.json:3:3
+///   |
+/// 3 |   "b": 2
+///   |   ^^^ did you forget a comma?
+/// ```
+pub struct Diagnostic<'a> {
+    source: &'a str,
+    /// Byte offset in the input where `source` starts.
+    source_offset: usize,
+    error: &'a JsonError,
+    file_name: Option<&'a str>,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(source: &'a str, error: &'a JsonError) -> Self {
+        Diagnostic {
+            source,
+            source_offset: 0,
+            error,
+            file_name: None,
+        }
+    }
+
+    /// Render against just the line the error is on, which starts
+    /// `line_offset` bytes into the input, for input too large to hold.
+    pub fn for_line(line: &'a str, line_offset: usize, error: &'a JsonError) -> Self {
+        Diagnostic {
+            source: line,
+            source_offset: line_offset,
+            error,
+            file_name: None,
+        }
+    }
+
+    /// Name shown in the `-->` line instead of `<input>`.
+    pub fn with_file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    /// The first line of the report.
+    pub fn headline(&self) -> String {
+        let message = match self.error {
+            // The parser already words these as full sentences
+            JsonError::UnexpectedToken(unexpected, _)
+                if !matches!(unexpected, Unexpected::Character(_)) =>
+            {
+                unexpected.to_string()
+            }
+            _ => self.error.message(),
+        };
+
+        // Lowercase the first word, unless it's an acronym like "I/O"
+        let mut chars = message.chars();
+        match chars.next() {
+            Some(first) if chars.clone().next().is_some_and(char::is_lowercase) => {
+                first.to_lowercase().chain(chars).collect()
+            }
+            _ => message,
+        }
+    }
+
+    /// A short suggestion for fixing the error, if we have one.
+    pub fn hint(&self) -> Option<&'static str> {
+        let offending = self.source[self.offset()..].chars().next();
+
+        match self.error {
+            JsonError::UnexpectedToken(unexpected, _) => match unexpected {
+                Unexpected::ExpectedObjectSeparator(_) | Unexpected::ExpectedArraySeparator(_) => {
+                    match offending {
+                        Some('"' | '{' | '[' | '-' | '0'..='9' | 't' | 'f' | 'n') => {
+                            Some("did you forget a comma?")
+                        }
+                        _ => None,
+                    }
+                }
+                Unexpected::TrailingCommaInObject | Unexpected::TrailingCommaInArray => {
+                    Some("JSON does not allow a comma before the closing bracket")
+                }
+                Unexpected::ExpectedColon(_) => Some("keys and values are separated by ':'"),
+                Unexpected::ExpectedEnd => {
+                    Some("a document holds a single value; wrap several values in an array")
+                }
+                _ if offending == Some('\'') => Some("JSON strings must use double quotes"),
+                Unexpected::ExpectedKey(_) => Some("object keys must be double-quoted strings"),
+                _ if offending.is_some_and(char::is_whitespace) => {
+                    Some("only space, tab, newline and carriage return separate JSON tokens")
+                }
+                _ => None,
+            },
+            JsonError::UnexpectedEof(_) => {
+                Some("the input ended early; check for an unclosed '{', '[' or '\"'")
+            }
+            JsonError::InvalidNumber(NumberError::LeadingZeros, _) => {
+                Some("write the number without its leading zeros")
+            }
+            JsonError::InvalidNumber(NumberError::OutOfRange(_), _) => {
+                Some("the number is too large for an f64; enable arbitrary_precision to keep it")
+            }
+            JsonError::InvalidNumber(_, _) => {
+                Some("numbers need digits before and after '.', and after 'e'")
+            }
+            JsonError::InvalidEscapeSequence(_, _) => {
+                Some("valid escapes are \\\" \\\\ \\/ \\b \\f \\n \\r \\t and \\uXXXX")
+            }
+            JsonError::InvalidUnicodeSequence(_, _) => {
+                Some("\\u must be followed by four hexadecimal digits")
+            }
+            JsonError::LoneSurrogate(unit, _) if *unit < 0xDC00 => {
+                Some("a high surrogate must be followed by a \\uDC00-\\uDFFF escape")
+            }
+            JsonError::LoneSurrogate(_, _) => {
+                Some("a low surrogate must follow a \\uD800-\\uDBFF escape")
+            }
+            JsonError::ControlCharacter(_, _) => {
+                Some("escape it, for example as \\n, \\t or \\u0000")
+            }
+            JsonError::DepthLimitExceeded(_, _) => {
+                Some("raise ParseOptions::max_depth to accept more deeply nested documents")
+            }
+            JsonError::LimitExceeded(_, _) => {
+                Some("raise the matching field of ParseOptions::limits to accept this document")
+            }
+            JsonError::DuplicateKey(_, _, _) => {
+                Some("remove or rename one of the members with this key")
+            }
+            JsonError::Io(_, _) => None,
+        }
+    }
+
+    /// Byte offset of the error, clamped to a char boundary inside the source.
+    fn offset(&self) -> usize {
+        let offset = self
+            .error
+            .position()
+            .offset
+            .saturating_sub(self.source_offset);
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+
+    /// Number of characters to underline, starting at the error.
+    fn span_width(&self) -> usize {
+        let rest = &self.source[self.offset()..];
+        let mut chars = rest.chars();
+
+        match chars.next() {
+            None | Some('\n' | '\r') => 1,
+            Some('"') => {
+                let mut width = 1;
+                let mut escaped = false;
+                for c in chars {
+                    if c == '\n' || c == '\r' {
+                        break;
+                    }
+                    width += 1;
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == '"' {
+                        break;
+                    }
+                }
+                width
+            }
+            Some(c) if is_word_char(c) => 1 + chars.take_while(|&c| is_word_char(c)).count(),
+            Some(_) => 1,
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '+' | '.' | '_')
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let position = self.error.position();
+        let offset = self.offset();
+
+        let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[offset..]
+            .find('\n')
+            .map_or(self.source.len(), |i| offset + i);
+        let line = self.source[line_start..line_end].trim_end_matches('\r');
+
+        let gutter = position.line.to_string();
+        let blank = " ".repeat(gutter.len());
+
+        // Mirror tabs so the caret lines up however the terminal renders them
+        let padding: String = self.source[line_start..offset]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline = "^".repeat(self.span_width());
+
+        writeln!(f, "error: {}", self.headline())?;
+        writeln!(
+            f,
+            "{}--> {}:{}:{}",
+            blank,
+            self.file_name.unwrap_or("<input>"),
+            position.line,
+            position.column
+        )?;
+        writeln!(f, "{} |", blank)?;
+        writeln!(f, "{} | {}", gutter, line)?;
+        match self.hint() {
+            Some(hint) => writeln!(f, "{} | {}{} {}", blank, padding, underline, hint),
+            None => writeln!(f, "{} | {}{}", blank, padding, underline),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::json::JsonValue;
+use crate::patch::{Patch, PatchOperation};
+use crate::pointer::escape;
+use std::fmt;
+
+/// Above this many element pairs, arrays are lined up by position instead
+/// of by their longest common subsequence, which needs a table that big.
+const ALIGN_LIMIT: usize = 1 << 22;
+
+/// One difference found by [`diff`], at the JSON Pointer `path`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A member or element that is only in the new value.
+    Added { path: String, value: JsonValue },
+    /// A member or element that is only in the old value.
+    Removed { path: String, value: JsonValue },
+    /// A value replaced by another that can't be compared member by member
+    /// or element by element.
+    Changed {
+        path: String,
+        from: JsonValue,
+        to: JsonValue,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Prints `+ path: value`, `- path: value` or `~ path: from -> to`.
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", path, value),
+            Change::Removed { path, value } => write!(f, "- {}: {}", path, value),
+            Change::Changed { path, from, to } => write!(f, "~ {}: {} -> {}", path, from, to),
+        }
+    }
+}
+
+/// The differences between two values, made by [`diff`].
+///
+/// The changes are in the order a patch would make them, and each path is
+/// where its change happens in the value as it is by then. Within an array,
+/// changed elements come first, at their old indices, then removed ones,
+/// last first, at their old indices, then added ones at their new indices.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// A JSON Patch that turns the old value into the new one.
+    pub fn to_patch(&self) -> Patch {
+        let operations = self.changes.iter().map(|change| match change {
+            Change::Added { path, value } => PatchOperation::Add {
+                path: path.clone(),
+                value: value.clone(),
+            },
+            Change::Removed { path, .. } => PatchOperation::Remove { path: path.clone() },
+            Change::Changed { path, to, .. } => PatchOperation::Replace {
+                path: path.clone(),
+                value: to.clone(),
+            },
+        });
+        Patch::new(operations.collect())
+    }
+}
+
+/// Prints one change per line.
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two values, member by member and element by element.
+///
+/// Arrays are lined up by their longest common subsequence, so inserting or
+/// removing an element shows up as just that. Elements left over on both
+/// sides between two that line up are compared in turn.
+///
+/// ```
+/// use json_parser::{diff, parse_json};
+///
+/// let old = parse_json(r#"{"name": "a", "tags": ["x", "y"], "n": 1}"#).unwrap();
+/// let new = parse_json(r#"{"name": "b", "tags": ["w", "x", "y"]}"#).unwrap();
+/// let changes = diff(&old, &new);
+/// assert_eq!(changes.to_string(), "- /n: 1\n~ /name: \"a\" -> \"b\"\n+ /tags/0: \"w\"");
+///
+/// let mut value = old.clone();
+/// changes.to_patch().apply(&mut value).unwrap();
+/// assert_eq!(value, new);
+/// ```
+pub fn diff(from: &JsonValue, to: &JsonValue) -> Diff {
+    let mut changes = Vec::new();
+    let mut path = String::new();
+    compare(from, to, &mut path, &mut changes);
+    Diff { changes }
+}
+
+/// Add the changes between `from` and `to` at `path`, leaving `path` as it
+/// was.
+fn compare(from: &JsonValue, to: &JsonValue, path: &mut String, changes: &mut Vec<Change>) {
+    match (from, to) {
+        (JsonValue::Object(old), JsonValue::Object(new)) => {
+            for (key, value) in old {
+                if !new.contains_key(key) {
+                    changes.push(Change::Removed {
+                        path: child(path, key),
+                        value: value.clone(),
+                    });
+                }
+            }
+            for (key, value) in new {
+                match old.get(key) {
+                    Some(old) => {
+                        let len = path.len();
+                        path.push('/');
+                        path.push_str(&escape(key));
+                        compare(old, value, path, changes);
+                        path.truncate(len);
+                    }
+                    None => changes.push(Change::Added {
+                        path: child(path, key),
+                        value: value.clone(),
+                    }),
+                }
+            }
+        }
+        (JsonValue::Array(old), JsonValue::Array(new)) => compare_arrays(old, new, path, changes),
+        _ if from == to => {}
+        _ => changes.push(Change::Changed {
+            path: path.clone(),
+            from: from.clone(),
+            to: to.clone(),
+        }),
+    }
+}
+
+fn compare_arrays(
+    old: &[JsonValue],
+    new: &[JsonValue],
+    path: &mut String,
+    changes: &mut Vec<Change>,
+) {
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    // One past the end of each side closes the last gap
+    let matched = align(old, new).into_iter().chain([(old.len(), new.len())]);
+    for (next_i, next_j) in matched {
+        // Pair up what's left between matches and compare those in place
+        let paired = (next_i - i).min(next_j - j);
+        for k in 0..paired {
+            let len = path.len();
+            path.push('/');
+            path.push_str(&(i + k).to_string());
+            compare(&old[i + k], &new[j + k], path, changes);
+            path.truncate(len);
+        }
+        removed.extend(i + paired..next_i);
+        added.extend(j + paired..next_j);
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+
+    for &index in removed.iter().rev() {
+        changes.push(Change::Removed {
+            path: child(path, &index.to_string()),
+            value: old[index].clone(),
+        });
+    }
+    for &index in &added {
+        changes.push(Change::Added {
+            path: child(path, &index.to_string()),
+            value: new[index].clone(),
+        });
+    }
+}
+
+/// The index pairs of equal elements in the longest common subsequence of
+/// `old` and `new`, in order.
+fn align(old: &[JsonValue], new: &[JsonValue]) -> Vec<(usize, usize)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (rows, columns) = (old.len() - prefix - suffix, new.len() - prefix - suffix);
+
+    let mut pairs: Vec<_> = (0..prefix).map(|k| (k, k)).collect();
+    if rows > 0 && columns > 0 && rows.saturating_mul(columns) <= ALIGN_LIMIT {
+        let old = &old[prefix..prefix + rows];
+        let new = &new[prefix..prefix + columns];
+        // lengths[r * (columns + 1) + c]: the LCS length of old[r..] and new[c..]
+        let width = columns + 1;
+        let mut lengths = vec![0u32; (rows + 1) * width];
+        for r in (0..rows).rev() {
+            for c in (0..columns).rev() {
+                lengths[r * width + c] = if old[r] == new[c] {
+                    lengths[(r + 1) * width + c + 1] + 1
+                } else {
+                    lengths[(r + 1) * width + c].max(lengths[r * width + c + 1])
+                };
+            }
+        }
+        let (mut r, mut c) = (0, 0);
+        while r < rows && c < columns {
+            if old[r] == new[c] {
+                pairs.push((prefix + r, prefix + c));
+                r += 1;
+                c += 1;
+            } else if lengths[(r + 1) * width + c] >= lengths[r * width + c + 1] {
+                r += 1;
+            } else {
+                c += 1;
+            }
+        }
+    }
+    let (old_end, new_end) = (prefix + rows, prefix + columns);
+    pairs.extend((0..suffix).map(|k| (old_end + k, new_end + k)));
+    pairs
+}
+
+fn child(path: &str, token: &str) -> String {
+    format!("{}/{}", path, escape(token))
+}
@@ -0,0 +1,529 @@
+use crate::error::{JsonError, Position, Result};
+use crate::events::{EventParser, Step};
+use crate::json::JsonValue;
+use crate::lexer::Token;
+use crate::map::Map;
+use crate::number::Number;
+use crate::options::{DuplicateKeys, ParseOptions};
+use crate::source::StrSource;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+
+/// Objects with more than this many members get a hash index while they are
+/// parsed, to find duplicate keys.
+const INDEX_THRESHOLD: usize = 8;
+
+/// A parsed document whose nodes and strings all live in a few flat
+/// buffers, navigated through [`Node`] handles.
+///
+/// Parsing into a `Document` allocates nothing per node. Parsing into the
+/// same `Document` again reuses its buffers, so once they have grown to fit
+/// the documents being read, parsing allocates nothing at all.
+///
+/// ```
+/// use json_parser::Document;
+///
+/// let mut document = Document::new();
+/// for input in [r#"{"id": 1, "tags": ["a", "b"]}"#, r#"{"id": 2, "tags": []}"#] {
+///     let root = document.parse(input).unwrap();
+///     let tags = root.get("tags").unwrap();
+///     println!("{}: {} tags", root.get("id").unwrap().as_number().unwrap(), tags.len());
+/// }
+/// ```
+#[derive(Default)]
+pub struct Document {
+    slots: Vec<Slot>,
+    /// The elements of every array, each array's in one run.
+    elements: Vec<usize>,
+    /// The members of every object, each object's in one run.
+    members: Vec<Member>,
+    /// The text of every string and key.
+    strings: String,
+    root: Option<usize>,
+    scratch: Scratch,
+}
+
+/// A node of a document: a scalar, or where a container's children are.
+enum Slot {
+    Null,
+    Boolean(bool),
+    Number(Number),
+    String(Span),
+    Array(Span),
+    Object(Span),
+}
+
+/// A run of bytes in `strings`, elements in `elements` or members in
+/// `members`.
+#[derive(Clone, Copy)]
+struct Span {
+    start: usize,
+    len: usize,
+}
+
+impl Span {
+    fn range(self) -> std::ops::Range<usize> {
+        self.start..self.start + self.len
+    }
+}
+
+struct Member {
+    key: Span,
+    value: usize,
+}
+
+/// What a parse keeps track of on its way through the document, kept
+/// between parses so its buffers are reused too.
+#[derive(Default)]
+struct Scratch {
+    /// Containers we are inside of, innermost last.
+    stack: Vec<Frame>,
+    /// Elements of the open arrays, each array's after its parent's.
+    elements: Vec<usize>,
+    /// Members of the open objects, each object's after its parent's.
+    members: Vec<PendingMember>,
+    /// Values `DuplicateKeys::CollectAll` has gathered for members of the
+    /// open objects, after the first value of each.
+    collected: Vec<(usize, usize)>,
+    /// Where the members of large open objects are, by depth and key hash.
+    index: HashMap<(usize, u64), usize>,
+    hasher: RandomState,
+}
+
+enum Frame {
+    Array {
+        elements: usize,
+    },
+    Object {
+        members: usize,
+        collected: usize,
+        /// The key of the member whose value is being parsed, and where it
+        /// is.
+        key: Option<(Span, Position)>,
+        indexed: bool,
+    },
+}
+
+struct PendingMember {
+    key: Span,
+    position: Position,
+    value: usize,
+    /// Later values for this key have been collected into an array.
+    collected: bool,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Document::default()
+    }
+
+    /// Parse `input`, replacing whatever the document held before.
+    pub fn parse(&mut self, input: &str) -> Result<Node<'_>> {
+        self.parse_with(input, ParseOptions::default())
+    }
+
+    /// Parse `input` using the given options, replacing whatever the
+    /// document held before.
+    ///
+    /// On error the document is left empty.
+    pub fn parse_with(&mut self, input: &str, options: ParseOptions) -> Result<Node<'_>> {
+        self.clear();
+        if let Err(e) = self.build(EventParser::with_options(input, options), options) {
+            self.clear();
+            return Err(e);
+        }
+        Ok(self.root().expect("a parsed document has a root"))
+    }
+
+    /// The top-level value, if a document has been parsed.
+    pub fn root(&self) -> Option<Node<'_>> {
+        self.root.map(|index| Node {
+            document: self,
+            index,
+        })
+    }
+
+    /// Empty the document, keeping its buffers for the next parse.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.elements.clear();
+        self.members.clear();
+        self.strings.clear();
+        self.root = None;
+        let scratch = &mut self.scratch;
+        scratch.stack.clear();
+        scratch.elements.clear();
+        scratch.members.clear();
+        scratch.collected.clear();
+        scratch.index.clear();
+    }
+
+    fn build<'a>(
+        &mut self,
+        mut events: EventParser<'a, StrSource<'a>>,
+        options: ParseOptions,
+    ) -> Result<()> {
+        while let Some((step, position)) = events.next_step()? {
+            let value = match step {
+                Step::StartObject => {
+                    self.scratch.stack.push(Frame::Object {
+                        members: self.scratch.members.len(),
+                        collected: self.scratch.collected.len(),
+                        key: None,
+                        indexed: false,
+                    });
+                    continue;
+                }
+                Step::StartArray => {
+                    self.scratch.stack.push(Frame::Array {
+                        elements: self.scratch.elements.len(),
+                    });
+                    continue;
+                }
+                Step::Key => {
+                    let key = match events.take_held() {
+                        Some(Token::String(key)) => self.push_str(&key),
+                        _ => unreachable!("keys are always strings"),
+                    };
+                    if let Some(Frame::Object { key: pending, .. }) = self.scratch.stack.last_mut()
+                    {
+                        *pending = Some((key, position));
+                    }
+                    continue;
+                }
+                Step::Value => {
+                    let slot = match events.take_held() {
+                        Some(Token::Boolean(b)) => Slot::Boolean(b),
+                        Some(Token::Number(n)) => Slot::Number(n),
+                        Some(Token::String(s)) => Slot::String(self.push_str(&s)),
+                        _ => Slot::Null,
+                    };
+                    self.push_slot(slot)
+                }
+                Step::EndArray => self.close_array(),
+                Step::EndObject => self.close_object(),
+            };
+            self.place(value, options.duplicate_keys)?;
+        }
+        Ok(())
+    }
+
+    fn push_str(&mut self, text: &str) -> Span {
+        let start = self.strings.len();
+        self.strings.push_str(text);
+        Span {
+            start,
+            len: text.len(),
+        }
+    }
+
+    fn push_slot(&mut self, slot: Slot) -> usize {
+        self.slots.push(slot);
+        self.slots.len() - 1
+    }
+
+    fn text(&self, span: Span) -> &str {
+        &self.strings[span.range()]
+    }
+
+    fn hash(&self, key: Span) -> u64 {
+        self.scratch.hasher.hash_one(self.text(key))
+    }
+
+    /// Hand the finished value at `value` to its container.
+    fn place(&mut self, value: usize, duplicate_keys: DuplicateKeys) -> Result<()> {
+        let depth = self.scratch.stack.len();
+        let (start, key, indexed) = match self.scratch.stack.last_mut() {
+            None => {
+                self.root = Some(value);
+                return Ok(());
+            }
+            Some(Frame::Array { .. }) => {
+                self.scratch.elements.push(value);
+                return Ok(());
+            }
+            Some(Frame::Object {
+                members,
+                key,
+                indexed,
+                ..
+            }) => (
+                *members,
+                key.take().expect("a key comes before every member"),
+                *indexed,
+            ),
+        };
+        let (key, position) = key;
+
+        let Some(existing) = self.find_member(depth, start, indexed, key) else {
+            let member = self.scratch.members.len();
+            if indexed {
+                let hash = self.hash(key);
+                self.scratch.index.entry((depth, hash)).or_insert(member);
+            }
+            self.scratch.members.push(PendingMember {
+                key,
+                position,
+                value,
+                collected: false,
+            });
+            if !indexed && member + 1 - start > INDEX_THRESHOLD {
+                self.index_members(depth, start);
+                if let Some(Frame::Object { indexed, .. }) = self.scratch.stack.last_mut() {
+                    *indexed = true;
+                }
+            }
+            return Ok(());
+        };
+
+        let member = &mut self.scratch.members[existing];
+        match duplicate_keys {
+            DuplicateKeys::Error => {
+                let key = self.strings[key.range()].to_string();
+                return Err(JsonError::DuplicateKey(key, member.position, position));
+            }
+            DuplicateKeys::FirstWins => {}
+            DuplicateKeys::LastWins => member.value = value,
+            DuplicateKeys::CollectAll => {
+                member.collected = true;
+                self.scratch.collected.push((existing, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Where among the open object's members the one with `key` is.
+    fn find_member(&self, depth: usize, start: usize, indexed: bool, key: Span) -> Option<usize> {
+        let text = self.text(key);
+        if indexed {
+            let found = *self.scratch.index.get(&(depth, self.hash(key)))?;
+            // Keys whose hashes collide share an entry
+            if self.text(self.scratch.members[found].key) == text {
+                return Some(found);
+            }
+        }
+        self.scratch.members[start..]
+            .iter()
+            .position(|member| self.text(member.key) == text)
+            .map(|i| start + i)
+    }
+
+    fn index_members(&mut self, depth: usize, start: usize) {
+        for member in start..self.scratch.members.len() {
+            let hash = self.hash(self.scratch.members[member].key);
+            self.scratch.index.entry((depth, hash)).or_insert(member);
+        }
+    }
+
+    fn close_array(&mut self) -> usize {
+        let Some(Frame::Array { elements: start }) = self.scratch.stack.pop() else {
+            unreachable!("events are balanced");
+        };
+        let span = Span {
+            start: self.elements.len(),
+            len: self.scratch.elements.len() - start,
+        };
+        self.elements.extend(self.scratch.elements.drain(start..));
+        self.push_slot(Slot::Array(span))
+    }
+
+    fn close_object(&mut self) -> usize {
+        let depth = self.scratch.stack.len();
+        let Some(Frame::Object {
+            members: start,
+            collected,
+            indexed,
+            ..
+        }) = self.scratch.stack.pop()
+        else {
+            unreachable!("events are balanced");
+        };
+
+        if indexed {
+            for member in start..self.scratch.members.len() {
+                let hash = self.hash(self.scratch.members[member].key);
+                self.scratch.index.remove(&(depth, hash));
+            }
+        }
+
+        // Gather the values collected for each key into an array
+        for member in start..self.scratch.members.len() {
+            if !self.scratch.members[member].collected {
+                continue;
+            }
+            let first = self.scratch.members[member].value;
+            let span = Span {
+                start: self.elements.len(),
+                len: 0,
+            };
+            self.elements.push(first);
+            for &(owner, value) in &self.scratch.collected[collected..] {
+                if owner == member {
+                    self.elements.push(value);
+                }
+            }
+            let len = self.elements.len() - span.start;
+            self.scratch.members[member].value = self.push_slot(Slot::Array(Span { len, ..span }));
+        }
+        self.scratch.collected.truncate(collected);
+
+        let span = Span {
+            start: self.members.len(),
+            len: self.scratch.members.len() - start,
+        };
+        self.members
+            .extend(self.scratch.members.drain(start..).map(|member| Member {
+                key: member.key,
+                value: member.value,
+            }));
+        self.push_slot(Slot::Object(span))
+    }
+}
+
+/// A handle to a value in a [`Document`].
+///
+/// Handles are small and `Copy`; navigating with them neither allocates nor
+/// copies anything out of the document.
+#[derive(Clone, Copy)]
+pub struct Node<'d> {
+    document: &'d Document,
+    index: usize,
+}
+
+impl<'d> Node<'d> {
+    fn slot(&self) -> &'d Slot {
+        &self.document.slots[self.index]
+    }
+
+    fn node(&self, index: usize) -> Node<'d> {
+        Node {
+            document: self.document,
+            index,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.slot(), Slot::Null)
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self.slot(), Slot::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self.slot(), Slot::Object(_))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.slot() {
+            Slot::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&'d Number> {
+        match self.slot() {
+            Slot::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'d str> {
+        match self.slot() {
+            Slot::String(span) => Some(self.document.text(*span)),
+            _ => None,
+        }
+    }
+
+    /// How many elements or members an array or object has; zero for
+    /// anything else.
+    pub fn len(&self) -> usize {
+        match self.slot() {
+            Slot::Array(span) | Slot::Object(span) => span.len,
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The element of an array at `index`.
+    pub fn element(&self, index: usize) -> Option<Node<'d>> {
+        match self.slot() {
+            Slot::Array(span) if index < span.len => {
+                Some(self.node(self.document.elements[span.start + index]))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of an object's member with `key`.
+    ///
+    /// Members are searched in order, so this suits the small objects
+    /// documents are usually made of.
+    pub fn get(&self, key: &str) -> Option<Node<'d>> {
+        self.members()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The elements of an array, in order; nothing for anything else.
+    pub fn elements(&self) -> impl Iterator<Item = Node<'d>> + 'd {
+        let document = self.document;
+        let elements = match self.slot() {
+            Slot::Array(span) => &document.elements[span.range()],
+            _ => &[],
+        };
+        elements.iter().map(move |&index| Node { document, index })
+    }
+
+    /// The keys and values of an object's members, in order; nothing for
+    /// anything else.
+    pub fn members(&self) -> impl Iterator<Item = (&'d str, Node<'d>)> + 'd {
+        let document = self.document;
+        let members = match self.slot() {
+            Slot::Object(span) => &document.members[span.range()],
+            _ => &[],
+        };
+        members.iter().map(move |member| {
+            let value = Node {
+                document,
+                index: member.value,
+            };
+            (document.text(member.key), value)
+        })
+    }
+
+    /// Copy the value out of the document.
+    pub fn to_value(&self) -> JsonValue {
+        match self.slot() {
+            Slot::Null => JsonValue::Null,
+            Slot::Boolean(b) => JsonValue::Boolean(*b),
+            Slot::Number(n) => JsonValue::Number(n.clone()),
+            Slot::String(span) => JsonValue::String(self.document.text(*span).to_string()),
+            Slot::Array(_) => JsonValue::Array(self.elements().map(|e| e.to_value()).collect()),
+            Slot::Object(span) => {
+                let mut object = Map::with_capacity(span.len);
+                for (key, value) in self.members() {
+                    object.insert(key.to_string(), value.to_value());
+                }
+                JsonValue::Object(object)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_value(), f)
+    }
+}
+
+impl From<Node<'_>> for JsonValue {
+    fn from(node: Node<'_>) -> Self {
+        node.to_value()
+    }
+}
@@ -1,29 +1,400 @@
 use std::error::Error;
 use std::fmt;
 
+/// A location in the source text.
+///
+/// `offset` is a byte offset from the start of the input, while `line` and
+/// `column` are 1-based and count characters rather than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// The position of the first character of the input.
+    pub fn start() -> Self {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Advance the position past `c`.
+    pub fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::start()
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
 #[derive(Debug)]
 pub enum JsonError {
-    UnexpectedToken(String),
-    UnexpectedEof,
-    InvalidNumber(String),
-    InvalidEscapeSequence(String),
-    InvalidUnicodeSequence(String),
+    UnexpectedToken(Unexpected, Position),
+    UnexpectedEof(Position),
+    InvalidNumber(NumberError, Position),
+    InvalidEscapeSequence(String, Position),
+    InvalidUnicodeSequence(String, Position),
+    /// A `\uXXXX` escape for half of a UTF-16 surrogate pair without the
+    /// other half.
+    LoneSurrogate(u16, Position),
+    /// A character below U+0020 written into a string without escaping it.
+    ControlCharacter(char, Position),
+    /// Arrays and objects nested deeper than `ParseOptions::max_depth`. Holds
+    /// the depth the offending container would have opened at.
+    DepthLimitExceeded(usize, Position),
+    /// The document broke one of the `ParseOptions::limits`.
+    LimitExceeded(Limit, Position),
+    /// An object key that appeared earlier in the same object, with the
+    /// positions of the first and the repeated key.
+    DuplicateKey(String, Position, Position),
+    /// Reading the input failed, or it was not valid UTF-8.
+    Io(std::io::Error, Position),
+}
+
+/// What a `JsonError::UnexpectedToken` found out of place. Where it names
+/// what was found, that is a description such as `string "b"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unexpected {
+    /// A character that can't start a token.
+    Character(char),
+    ExpectedValue(String),
+    ExpectedKey(String),
+    ExpectedColon(String),
+    /// Neither `,` nor `}` after an object member.
+    ExpectedObjectSeparator(String),
+    /// Neither `,` nor `]` after an array element.
+    ExpectedArraySeparator(String),
+    /// More input after the document's value.
+    ExpectedEnd,
+    TrailingCommaInObject,
+    TrailingCommaInArray,
+}
+
+impl fmt::Display for Unexpected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unexpected::Character(c) => write!(f, "{}", c),
+            Unexpected::ExpectedValue(found) => write!(f, "Expected a value, got {}", found),
+            Unexpected::ExpectedKey(found) => write!(f, "Expected string key, got {}", found),
+            Unexpected::ExpectedColon(found) => {
+                write!(f, "Expected ':' after object key, got {}", found)
+            }
+            Unexpected::ExpectedObjectSeparator(found) => {
+                write!(f, "Expected ',' or '}}' after object value, got {}", found)
+            }
+            Unexpected::ExpectedArraySeparator(found) => {
+                write!(f, "Expected ',' or ']' after array element, got {}", found)
+            }
+            Unexpected::ExpectedEnd => write!(f, "Expected end of input"),
+            Unexpected::TrailingCommaInObject => write!(f, "Trailing comma in object"),
+            Unexpected::TrailingCommaInArray => write!(f, "Trailing comma in array"),
+        }
+    }
+}
+
+/// What is wrong with a number literal, for `JsonError::InvalidNumber`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NumberError {
+    /// A leading zero, such as in `012`, in strict mode.
+    LeadingZeros,
+    /// No digit where one is required, such as in `1.` or `-`.
+    MissingDigit,
+    /// The literal, too large for an `f64`, in strict mode.
+    OutOfRange(String),
+    /// A literal that could not be read as a number.
+    Malformed(String),
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumberError::LeadingZeros => write!(f, "Leading zeros are not allowed"),
+            NumberError::MissingDigit => write!(f, "Expected at least one digit"),
+            NumberError::OutOfRange(literal) => write!(f, "{} is out of range", literal),
+            NumberError::Malformed(literal) => write!(f, "{}", literal),
+        }
+    }
+}
+
+/// One of the `ParseLimits`, with the value that was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    InputBytes(usize),
+    StringBytes(usize),
+    ArrayLength(usize),
+    ObjectMembers(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::InputBytes(n) => write!(f, "input size of {} bytes", n),
+            Limit::StringBytes(n) => write!(f, "string length of {} bytes", n),
+            Limit::ArrayLength(n) => write!(f, "array length of {} elements", n),
+            Limit::ObjectMembers(n) => write!(f, "object size of {} members", n),
+        }
+    }
+}
+
+impl JsonError {
+    /// Where in the input the error was detected.
+    pub fn position(&self) -> Position {
+        match self {
+            JsonError::UnexpectedToken(_, pos)
+            | JsonError::UnexpectedEof(pos)
+            | JsonError::InvalidNumber(_, pos)
+            | JsonError::InvalidEscapeSequence(_, pos)
+            | JsonError::InvalidUnicodeSequence(_, pos)
+            | JsonError::LoneSurrogate(_, pos)
+            | JsonError::ControlCharacter(_, pos)
+            | JsonError::DepthLimitExceeded(_, pos)
+            | JsonError::LimitExceeded(_, pos)
+            | JsonError::DuplicateKey(_, _, pos)
+            | JsonError::Io(_, pos) => *pos,
+        }
+    }
+
+    /// The error description without the trailing position.
+    pub fn message(&self) -> String {
+        match self {
+            JsonError::UnexpectedToken(token, _) => format!("Unexpected token: {}", token),
+            JsonError::UnexpectedEof(_) => "Unexpected end of file".to_string(),
+            JsonError::InvalidNumber(msg, _) => format!("Invalid number: {}", msg),
+            JsonError::InvalidEscapeSequence(seq, _) => {
+                format!("Invalid escape sequence: {}", seq)
+            }
+            JsonError::InvalidUnicodeSequence(seq, _) => {
+                format!("Invalid unicode sequence: {}", seq)
+            }
+            JsonError::LoneSurrogate(unit, _) => {
+                format!("Unpaired UTF-16 surrogate: \\u{:04X}", unit)
+            }
+            JsonError::ControlCharacter(c, _) => {
+                format!("Unescaped control character in string: U+{:04X}", *c as u32)
+            }
+            JsonError::DepthLimitExceeded(depth, _) => {
+                format!("Nesting depth limit exceeded: {} levels deep", depth)
+            }
+            JsonError::LimitExceeded(limit, _) => format!("Exceeded maximum {}", limit),
+            JsonError::DuplicateKey(key, first, _) => {
+                format!("Duplicate key {:?}, first defined at {}", key, first)
+            }
+            JsonError::Io(e, _) => format!("I/O error: {}", e),
+        }
+    }
 }
 
 impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message(), self.position())
+    }
+}
+
+impl Error for JsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JsonError::Io(e, _) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Errors raised while writing a `JsonValue` out as JSON text.
+#[derive(Debug)]
+pub enum SerializeError {
+    /// NaN and the infinities have no JSON representation.
+    NonFiniteNumber(f64),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for SerializeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            JsonError::UnexpectedToken(token) => write!(f, "Unexpected token: {}", token),
-            JsonError::UnexpectedEof => write!(f, "Unexpected end of file"),
-            JsonError::InvalidNumber(msg) => write!(f, "Invalid number: {}", msg),
-            JsonError::InvalidEscapeSequence(seq) => write!(f, "Invalid escape sequence: {}", seq),
-            JsonError::InvalidUnicodeSequence(seq) => {
-                write!(f, "Invalid unicode sequence: {}", seq)
+            SerializeError::NonFiniteNumber(n) => {
+                write!(f, "Cannot serialize non-finite number: {}", n)
             }
+            SerializeError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for SerializeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SerializeError::Io(e) => Some(e),
+            _ => None,
         }
     }
 }
 
-impl Error for JsonError {}
+/// Errors raised following a JSON Pointer into a `JsonValue`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerError {
+    /// The pointer is not empty and does not start with `/`, or has a `~`
+    /// that is not followed by `0` or `1`.
+    InvalidPointer(String),
+    /// An object has no member with this key.
+    MissingKey(String),
+    /// An array index, with the length of the array, that is past its end.
+    IndexOutOfRange(usize, usize),
+    /// A reference token used on an array that is not a valid index.
+    InvalidIndex(String),
+    /// A reference token used on a value that is neither an array nor an
+    /// object.
+    NotAContainer(String),
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PointerError::InvalidPointer(pointer) => {
+                write!(f, "Invalid JSON pointer: {:?}", pointer)
+            }
+            PointerError::MissingKey(key) => write!(f, "No member named {:?}", key),
+            PointerError::IndexOutOfRange(index, len) => write!(
+                f,
+                "Index {} is out of range for an array of length {}",
+                index, len
+            ),
+            PointerError::InvalidIndex(token) => write!(f, "Invalid array index: {:?}", token),
+            PointerError::NotAContainer(token) => write!(
+                f,
+                "Cannot look up {:?} in a value that is not an array or object",
+                token
+            ),
+        }
+    }
+}
+
+impl Error for PointerError {}
+
+/// Errors raised reading or applying a JSON Patch. Those raised applying one
+/// name the index of the failing operation and the pointer it failed at.
+#[derive(Debug)]
+pub enum PatchError {
+    /// The patch is not valid JSON.
+    Parse(JsonError),
+    /// The patch is not an array of operations.
+    NotAnArray,
+    /// An operation that is malformed or can't be carried out, with its
+    /// index and what is wrong with it.
+    InvalidOperation(usize, String),
+    /// An operation whose pointer leads nowhere usable.
+    Pointer(usize, String, PointerError),
+    /// A `test` operation whose value did not match.
+    TestFailed(usize, String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::Parse(e) => write!(f, "Invalid patch: {}", e),
+            PatchError::NotAnArray => write!(f, "A patch must be an array of operations"),
+            PatchError::InvalidOperation(index, message) => {
+                write!(f, "Invalid operation {}: {}", index, message)
+            }
+            PatchError::Pointer(index, pointer, e) => {
+                write!(f, "Operation {} failed at {:?}: {}", index, pointer, e)
+            }
+            PatchError::TestFailed(index, pointer) => {
+                write!(
+                    f,
+                    "Operation {} failed: the value at {:?} does not match",
+                    index, pointer
+                )
+            }
+        }
+    }
+}
+
+impl Error for PatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PatchError::Parse(e) => Some(e),
+            PatchError::Pointer(_, _, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A JSONPath query that could not be parsed, with the byte offset in the
+/// query where the problem was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid JSONPath: {} at offset {}",
+            self.message, self.offset
+        )
+    }
+}
+
+impl Error for PathError {}
+
+/// A JSON Schema that could not be compiled, with the JSON Pointer to the
+/// part of the schema at fault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid schema at {:?}: {}",
+            self.schema_path, self.message
+        )
+    }
+}
+
+impl Error for SchemaError {}
+
+/// One way a value fails a JSON Schema: the JSON Pointer to the offending
+/// part of the value, and to the schema keyword it fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid value at {:?}: {} (schema {:?})",
+            self.instance_path, self.message, self.schema_path
+        )
+    }
+}
+
+impl Error for ValidationError {}
 
 pub type Result<T> = std::result::Result<T, JsonError>;
@@ -0,0 +1,711 @@
+use crate::error::{JsonError, Limit, Position, Result, Unexpected};
+use crate::json::JsonValue;
+use crate::lexer::{Lexer, Token};
+use crate::number::Number;
+use crate::options::ParseOptions;
+use crate::source::{ReaderSource, SliceSource, Source, StrSource};
+use std::io::Read;
+
+/// One step through a JSON document, as produced by [`EventParser`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// The key of the object member whose value comes next.
+    Key(&'a str),
+    Value(Scalar<'a>),
+}
+
+/// A JSON value that is not an array or an object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar<'a> {
+    Null,
+    Boolean(bool),
+    Number(&'a Number),
+    String(&'a str),
+}
+
+impl From<Scalar<'_>> for JsonValue {
+    fn from(scalar: Scalar<'_>) -> Self {
+        match scalar {
+            Scalar::Null => JsonValue::Null,
+            Scalar::Boolean(b) => JsonValue::Boolean(b),
+            Scalar::Number(n) => JsonValue::Number(n.clone()),
+            Scalar::String(s) => JsonValue::String(s.to_string()),
+        }
+    }
+}
+
+/// A pull parser that walks a document one [`Event`] at a time, without
+/// building a tree.
+///
+/// ```
+/// use json_parser::{Event, EventParser, Scalar};
+///
+/// let mut events = EventParser::new(r#"{"id": 7, "tags": ["a"]}"#);
+/// let mut strings = 0;
+/// while let Some((event, _position)) = events.next_event().unwrap() {
+///     if let Event::Value(Scalar::String(_)) = event {
+///         strings += 1;
+///     }
+/// }
+/// assert_eq!(strings, 1);
+/// ```
+pub struct EventParser<'a, S> {
+    lexer: Lexer<S>,
+    current_token: Option<Token<'a>>,
+    current_position: Position,
+    /// When set, errors are collected here instead of aborting the parse.
+    errors: Option<Vec<JsonError>>,
+    /// The current token is a stand-in for one the lexer rejected.
+    placeholder: bool,
+    options: ParseOptions,
+    /// Arrays and objects we are inside of, innermost last.
+    stack: Vec<Container>,
+    state: State,
+    /// The token the latest `Key` or `Value` event borrows from.
+    held: Option<Token<'a>>,
+    /// The current token has been dealt with, and the next is yet to be read.
+    consumed: bool,
+}
+
+/// An open array or object, with how many elements or members it has had.
+#[derive(Clone, Copy)]
+enum Container {
+    Array(usize),
+    Object(usize),
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    /// A value comes next.
+    Value,
+    /// An object has just been opened.
+    FirstMember,
+    /// An array has just been opened.
+    FirstElement,
+    /// A value has just ended.
+    AfterValue,
+    /// The document is over.
+    Done,
+}
+
+/// Everything `next_event` changes besides how far the source has been read,
+/// so an event cut short by the end of the input so far can be taken back.
+pub(crate) struct Checkpoint<'a> {
+    lexer_position: Position,
+    token_start: Position,
+    current_token: Option<Token<'a>>,
+    current_position: Position,
+    placeholder: bool,
+    stack: Vec<Container>,
+    state: State,
+    consumed: bool,
+    errors: usize,
+}
+
+/// An `Event` before it borrows its data from the held token.
+pub(crate) enum Step {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key,
+    Value,
+}
+
+impl<'a> EventParser<'a, StrSource<'a>> {
+    pub fn new(input: &'a str) -> Self {
+        EventParser::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
+        EventParser::with_source(StrSource::new(input), options)
+    }
+}
+
+impl<'a> EventParser<'a, SliceSource<'a>> {
+    /// Read events from UTF-8 bytes, checking strings as they are read.
+    pub fn from_slice(input: &'a [u8], options: ParseOptions) -> Self {
+        EventParser::with_source(SliceSource::new(input), options)
+    }
+}
+
+impl<R: Read> EventParser<'_, ReaderSource<R>> {
+    /// Read events from UTF-8 read from `reader`, a buffer at a time.
+    pub fn from_reader(reader: R, options: ParseOptions) -> Self {
+        EventParser::with_source(ReaderSource::new(reader), options)
+    }
+}
+
+impl<'a, S: Source<'a>> EventParser<'a, S> {
+    pub fn with_source(input: S, options: ParseOptions) -> Self {
+        EventParser {
+            lexer: Lexer::with_source(input, options),
+            current_token: None,
+            current_position: Position::start(),
+            errors: None,
+            placeholder: false,
+            options,
+            stack: Vec::new(),
+            state: State::Value,
+            held: None,
+            consumed: true,
+        }
+    }
+
+    /// The next event and where in the input it starts, or `None` once the
+    /// whole document has been read.
+    ///
+    /// Every `StartObject` and `StartArray` is matched by an `EndObject` or
+    /// `EndArray`. After an error, there are no more events.
+    pub fn next_event(&mut self) -> Result<Option<(Event<'_>, Position)>> {
+        let step = self.next_step()?;
+        Ok(step.map(|(step, position)| (self.event(step), position)))
+    }
+
+    /// Like `next_event`, leaving the event to be looked at with `event`.
+    pub(crate) fn next_step(&mut self) -> Result<Option<(Step, Position)>> {
+        let step = self.step();
+        if step.is_err() {
+            self.state = State::Done;
+        }
+        step
+    }
+
+    /// Read the next event and, if it opens an array or object, every event
+    /// up to the one that closes it, returning the first.
+    ///
+    /// The strings and numbers skipped over are checked but not decoded.
+    pub(crate) fn skip_value(&mut self) -> Result<Option<(Step, Position)>> {
+        self.lexer.set_skipping(true);
+        let skipped = self.skip_tokens();
+        self.lexer.set_skipping(false);
+        skipped
+    }
+
+    fn skip_tokens(&mut self) -> Result<Option<(Step, Position)>> {
+        let Some(first) = self.next_step()? else {
+            return Ok(None);
+        };
+        let mut depth = match first.0 {
+            Step::StartObject | Step::StartArray => 1usize,
+            _ => 0,
+        };
+        while depth > 0 {
+            match self.next_step()? {
+                Some((Step::StartObject | Step::StartArray, _)) => depth += 1,
+                Some((Step::EndObject | Step::EndArray, _)) => depth -= 1,
+                Some(_) => self.held = None,
+                None => break,
+            }
+        }
+        self.held = None;
+        Ok(Some(first))
+    }
+
+    /// How many bytes of the input have been read. Straight after an event,
+    /// this is where the token it ended with ends.
+    pub(crate) fn offset(&self) -> usize {
+        self.lexer.position().offset
+    }
+
+    pub(crate) fn source_mut(&mut self) -> &mut S {
+        self.lexer.source_mut()
+    }
+
+    pub(crate) fn checkpoint(&self) -> Checkpoint<'a> {
+        Checkpoint {
+            lexer_position: self.lexer.position(),
+            token_start: self.lexer.token_start(),
+            current_token: self.current_token.clone(),
+            current_position: self.current_position,
+            placeholder: self.placeholder,
+            stack: self.stack.clone(),
+            state: self.state,
+            consumed: self.consumed,
+            errors: self.errors.as_ref().map_or(0, Vec::len),
+        }
+    }
+
+    /// Go back to `checkpoint`. The source must be rewound to match.
+    pub(crate) fn restore(&mut self, checkpoint: Checkpoint<'a>) {
+        self.lexer
+            .rewind(checkpoint.lexer_position, checkpoint.token_start);
+        self.current_token = checkpoint.current_token;
+        self.current_position = checkpoint.current_position;
+        self.placeholder = checkpoint.placeholder;
+        self.stack = checkpoint.stack;
+        self.state = checkpoint.state;
+        self.consumed = checkpoint.consumed;
+        if let Some(errors) = &mut self.errors {
+            errors.truncate(checkpoint.errors);
+        }
+    }
+
+    /// Collect errors instead of failing on them, standing in `null` for
+    /// values that could not be read.
+    pub(crate) fn recover_errors(&mut self) {
+        self.errors = Some(Vec::new());
+    }
+
+    /// The errors collected while recovering.
+    pub(crate) fn take_errors(&mut self) -> Vec<JsonError> {
+        self.errors.take().unwrap_or_default()
+    }
+
+    /// Take the token the latest `Key` or `Value` step is made of, instead
+    /// of looking at it through `event`.
+    pub(crate) fn take_held(&mut self) -> Option<Token<'a>> {
+        self.held.take()
+    }
+
+    pub(crate) fn event(&self, step: Step) -> Event<'_> {
+        match step {
+            Step::StartObject => Event::StartObject,
+            Step::EndObject => Event::EndObject,
+            Step::StartArray => Event::StartArray,
+            Step::EndArray => Event::EndArray,
+            Step::Key => match &self.held {
+                Some(Token::String(key)) => Event::Key(key),
+                _ => unreachable!("keys are always strings"),
+            },
+            Step::Value => Event::Value(match &self.held {
+                Some(Token::Boolean(b)) => Scalar::Boolean(*b),
+                Some(Token::Number(n)) => Scalar::Number(n),
+                Some(Token::String(s)) => Scalar::String(s),
+                _ => Scalar::Null,
+            }),
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<(Step, Position)>> {
+        if let State::Done = self.state {
+            return Ok(None);
+        }
+        // Reading ahead only now lets an event out before the next token is in
+        if self.consumed {
+            self.consumed = false;
+            self.advance_token()?;
+        }
+
+        loop {
+            match self.state {
+                State::Value => return self.value().map(Some),
+                State::FirstMember => {
+                    // Handle empty object
+                    if let Some(Token::RightBrace) = &self.current_token {
+                        self.consumed = true;
+                        return Ok(Some(self.close(self.current_position)));
+                    }
+                    let position = self.current_position;
+                    return Ok(Some(match self.next_member(0)? {
+                        Some(key_position) => {
+                            self.state = State::Value;
+                            (Step::Key, key_position)
+                        }
+                        None => self.close(position),
+                    }));
+                }
+                State::FirstElement => {
+                    // Handle empty array
+                    if let Some(Token::RightBracket) = &self.current_token {
+                        self.consumed = true;
+                        return Ok(Some(self.close(self.current_position)));
+                    }
+                    let max = self.options.limits.max_array_len;
+                    self.check_length(0, max, Limit::ArrayLength)?;
+                    self.state = State::Value;
+                }
+                State::AfterValue => {
+                    let position = self.current_position;
+                    match self.stack.last_mut() {
+                        None => {
+                            self.end_document()?;
+                            return Ok(None);
+                        }
+                        Some(Container::Array(len)) => {
+                            *len += 1;
+                            let len = *len;
+                            if !self.array_separator()? {
+                                return Ok(Some(self.close(position)));
+                            }
+                            let max = self.options.limits.max_array_len;
+                            self.check_length(len, max, Limit::ArrayLength)?;
+                            self.state = State::Value;
+                        }
+                        Some(Container::Object(members)) => {
+                            *members += 1;
+                            let members = *members;
+                            if self.object_separator()?
+                                && let Some(key_position) = self.next_member(members)?
+                            {
+                                self.state = State::Value;
+                                return Ok(Some((Step::Key, key_position)));
+                            }
+                            return Ok(Some(self.close(position)));
+                        }
+                    }
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// Read the start of a value: a whole scalar, or the token opening an
+    /// array or object.
+    fn value(&mut self) -> Result<(Step, Position)> {
+        let position = self.current_position;
+        self.state = State::AfterValue;
+
+        match &self.current_token {
+            Some(Token::LeftBrace | Token::LeftBracket)
+                if self.stack.len() >= self.options.max_depth =>
+            {
+                self.error(JsonError::DepthLimitExceeded(
+                    self.stack.len() + 1,
+                    position,
+                ))?;
+                self.skip_container()?;
+                self.held = Some(Token::Null);
+                Ok((Step::Value, position))
+            }
+            Some(Token::LeftBrace) => {
+                self.consumed = true;
+                self.stack.push(Container::Object(0));
+                self.state = State::FirstMember;
+                Ok((Step::StartObject, position))
+            }
+            Some(Token::LeftBracket) => {
+                self.consumed = true;
+                self.stack.push(Container::Array(0));
+                self.state = State::FirstElement;
+                Ok((Step::StartArray, position))
+            }
+            Some(token) if starts_value(token) => {
+                self.held = self.current_token.take();
+                self.consumed = true;
+                Ok((Step::Value, position))
+            }
+            Some(token) => {
+                // Leave the token for the enclosing container to resync on
+                self.error(JsonError::UnexpectedToken(
+                    Unexpected::ExpectedValue(token.to_string()),
+                    position,
+                ))?;
+                self.held = Some(Token::Null);
+                Ok((Step::Value, position))
+            }
+            None => {
+                self.error(JsonError::UnexpectedEof(position))?;
+                self.held = Some(Token::Null);
+                Ok((Step::Value, position))
+            }
+        }
+    }
+
+    /// End the innermost container.
+    fn close(&mut self, position: Position) -> (Step, Position) {
+        self.state = State::AfterValue;
+        match self.stack.pop() {
+            Some(Container::Array(_)) => (Step::EndArray, position),
+            Some(Container::Object(_)) => (Step::EndObject, position),
+            None => unreachable!("only open containers are closed"),
+        }
+    }
+
+    fn end_document(&mut self) -> Result<()> {
+        self.state = State::Done;
+
+        // Ensure we've consumed all tokens
+        if self.current_token.is_some() {
+            self.error(JsonError::UnexpectedToken(
+                Unexpected::ExpectedEnd,
+                self.current_position,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Fail with `error`, or record it and carry on when recovering.
+    pub(crate) fn error(&mut self, error: JsonError) -> Result<()> {
+        match &mut self.errors {
+            // Carrying on past a limit would defeat its purpose, and there is
+            // nothing more to read after an I/O error
+            Some(_) if matches!(error, JsonError::LimitExceeded(_, _) | JsonError::Io(_, _)) => {
+                Err(error)
+            }
+            Some(errors) => {
+                // Every open container would otherwise report the same early end
+                let repeated_eof = matches!(error, JsonError::UnexpectedEof(_))
+                    && matches!(errors.last(), Some(JsonError::UnexpectedEof(_)));
+                if !repeated_eof {
+                    errors.push(error);
+                }
+                Ok(())
+            }
+            None => Err(error),
+        }
+    }
+
+    fn advance_token(&mut self) -> Result<()> {
+        self.placeholder = false;
+        match self.lexer.next_token() {
+            Ok(token) => self.current_token = token,
+            Err(e) => {
+                let eof = matches!(e, JsonError::UnexpectedEof(_));
+                self.error(e)?;
+                // The lexer has skipped the malformed token; stand a null in for it
+                self.current_token = if eof { None } else { Some(Token::Null) };
+                self.placeholder = !eof;
+            }
+        }
+        self.current_position = self.lexer.token_start();
+        Ok(())
+    }
+
+    /// Skip tokens up to the next `,`, `}` or `]` that is not nested inside
+    /// the skipped tokens.
+    fn synchronize(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+        loop {
+            match &self.current_token {
+                None => return Ok(()),
+                Some(Token::Comma | Token::RightBrace | Token::RightBracket) if depth == 0 => {
+                    return Ok(());
+                }
+                Some(Token::LeftBrace | Token::LeftBracket) => depth += 1,
+                Some(Token::RightBrace | Token::RightBracket) => depth -= 1,
+                Some(_) => {}
+            }
+            self.advance_token()?;
+        }
+    }
+
+    /// Skip the container starting at the current token without building it.
+    fn skip_container(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+        loop {
+            match &self.current_token {
+                None => return Ok(()),
+                Some(Token::LeftBrace | Token::LeftBracket) => depth += 1,
+                Some(Token::RightBrace | Token::RightBracket) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.advance_token();
+                    }
+                }
+                Some(_) => {}
+            }
+            self.advance_token()?;
+        }
+    }
+
+    /// Fail if a container already holding `len` items can't take another,
+    /// blaming the current token.
+    fn check_length(
+        &mut self,
+        len: usize,
+        max: Option<usize>,
+        limit: fn(usize) -> Limit,
+    ) -> Result<()> {
+        match max {
+            Some(max) if len >= max => {
+                self.error(JsonError::LimitExceeded(limit(max), self.current_position))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Read object members up to the next one with a value to parse, holding
+    /// its key and returning where the key is, or `None` once the object has
+    /// ended. `members` is how many the object has had so far.
+    fn next_member(&mut self, members: usize) -> Result<Option<Position>> {
+        loop {
+            // Parse key (must be a string)
+            let key = match &self.current_token {
+                Some(Token::String(_)) if !self.placeholder => {
+                    let key_position = self.current_position;
+                    let max = self.options.limits.max_object_members;
+                    self.check_length(members, max, Limit::ObjectMembers)?;
+                    let key = self.current_token.take();
+                    self.advance_token()?;
+                    Some((key, key_position))
+                }
+                Some(token) => {
+                    // A rejected token has already been reported by the lexer
+                    if !self.placeholder {
+                        self.error(JsonError::UnexpectedToken(
+                            Unexpected::ExpectedKey(token.to_string()),
+                            self.current_position,
+                        ))?;
+                    }
+                    self.synchronize()?;
+                    None
+                }
+                None => {
+                    self.error(JsonError::UnexpectedEof(self.current_position))?;
+                    return Ok(None);
+                }
+            };
+
+            if let Some((key, key_position)) = key {
+                // Parse colon
+                match &self.current_token {
+                    Some(Token::Colon) => {
+                        self.consumed = true;
+                        self.held = key;
+                        return Ok(Some(key_position));
+                    }
+                    Some(token) => {
+                        let value_follows = starts_value(token);
+                        self.error(JsonError::UnexpectedToken(
+                            Unexpected::ExpectedColon(token.to_string()),
+                            self.current_position,
+                        ))?;
+                        // Carry on as if the colon were there when a value follows
+                        if value_follows {
+                            self.held = key;
+                            return Ok(Some(key_position));
+                        }
+                        self.synchronize()?;
+                    }
+                    None => {
+                        self.error(JsonError::UnexpectedEof(self.current_position))?;
+                        return Ok(None);
+                    }
+                }
+            }
+
+            if !self.object_separator()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Read what follows an object member, returning whether another member
+    /// comes next.
+    fn object_separator(&mut self) -> Result<bool> {
+        // Check for comma or closing brace
+        match &self.current_token {
+            Some(Token::Comma) => {
+                self.advance_token()?;
+                // Handle trailing comma (not allowed in JSON)
+                if let Some(Token::RightBrace) = &self.current_token {
+                    self.error(JsonError::UnexpectedToken(
+                        Unexpected::TrailingCommaInObject,
+                        self.current_position,
+                    ))?;
+                    self.consumed = true;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            Some(Token::RightBrace) => {
+                self.consumed = true;
+                Ok(false)
+            }
+            Some(token) => {
+                let token = token.clone();
+                self.error(JsonError::UnexpectedToken(
+                    Unexpected::ExpectedObjectSeparator(token.to_string()),
+                    self.current_position,
+                ))?;
+                match &token {
+                    // Mismatched closer: end the object here
+                    Token::RightBracket => {
+                        self.consumed = true;
+                        Ok(false)
+                    }
+                    // Most likely a missing comma before the next member
+                    Token::String(_) => Ok(true),
+                    _ => {
+                        self.advance_token()?;
+                        self.synchronize()?;
+                        match &self.current_token {
+                            Some(Token::Comma) => self.advance_token()?,
+                            Some(Token::RightBrace | Token::RightBracket) => {
+                                self.consumed = true;
+                                return Ok(false);
+                            }
+                            _ => {}
+                        }
+                        Ok(true)
+                    }
+                }
+            }
+            None => {
+                self.error(JsonError::UnexpectedEof(self.current_position))?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Read what follows an array element, returning whether another element
+    /// comes next.
+    fn array_separator(&mut self) -> Result<bool> {
+        // Check for comma or closing bracket
+        match &self.current_token {
+            Some(Token::Comma) => {
+                self.advance_token()?;
+                // Handle trailing comma (not allowed in JSON)
+                if let Some(Token::RightBracket) = &self.current_token {
+                    self.error(JsonError::UnexpectedToken(
+                        Unexpected::TrailingCommaInArray,
+                        self.current_position,
+                    ))?;
+                    self.consumed = true;
+                    return Ok(false);
+                }
+                Ok(true)
+            }
+            Some(Token::RightBracket) => {
+                self.consumed = true;
+                Ok(false)
+            }
+            Some(token) => {
+                let token = token.clone();
+                self.error(JsonError::UnexpectedToken(
+                    Unexpected::ExpectedArraySeparator(token.to_string()),
+                    self.current_position,
+                ))?;
+                match &token {
+                    // Mismatched closer: end the array here
+                    Token::RightBrace => {
+                        self.consumed = true;
+                        Ok(false)
+                    }
+                    // Most likely a missing comma before the next element
+                    token if starts_value(token) => Ok(true),
+                    _ => {
+                        self.advance_token()?;
+                        self.synchronize()?;
+                        match &self.current_token {
+                            Some(Token::Comma) => self.advance_token()?,
+                            Some(Token::RightBracket | Token::RightBrace) => {
+                                self.consumed = true;
+                                return Ok(false);
+                            }
+                            _ => {}
+                        }
+                        Ok(true)
+                    }
+                }
+            }
+            None => {
+                self.error(JsonError::UnexpectedEof(self.current_position))?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Whether `token` can begin a JSON value.
+fn starts_value(token: &Token) -> bool {
+    !matches!(
+        token,
+        Token::RightBrace | Token::RightBracket | Token::Colon | Token::Comma
+    )
+}
@@ -1,43 +1,161 @@
-use std::collections::HashMap;
+use crate::map::{self, Map};
+use crate::number::Number;
+use crate::pretty::{PrettyConfig, to_string_pretty};
+use crate::serializer::{FmtSink, NonFiniteFloat, write_value};
 use std::fmt;
 
-#[derive(Debug, Clone, PartialEq)]
+/// A JSON value.
+///
+/// Cloning, comparing and printing values work through nested arrays and
+/// objects with a stack of their own rather than by recursion, so they cope
+/// with values nested as deeply as a raised `ParseOptions::max_depth` lets
+/// through. Dropping a value does recurse; drop ones that deep with
+/// [`JsonValue::drop_iteratively`].
+#[derive(Debug)]
 pub enum JsonValue {
     Null,
     Boolean(bool),
-    Number(f64),
+    Number(Number),
     String(String),
     Array(Vec<JsonValue>),
-    Object(HashMap<String, JsonValue>),
+    Object(Map),
 }
 
-impl fmt::Display for JsonValue {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            JsonValue::Null => write!(f, "null"),
-            JsonValue::Boolean(b) => write!(f, "{}", b),
-            JsonValue::Number(n) => write!(f, "{}", n),
-            JsonValue::String(s) => write!(f, "\"{}\"", s),
-            JsonValue::Array(arr) => {
-                write!(f, "[")?;
-                for (i, val) in arr.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
+impl Clone for JsonValue {
+    fn clone(&self) -> Self {
+        /// A copy of an array or object being filled in, with the members
+        /// still to copy and the key of the one being copied.
+        enum Copying<'v> {
+            Array(Vec<JsonValue>, std::slice::Iter<'v, JsonValue>),
+            Object(Map, map::Iter<'v, String, JsonValue>, Option<&'v String>),
+        }
+
+        let mut copying = Vec::new();
+        let mut next = self;
+        loop {
+            let mut copied = match next {
+                JsonValue::Null => Some(JsonValue::Null),
+                JsonValue::Boolean(b) => Some(JsonValue::Boolean(*b)),
+                JsonValue::Number(n) => Some(JsonValue::Number(n.clone())),
+                JsonValue::String(s) => Some(JsonValue::String(s.clone())),
+                JsonValue::Array(values) => {
+                    copying.push(Copying::Array(
+                        Vec::with_capacity(values.len()),
+                        values.iter(),
+                    ));
+                    None
+                }
+                JsonValue::Object(members) => {
+                    let copy = Map::with_capacity(members.len());
+                    copying.push(Copying::Object(copy, members.iter(), None));
+                    None
+                }
+            };
+
+            // Hand finished copies to their containers, up to one with more
+            // members to copy
+            loop {
+                let found = match copying.last_mut() {
+                    None => return copied.expect("the outermost value is done"),
+                    Some(Copying::Array(copy, rest)) => {
+                        copy.extend(copied.take());
+                        rest.next()
                     }
-                    write!(f, "{}", val)?;
+                    Some(Copying::Object(copy, rest, key)) => {
+                        if let Some(value) = copied.take() {
+                            copy.insert(key.take().expect("a key").clone(), value);
+                        }
+                        rest.next().map(|(k, value)| {
+                            *key = Some(k);
+                            value
+                        })
+                    }
+                };
+                if let Some(value) = found {
+                    next = value;
+                    break;
                 }
-                write!(f, "]")
+                copied = match copying.pop() {
+                    Some(Copying::Array(copy, _)) => Some(JsonValue::Array(copy)),
+                    Some(Copying::Object(copy, _, _)) => Some(JsonValue::Object(copy)),
+                    None => unreachable!("there is a container to finish"),
+                };
             }
-            JsonValue::Object(obj) => {
-                write!(f, "{{")?;
-                for (i, (key, val)) in obj.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
+        }
+    }
+}
+
+/// Values are equal when they have the same type and contents. Numbers
+/// are compared by value, so `1` equals `1.0`, and objects regardless of
+/// the order of their members.
+impl PartialEq for JsonValue {
+    fn eq(&self, other: &JsonValue) -> bool {
+        let mut pending = vec![(self, other)];
+        while let Some(pair) = pending.pop() {
+            match pair {
+                (JsonValue::Null, JsonValue::Null) => {}
+                (JsonValue::Boolean(a), JsonValue::Boolean(b)) if a == b => {}
+                (JsonValue::Number(a), JsonValue::Number(b)) if a == b => {}
+                (JsonValue::String(a), JsonValue::String(b)) if a == b => {}
+                (JsonValue::Array(a), JsonValue::Array(b)) if a.len() == b.len() => {
+                    pending.extend(a.iter().zip(b));
+                }
+                (JsonValue::Object(a), JsonValue::Object(b)) if a.len() == b.len() => {
+                    for (key, a) in a {
+                        match b.get(key) {
+                            Some(b) => pending.push((a, b)),
+                            None => return false,
+                        }
                     }
-                    write!(f, "\"{}\": {}", key, val)?;
                 }
-                write!(f, "}}")
+                _ => return false,
             }
         }
+        true
+    }
+}
+
+impl JsonValue {
+    /// Drop the value a level at a time, rather than recursing once per
+    /// level of nesting the way dropping it normally does.
+    pub fn drop_iteratively(mut self) {
+        let mut nested = Vec::new();
+        take_nested(&mut self, &mut nested);
+        while let Some(mut value) = nested.pop() {
+            take_nested(&mut value, &mut nested);
+        }
+    }
+}
+
+/// Move the arrays and objects directly inside `value` onto `nested`.
+fn take_nested(value: &mut JsonValue, nested: &mut Vec<JsonValue>) {
+    let is_nested = |value: &JsonValue| matches!(value, JsonValue::Array(_) | JsonValue::Object(_));
+    match value {
+        JsonValue::Array(values) => nested.extend(values.drain(..).filter(is_nested)),
+        JsonValue::Object(members) => nested.extend(
+            std::mem::take(members)
+                .into_iter()
+                .map(|(_, value)| value)
+                .filter(is_nested),
+        ),
+        _ => {}
+    }
+}
+
+/// `{}` prints the value on one line; `{:#}` pretty-prints it with the
+/// default `PrettyConfig`.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            let config = PrettyConfig {
+                non_finite: NonFiniteFloat::Null,
+                ..PrettyConfig::default()
+            };
+            let text = to_string_pretty(self, &config).map_err(|_| fmt::Error)?;
+            return f.write_str(&text);
+        }
+
+        // Display can't fail, so numbers JSON can't represent become null
+        write_value(&mut FmtSink(f), self, NonFiniteFloat::Null, true).map_err(|_| fmt::Error)
     }
 }
@@ -0,0 +1,831 @@
+use crate::error::PathError;
+use crate::json::JsonValue;
+use crate::number::Number;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+type Result<T> = std::result::Result<T, PathError>;
+
+/// The largest array index or slice bound a query may hold, so that
+/// numbers in queries stay exact in any JSON implementation (RFC 9535).
+const MAX_INDEX: i64 = (1 << 53) - 1;
+
+/// How deeply parentheses and filters may nest within a query, so parsing
+/// it can't overflow the stack.
+const MAX_NESTING: usize = 128;
+
+/// A parsed JSONPath query (RFC 9535), such as
+/// `$.store.book[?@.price < 10].title`.
+///
+/// Queries are made of child segments (`.name`, `.*`, `[...]`) and
+/// descendant segments (`..name`, `..*`, `..[...]`). Inside brackets come
+/// names (`'name'`), wildcards (`*`), indices (`0`, `-1`), slices
+/// (`1:5:2`) and filters (`?@.price < 10`), separated by commas. Filters
+/// compare with `==`, `!=`, `<`, `<=`, `>` and `>=`, test whether a query
+/// finds anything, and combine these with `&&`, `||`, `!` and parentheses.
+/// Function extensions such as `length()` are not supported.
+///
+/// ```
+/// use json_parser::{JsonPath, parse_json};
+///
+/// let value = parse_json(r#"{"books": [{"price": 8}, {"price": 12}]}"#).unwrap();
+/// let path: JsonPath = "$.books[?@.price < 10].price".parse().unwrap();
+/// let found = path.query(&value);
+/// assert_eq!(found[0].path, "$['books'][0]['price']");
+/// assert_eq!(found[0].value.to_string(), "8");
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    text: String,
+    query: Query,
+}
+
+/// A value a query found, with its normalized path: the path that leads
+/// to it and nothing else, such as `$['books'][0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathMatch<'v> {
+    pub path: String,
+    pub value: &'v JsonValue,
+}
+
+impl JsonPath {
+    pub fn parse(text: &str) -> Result<JsonPath> {
+        let mut parser = PathParser {
+            text,
+            pos: 0,
+            depth: 0,
+        };
+        parser.expect('$')?;
+        let segments = parser.segments()?;
+        if parser.pos < text.len() {
+            return Err(parser.error("Unexpected character"));
+        }
+        Ok(JsonPath {
+            text: text.to_string(),
+            query: Query {
+                relative: false,
+                segments,
+            },
+        })
+    }
+
+    /// The values the query finds in `value`, in order, with their paths.
+    pub fn query<'v>(&self, value: &'v JsonValue) -> Vec<PathMatch<'v>> {
+        self.query
+            .evaluate(value, value, String::from("$"))
+            .into_iter()
+            .map(|(value, path)| PathMatch { path, value })
+            .collect()
+    }
+
+    /// The values the query finds in `value`, in order, without working out
+    /// their paths.
+    pub fn select<'v>(&self, value: &'v JsonValue) -> Vec<&'v JsonValue> {
+        self.query
+            .evaluate(value, value, ())
+            .into_iter()
+            .map(|(value, ())| value)
+            .collect()
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = PathError;
+
+    fn from_str(text: &str) -> Result<JsonPath> {
+        JsonPath::parse(text)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl JsonValue {
+    /// Run the JSONPath query `path` against this value. See [`JsonPath`].
+    pub fn query(&self, path: &str) -> Result<Vec<PathMatch<'_>>> {
+        Ok(JsonPath::parse(path)?.query(self))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Query {
+    /// Starts at the current node `@` rather than the root `$`.
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    /// Applies to the input and all its descendants, not just the input.
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    /// True when the query finds anything.
+    Exists(Query),
+    Compare(Comparable, Op, Comparable),
+}
+
+#[derive(Debug, Clone)]
+enum Comparable {
+    Literal(JsonValue),
+    /// A query that finds at most one value.
+    Query(Query),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Where a node found by a query is, if that is being kept track of.
+trait Trail: Clone {
+    fn key(&self, key: &str) -> Self;
+    fn index(&self, index: usize) -> Self;
+}
+
+/// Not keeping track.
+impl Trail for () {
+    fn key(&self, _key: &str) {}
+    fn index(&self, _index: usize) {}
+}
+
+/// The normalized path so far.
+impl Trail for String {
+    fn key(&self, key: &str) -> Self {
+        let mut path = self.clone();
+        path.push_str("['");
+        for c in key.chars() {
+            match c {
+                '\'' => path.push_str("\\'"),
+                '\\' => path.push_str("\\\\"),
+                '\u{0008}' => path.push_str("\\b"),
+                '\u{000C}' => path.push_str("\\f"),
+                '\n' => path.push_str("\\n"),
+                '\r' => path.push_str("\\r"),
+                '\t' => path.push_str("\\t"),
+                c if c < ' ' => path.push_str(&format!("\\u{:04x}", c as u32)),
+                c => path.push(c),
+            }
+        }
+        path.push_str("']");
+        path
+    }
+
+    fn index(&self, index: usize) -> Self {
+        format!("{}[{}]", self, index)
+    }
+}
+
+impl Query {
+    fn evaluate<'v, T: Trail>(
+        &self,
+        root: &'v JsonValue,
+        current: &'v JsonValue,
+        trail: T,
+    ) -> Vec<(&'v JsonValue, T)> {
+        let start = if self.relative { current } else { root };
+        let mut nodes = vec![(start, trail)];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for (value, trail) in &nodes {
+                segment.apply(root, value, trail, &mut next);
+            }
+            nodes = next;
+        }
+        nodes
+    }
+
+    /// Whether the query can only ever find one value: each segment is a
+    /// child segment with a single name or index.
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(
+                    segment.selectors[..],
+                    [Selector::Name(_)] | [Selector::Index(_)]
+                )
+        })
+    }
+}
+
+impl Segment {
+    fn apply<'v, T: Trail>(
+        &self,
+        root: &'v JsonValue,
+        value: &'v JsonValue,
+        trail: &T,
+        out: &mut Vec<(&'v JsonValue, T)>,
+    ) {
+        for selector in &self.selectors {
+            selector.select(root, value, trail, out);
+        }
+        if self.descendant {
+            each_child(value, trail, |child, trail| {
+                self.apply(root, child, &trail, out)
+            });
+        }
+    }
+}
+
+/// Call `f` with each element of an array or each member value of an
+/// object, in order.
+fn each_child<'v, T: Trail>(value: &'v JsonValue, trail: &T, mut f: impl FnMut(&'v JsonValue, T)) {
+    match value {
+        JsonValue::Array(elements) => {
+            for (i, element) in elements.iter().enumerate() {
+                f(element, trail.index(i));
+            }
+        }
+        JsonValue::Object(members) => {
+            for (key, member) in members {
+                f(member, trail.key(key));
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Selector {
+    fn select<'v, T: Trail>(
+        &self,
+        root: &'v JsonValue,
+        value: &'v JsonValue,
+        trail: &T,
+        out: &mut Vec<(&'v JsonValue, T)>,
+    ) {
+        match (self, value) {
+            (Selector::Name(name), JsonValue::Object(members)) => {
+                if let Some(member) = members.get(name) {
+                    out.push((member, trail.key(name)));
+                }
+            }
+            (Selector::Wildcard, _) => each_child(value, trail, |child, trail| {
+                out.push((child, trail));
+            }),
+            (Selector::Index(index), JsonValue::Array(elements)) => {
+                let len = elements.len() as i64;
+                let i = if *index < 0 { len + index } else { *index };
+                if (0..len).contains(&i) {
+                    out.push((&elements[i as usize], trail.index(i as usize)));
+                }
+            }
+            (Selector::Slice { start, end, step }, JsonValue::Array(elements)) => {
+                for i in slice_indices(elements.len() as i64, *start, *end, *step) {
+                    out.push((&elements[i], trail.index(i)));
+                }
+            }
+            (Selector::Filter(expr), _) => each_child(value, trail, |child, trail| {
+                if expr.test(root, child) {
+                    out.push((child, trail));
+                }
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// The indices a slice selects from an array of length `len`, in the order
+/// it selects them.
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<usize> {
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    let mut indices = Vec::new();
+
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i += step;
+        }
+    }
+    indices
+}
+
+impl Expr {
+    fn test(&self, root: &JsonValue, current: &JsonValue) -> bool {
+        match self {
+            Expr::Or(exprs) => exprs.iter().any(|e| e.test(root, current)),
+            Expr::And(exprs) => exprs.iter().all(|e| e.test(root, current)),
+            Expr::Not(expr) => !expr.test(root, current),
+            Expr::Exists(query) => !query.evaluate(root, current, ()).is_empty(),
+            Expr::Compare(left, op, right) => {
+                let left = left.value(root, current);
+                let right = right.value(root, current);
+                op.apply(left, right)
+            }
+        }
+    }
+}
+
+impl Comparable {
+    /// The value compared, or `None` if a query found nothing.
+    fn value<'x>(&'x self, root: &'x JsonValue, current: &'x JsonValue) -> Option<&'x JsonValue> {
+        match self {
+            Comparable::Literal(value) => Some(value),
+            Comparable::Query(query) => query
+                .evaluate(root, current, ())
+                .first()
+                .map(|(value, ())| *value),
+        }
+    }
+}
+
+impl Op {
+    /// Compare two values, where `None` is a query that found nothing and
+    /// equals only another such query.
+    fn apply(self, left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+        let equal = || match (left, right) {
+            (None, None) => true,
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        };
+        let less = |a: Option<&JsonValue>, b: Option<&JsonValue>| match (a, b) {
+            (Some(JsonValue::Number(a)), Some(JsonValue::Number(b))) => {
+                a.compare(b) == Some(Ordering::Less)
+            }
+            (Some(JsonValue::String(a)), Some(JsonValue::String(b))) => a < b,
+            _ => false,
+        };
+        match self {
+            Op::Eq => equal(),
+            Op::Ne => !equal(),
+            Op::Lt => less(left, right),
+            Op::Le => less(left, right) || equal(),
+            Op::Gt => less(right, left),
+            Op::Ge => less(right, left) || equal(),
+        }
+    }
+}
+
+struct PathParser<'t> {
+    text: &'t str,
+    pos: usize,
+    /// How many expressions the parser is inside.
+    depth: usize,
+}
+
+impl PathParser<'_> {
+    fn error(&self, message: &str) -> PathError {
+        PathError {
+            message: message.to_string(),
+            offset: self.pos,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.text[self.pos..].starts_with(prefix)
+    }
+
+    /// Move past `prefix` if it comes next.
+    fn eat(&mut self, prefix: &str) -> bool {
+        let found = self.starts_with(prefix);
+        if found {
+            self.pos += prefix.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected '{}'", c)))
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn segments(&mut self) -> Result<Vec<Segment>> {
+        let mut segments = Vec::new();
+        loop {
+            // Blank space may come between segments, but not after the last
+            let before = self.pos;
+            self.skip_blank();
+            let segment = if self.eat("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.bracketed()?,
+                    Some('*') => {
+                        self.pos += 1;
+                        vec![Selector::Wildcard]
+                    }
+                    _ => vec![Selector::Name(self.member_name()?)],
+                };
+                Segment {
+                    descendant: true,
+                    selectors,
+                }
+            } else if self.eat(".") {
+                let selector = if self.eat("*") {
+                    Selector::Wildcard
+                } else {
+                    Selector::Name(self.member_name()?)
+                };
+                Segment {
+                    descendant: false,
+                    selectors: vec![selector],
+                }
+            } else if self.peek() == Some('[') {
+                Segment {
+                    descendant: false,
+                    selectors: self.bracketed()?,
+                }
+            } else {
+                self.pos = before;
+                return Ok(segments);
+            };
+            segments.push(segment);
+        }
+    }
+
+    /// A name written after a dot, without quotes.
+    fn member_name(&mut self) -> Result<String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let allowed = c.is_ascii_alphabetic()
+                || c == '_'
+                || !c.is_ascii()
+                || (self.pos > start && c.is_ascii_digit());
+            if !allowed {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        if self.pos == start {
+            return Err(self.error("Expected a member name"));
+        }
+        Ok(self.text[start..self.pos].to_string())
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_blank();
+            selectors.push(self.selector()?);
+            self.skip_blank();
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => Ok(Selector::Name(self.string(quote)?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                self.skip_blank();
+                Ok(Selector::Filter(self.or_expr()?))
+            }
+            _ => {
+                let start = self.optional_int()?;
+                self.skip_blank();
+                if !self.eat(":") {
+                    return match start {
+                        Some(index) => Ok(Selector::Index(index)),
+                        None => Err(self.error("Expected a selector")),
+                    };
+                }
+                self.skip_blank();
+                let end = self.optional_int()?;
+                self.skip_blank();
+                let mut step = None;
+                if self.eat(":") {
+                    self.skip_blank();
+                    step = self.optional_int()?;
+                }
+                Ok(Selector::Slice { start, end, step })
+            }
+        }
+    }
+
+    /// An integer, with no leading zeros and within the exact range, if one
+    /// comes next.
+    fn optional_int(&mut self) -> Result<Option<i64>> {
+        let start = self.pos;
+        self.eat("-");
+        let digits = self.text[self.pos..]
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        if digits == 0 {
+            if self.pos > start {
+                return Err(self.error("Expected a digit"));
+            }
+            return Ok(None);
+        }
+        let text = &self.text[start..self.pos + digits];
+        let leading_zero = self.starts_with("0") && digits > 1;
+        if leading_zero || text == "-0" {
+            return Err(self.error("Integers may not have leading zeros or be -0"));
+        }
+        self.pos += digits;
+        match text.parse::<i64>() {
+            Ok(n) if (-MAX_INDEX..=MAX_INDEX).contains(&n) => Ok(Some(n)),
+            _ => Err(PathError {
+                message: "Integer out of range".to_string(),
+                offset: start,
+            }),
+        }
+    }
+
+    /// A string literal quoted with `quote`, unescaped.
+    fn string(&mut self, quote: char) -> Result<String> {
+        let start = self.pos;
+        self.expect(quote)?;
+        let unterminated = || PathError {
+            message: "Unterminated string".to_string(),
+            offset: start,
+        };
+        let mut string = String::new();
+        loop {
+            let c = self.peek().ok_or_else(unterminated)?;
+            self.pos += c.len_utf8();
+            match c {
+                c if c == quote => return Ok(string),
+                '\\' => {
+                    let escape = self.peek().ok_or_else(unterminated)?;
+                    self.pos += escape.len_utf8();
+                    match escape {
+                        'b' => string.push('\u{0008}'),
+                        'f' => string.push('\u{000C}'),
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        '/' | '\\' => string.push(escape),
+                        'u' => string.push(self.unicode_escape()?),
+                        c if c == quote => string.push(c),
+                        _ => return Err(self.error("Invalid escape sequence")),
+                    }
+                }
+                c if c < ' ' => return Err(self.error("Unescaped control character")),
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// The character a `\u` escape stands for, with the `\u` already read.
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !self.eat("\\u") {
+                    return Err(self.error("Expected the low half of a surrogate pair"));
+                }
+                let low = self.hex4()?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(self.error("Expected the low half of a surrogate pair"));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(self.error("Unpaired surrogate")),
+            code => code,
+        };
+        Ok(char::from_u32(code).expect("surrogates are paired"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self.text.get(self.pos..self.pos + 4).unwrap_or("");
+        if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error("Expected four hex digits"));
+        }
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    fn or_expr(&mut self) -> Result<Expr> {
+        if self.depth == MAX_NESTING {
+            return Err(self.error("Expressions are nested too deeply"));
+        }
+        self.depth += 1;
+        let expr = self.or_terms();
+        self.depth -= 1;
+        expr
+    }
+
+    fn or_terms(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.and_expr()?];
+        loop {
+            let before = self.pos;
+            self.skip_blank();
+            if !self.eat("||") {
+                self.pos = before;
+                break;
+            }
+            self.skip_blank();
+            exprs.push(self.and_expr()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn and_expr(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.basic_expr()?];
+        loop {
+            let before = self.pos;
+            self.skip_blank();
+            if !self.eat("&&") {
+                self.pos = before;
+                break;
+            }
+            self.skip_blank();
+            exprs.push(self.basic_expr()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    /// A parenthesized expression, a comparison or a test, each possibly
+    /// negated.
+    fn basic_expr(&mut self) -> Result<Expr> {
+        let negated = self.eat("!");
+        if negated {
+            self.skip_blank();
+        }
+
+        let expr = if self.eat("(") {
+            self.skip_blank();
+            let expr = self.or_expr()?;
+            self.skip_blank();
+            self.expect(')')?;
+            expr
+        } else {
+            let start = self.pos;
+            let left = self.comparable()?;
+            let before = self.pos;
+            self.skip_blank();
+            match self.op() {
+                Some(_) if negated => {
+                    return Err(
+                        self.error("Only tests and parenthesized expressions can be negated")
+                    );
+                }
+                Some(op) => {
+                    self.skip_blank();
+                    let right = self.comparable()?;
+                    for side in [&left, &right] {
+                        if let Comparable::Query(query) = side
+                            && !query.is_singular()
+                        {
+                            return Err(PathError {
+                                message: "Only queries that find at most one value can be compared"
+                                    .to_string(),
+                                offset: start,
+                            });
+                        }
+                    }
+                    Expr::Compare(left, op, right)
+                }
+                None => {
+                    self.pos = before;
+                    match left {
+                        Comparable::Query(query) => Expr::Exists(query),
+                        Comparable::Literal(_) => {
+                            return Err(PathError {
+                                message: "A literal on its own is not a test".to_string(),
+                                offset: start,
+                            });
+                        }
+                    }
+                }
+            }
+        };
+
+        Ok(if negated {
+            Expr::Not(Box::new(expr))
+        } else {
+            expr
+        })
+    }
+
+    fn op(&mut self) -> Option<Op> {
+        // Longer operators first, so `<=` isn't read as `<`
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        ops.into_iter()
+            .find_map(|(text, op)| self.eat(text).then_some(op))
+    }
+
+    fn comparable(&mut self) -> Result<Comparable> {
+        match self.peek() {
+            Some('@') | Some('$') => {
+                let relative = self.peek() == Some('@');
+                self.pos += 1;
+                let segments = self.segments()?;
+                Ok(Comparable::Query(Query { relative, segments }))
+            }
+            Some(quote @ ('\'' | '"')) => {
+                Ok(Comparable::Literal(JsonValue::String(self.string(quote)?)))
+            }
+            Some('-' | '0'..='9') => Ok(Comparable::Literal(JsonValue::Number(self.number()?))),
+            _ if self.eat("true") => Ok(Comparable::Literal(JsonValue::Boolean(true))),
+            _ if self.eat("false") => Ok(Comparable::Literal(JsonValue::Boolean(false))),
+            _ if self.eat("null") => Ok(Comparable::Literal(JsonValue::Null)),
+            Some(c) if c.is_ascii_lowercase() => {
+                Err(self.error("Function extensions are not supported"))
+            }
+            _ => Err(self.error("Expected a query or a literal")),
+        }
+    }
+
+    /// A number literal, written as in JSON, except that `-0` may have no
+    /// fraction or exponent.
+    fn number(&mut self) -> Result<Number> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let n = parser.text[parser.pos..]
+                .bytes()
+                .take_while(u8::is_ascii_digit)
+                .count();
+            parser.pos += n;
+            n
+        };
+
+        self.eat("-");
+        let int_start = self.pos;
+        let int_digits = digits(self);
+        if int_digits == 0 || (int_digits > 1 && self.text[int_start..].starts_with('0')) {
+            return Err(self.error("Invalid number"));
+        }
+        if self.eat(".") && digits(self) == 0 {
+            return Err(self.error("Expected a digit after the decimal point"));
+        }
+        if self.eat("e") || self.eat("E") {
+            if !self.eat("+") {
+                self.eat("-");
+            }
+            if digits(self) == 0 {
+                return Err(self.error("Expected a digit in the exponent"));
+            }
+        }
+
+        Number::from_literal(&self.text[start..self.pos], false).ok_or_else(|| PathError {
+            message: "Invalid number".to_string(),
+            offset: start,
+        })
+    }
+}
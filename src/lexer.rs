@@ -1,13 +1,18 @@
-use crate::error::{JsonError, Result};
-use std::iter::Peekable;
-use std::str::Chars;
+use crate::error::{JsonError, Limit, NumberError, Position, Result, Unexpected};
+use crate::number::Number;
+use crate::options::{LoneSurrogates, ParseOptions};
+use crate::source::{SliceSource, Source, StrSource, invalid_utf8, utf8_width};
+use std::borrow::Cow;
+use std::fmt;
 
+/// A token of JSON text. Strings borrow from input that lives for `'a`
+/// where they can.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Token {
+pub enum Token<'a> {
     Null,
     Boolean(bool),
-    Number(f64),
-    String(String),
+    Number(Number),
+    String(Cow<'a, str>),
     LeftBrace,    // {
     RightBrace,   // }
     LeftBracket,  // [
@@ -16,80 +21,443 @@ pub enum Token {
     Comma,        // ,
 }
 
-pub struct Lexer<'a> {
-    input: Peekable<Chars<'a>>,
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Null => write!(f, "null"),
+            Token::Boolean(b) => write!(f, "{}", b),
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::String(s) => write!(f, "string {:?}", s),
+            Token::LeftBrace => write!(f, "'{{'"),
+            Token::RightBrace => write!(f, "'}}'"),
+            Token::LeftBracket => write!(f, "'['"),
+            Token::RightBracket => write!(f, "']'"),
+            Token::Colon => write!(f, "':'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+pub struct Lexer<S> {
+    input: S,
+    /// Byte offset of the next character.
+    offset: usize,
+    /// Where the current line would have started were every character on it
+    /// one byte long, so that moving over ASCII only has to move `offset`.
+    line_start: usize,
+    line: usize,
+    token_start: Position,
+    options: ParseOptions,
+    /// Check strings and numbers without making anything of them.
+    skipping: bool,
 }
 
-impl<'a> Lexer<'a> {
+impl<'a> Lexer<StrSource<'a>> {
     pub fn new(input: &'a str) -> Self {
+        Lexer::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
+        Lexer::with_source(StrSource::new(input), options)
+    }
+}
+
+impl<'a> Lexer<SliceSource<'a>> {
+    /// Lex UTF-8 bytes, checking that the strings in them are valid as
+    /// they are read.
+    pub fn from_slice(input: &'a [u8], options: ParseOptions) -> Self {
+        Lexer::with_source(SliceSource::new(input), options)
+    }
+}
+
+impl<'a, S: Source<'a>> Lexer<S> {
+    pub fn with_source(mut input: S, options: ParseOptions) -> Self {
+        if let Some(max) = options.limits.max_input_bytes {
+            input.limit(max);
+        }
         Lexer {
-            input: input.chars().peekable(),
+            input,
+            offset: 0,
+            line: 1,
+            line_start: 0,
+            token_start: Position::start(),
+            options,
+            skipping: false,
+        }
+    }
+
+    /// The position of the next character to be read.
+    pub fn position(&self) -> Position {
+        Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.offset - self.line_start + 1,
         }
     }
 
-    pub fn next_token(&mut self) -> Result<Option<Token>> {
+    /// The position where the most recently returned token started.
+    ///
+    /// When `next_token` returns `None`, this is the end of the input.
+    pub fn token_start(&self) -> Position {
+        self.token_start
+    }
+
+    pub(crate) fn source_mut(&mut self) -> &mut S {
+        &mut self.input
+    }
+
+    /// Check the strings and numbers that follow as usual, but hand them
+    /// back empty and as zero, for tokens that are only being skipped over.
+    pub(crate) fn set_skipping(&mut self, skipping: bool) {
+        self.skipping = skipping;
+    }
+
+    /// Carry on from an earlier `position` and `token_start`, once the
+    /// source has been rewound to the same place.
+    pub(crate) fn rewind(&mut self, position: Position, token_start: Position) {
+        self.offset = position.offset;
+        self.line = position.line;
+        self.line_start = position.offset + 1 - position.column;
+        self.token_start = token_start;
+    }
+
+    pub fn next_token(&mut self) -> Result<Option<Token<'a>>> {
         self.skip_whitespace();
+        self.token_start = self.position();
+        match self.peek() {
+            Some(b'{') => self.punctuation().map(|()| Some(Token::LeftBrace)),
+            Some(b'}') => self.punctuation().map(|()| Some(Token::RightBrace)),
+            Some(b'[') => self.punctuation().map(|()| Some(Token::LeftBracket)),
+            Some(b']') => self.punctuation().map(|()| Some(Token::RightBracket)),
+            Some(b':') => self.punctuation().map(|()| Some(Token::Colon)),
+            Some(b',') => self.punctuation().map(|()| Some(Token::Comma)),
+            Some(b'"') => self.string_token(),
+            Some(b'n') => self.literal("null").map(|()| Some(Token::Null)),
+            Some(b't') => self.literal("true").map(|()| Some(Token::Boolean(true))),
+            Some(b'f') => self.literal("false").map(|()| Some(Token::Boolean(false))),
+            Some(b'0'..=b'9' | b'-') => self.number_token(),
+            Some(_) => {
+                let error = match self.peek_char() {
+                    Some(c) => {
+                        JsonError::UnexpectedToken(Unexpected::Character(c), self.position())
+                    }
+                    None => self.invalid_utf8(),
+                };
+                self.check(true, false)?;
+                Err(error)
+            }
+            None => {
+                self.check(false, false)?;
+                Ok(None)
+            }
+        }
+    }
 
-        match self.input.peek() {
-            Some(&c) => {
+    // Tokens are made right where they are returned. Moving one soon after
+    // making it, such as out of what a reader returns, stalls on the stores
+    // that made it, which costs more than lexing most tokens does
+    #[inline(never)]
+    fn string_token(&mut self) -> Result<Option<Token<'a>>> {
+        self.bump_ascii(1); // Skip opening quote
+        match self.read_string() {
+            Ok(string) => {
+                self.check(false, true)?;
+                Ok(Some(Token::String(string)))
+            }
+            Err(e) => {
+                self.check(true, true)?;
+                Err(e)
+            }
+        }
+    }
+
+    #[inline(never)]
+    fn number_token(&mut self) -> Result<Option<Token<'a>>> {
+        match self.read_number() {
+            Ok(number) => {
+                self.check(false, false)?;
+                Ok(Some(Token::Number(number)))
+            }
+            Err(e) => {
+                self.check(true, false)?;
+                Err(e)
+            }
+        }
+    }
+
+    fn literal(&mut self, word: &str) -> Result<()> {
+        match self.read_literal(word) {
+            Ok(()) => self.check(false, false),
+            Err(e) => {
+                self.check(true, false)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Fail if the source failed or the input limit was passed while a token
+    /// was read, which wins over the token or the error it ended in.
+    /// Otherwise move past the rest of the token if it `failed`.
+    #[inline(always)]
+    fn check(&mut self, failed: bool, in_string: bool) -> Result<()> {
+        if let Some(e) = self.input.take_error() {
+            return Err(JsonError::Io(e, self.position()));
+        }
+        if let Some(max) = self.options.limits.max_input_bytes
+            && (self.offset > max || self.input.exceeded() && self.input.fill(1).is_empty())
+        {
+            // Running into where the source cut the input off, or past the
+            // limit, is the limit's doing
+            return Err(JsonError::LimitExceeded(
+                Limit::InputBytes(max),
+                self.token_start,
+            ));
+        }
+        if failed {
+            self.skip_malformed(in_string);
+        }
+        Ok(())
+    }
+
+    /// Move past the rest of a token that failed to lex, so that lexing can
+    /// resume at the next one.
+    #[inline(never)]
+    fn skip_malformed(&mut self, in_string: bool) {
+        if in_string {
+            // Resume after the closing quote
+            while let Some(c) = self.bump() {
                 match c {
-                    '{' => {
-                        self.input.next();
-                        Ok(Some(Token::LeftBrace))
-                    }
-                    '}' => {
-                        self.input.next();
-                        Ok(Some(Token::RightBrace))
-                    }
-                    '[' => {
-                        self.input.next();
-                        Ok(Some(Token::LeftBracket))
+                    '"' => return,
+                    '\\' => {
+                        self.bump();
                     }
-                    ']' => {
-                        self.input.next();
-                        Ok(Some(Token::RightBracket))
-                    }
-                    ':' => {
-                        self.input.next();
-                        Ok(Some(Token::Colon))
-                    }
-                    ',' => {
-                        self.input.next();
-                        Ok(Some(Token::Comma))
-                    }
-                    '"' => {
-                        self.input.next(); // Skip opening quote
-                        self.read_string()
-                    }
-                    'n' => self.read_null(),
-                    't' | 'f' => self.read_boolean(),
-                    '0'..='9' | '-' => self.read_number(),
-                    _ => Err(JsonError::UnexpectedToken(c.to_string())),
+                    _ => {}
+                }
+            }
+            return;
+        }
+
+        loop {
+            match self.peek_char() {
+                Some(c)
+                    if self.is_whitespace(c)
+                        || matches!(c, '{' | '}' | '[' | ']' | ':' | ',' | '"') =>
+                {
+                    break;
+                }
+                None if self.peek().is_none() => break,
+                _ => {
+                    self.bump();
                 }
             }
-            None => Ok(None),
+        }
+    }
+
+    /// The next byte, without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        self.input.fill(1).first().copied()
+    }
+
+    /// The next character, without consuming it. `None` at the end of the
+    /// input, or where the bytes aren't valid UTF-8.
+    fn peek_char(&mut self) -> Option<char> {
+        let first = self.peek()?;
+        if first.is_ascii() {
+            return Some(char::from(first));
+        }
+        let width = utf8_width(first);
+        let bytes = self.input.fill(width);
+        let bytes = &bytes[..width.min(bytes.len())];
+        std::str::from_utf8(bytes).ok()?.chars().next()
+    }
+
+    /// Consume the next character, keeping track of where we are. A byte
+    /// that isn't valid UTF-8 is consumed on its own, as U+FFFD.
+    fn bump(&mut self) -> Option<char> {
+        match self.peek_char() {
+            Some(c) => {
+                self.input.consume(c.len_utf8());
+                self.offset += c.len_utf8();
+                if c == '\n' {
+                    self.line += 1;
+                    self.line_start = self.offset;
+                } else {
+                    self.line_start += c.len_utf8() - 1;
+                }
+                Some(c)
+            }
+            None => {
+                self.peek()?;
+                self.bump_ascii(1);
+                Some(char::REPLACEMENT_CHARACTER)
+            }
+        }
+    }
+
+    fn invalid_utf8(&self) -> JsonError {
+        JsonError::Io(invalid_utf8(), self.position())
+    }
+
+    /// Consume `n` bytes of ASCII that holds no newlines.
+    fn bump_ascii(&mut self, n: usize) {
+        self.input.consume(n);
+        self.offset += n;
+    }
+
+    fn punctuation(&mut self) -> Result<()> {
+        self.bump_ascii(1);
+        self.check(false, false)
+    }
+
+    /// Whether `c` may separate tokens. Strict mode allows only the four
+    /// characters RFC 8259 lists.
+    fn is_whitespace(&self, c: char) -> bool {
+        if self.options.strict {
+            matches!(c, ' ' | '\t' | '\n' | '\r')
+        } else {
+            c.is_whitespace()
         }
     }
 
     fn skip_whitespace(&mut self) {
-        while let Some(&c) = self.input.peek() {
-            if c.is_whitespace() {
-                self.input.next();
-            } else {
-                break;
+        // Most tokens follow straight on from the last one
+        if self.peek().is_some_and(|b| b > b' ' && b.is_ascii()) {
+            return;
+        }
+        loop {
+            // Skip the four JSON whitespace characters a buffer at a time
+            let bytes = self.input.fill(1);
+            let mut n = 0;
+            for &b in bytes {
+                match b {
+                    b' ' | b'\t' | b'\r' => {}
+                    b'\n' => {
+                        self.line += 1;
+                        self.line_start = self.offset + n + 1;
+                    }
+                    _ => break,
+                }
+                n += 1;
+            }
+            let next = bytes.get(n).copied();
+            self.offset += n;
+            self.input.consume(n);
+
+            match next {
+                None if n > 0 => continue,
+                None => return,
+                // Only these ASCII characters are whitespace outside strict mode
+                Some(b) if b.is_ascii() && !matches!(b, 0x0B | 0x0C | 0x1C..=0x1F) => return,
+                Some(_) => {}
+            }
+            // Anything else that counts as whitespace outside strict mode
+            match self.peek_char() {
+                Some(c) if self.is_whitespace(c) => {
+                    self.bump();
+                }
+                _ => return,
             }
         }
     }
 
-    fn read_string(&mut self) -> Result<Option<Token>> {
-        let mut string = String::new();
+    #[inline(always)]
+    fn read_string(&mut self) -> Result<Cow<'a, str>> {
+        let mut string = match self.skipping {
+            true => Text::Skipped(0),
+            false => Text::new(&self.input),
+        };
+        // A `\uD800`-`\uDBFF` escape waiting for its low surrogate
+        let mut high_surrogate: Option<(u32, Position)> = None;
+
+        loop {
+            // Take everything up to the next quote, escape or control
+            // character in one go
+            let bytes = self.input.fill(1);
+            let (run, non_ascii) = scan_string(bytes);
+            if run > 0 && high_surrogate.is_some() {
+                self.unpaired_surrogate(&mut string, &mut high_surrogate)?;
+                continue;
+            }
+            if !non_ascii && bytes.get(run) == Some(&b'"') && high_surrogate.is_none() {
+                // The whole rest of the string is ASCII, which is valid
+                // UTF-8 as it is
+                string.push_ascii(&bytes[..run]);
+                self.bump_ascii(run + 1);
+                self.check_string_length(&string)?;
+                return Ok(string.into_cow());
+            }
+            if run > 0 && !non_ascii {
+                string.push_ascii(&bytes[..run]);
+                self.bump_ascii(run);
+                self.check_string_length(&string)?;
+                continue;
+            }
+            if run > 0 {
+                // Only the non-ASCII text in a string needs checking
+                let valid = match std::str::from_utf8(&bytes[..run]) {
+                    Ok(chunk) => chunk,
+                    Err(e) if e.valid_up_to() > 0 => {
+                        std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap()
+                    }
+                    // A character cut off by the end of the buffer, which
+                    // reading more may finish
+                    Err(e) if e.error_len().is_none() && run == bytes.len() => {
+                        let len = bytes.len();
+                        if self.input.fill(len + 1).len() > len {
+                            continue;
+                        }
+                        return Err(self.invalid_utf8());
+                    }
+                    Err(_) => return Err(self.invalid_utf8()),
+                };
+                let (len, columns) = (valid.len(), valid.chars().count());
+                string.push_str_raw(valid);
+                self.offset += len;
+                self.line_start += len - columns;
+                self.input.consume(len);
+                self.check_string_length(&string)?;
+                continue;
+            }
+
+            let char_start = self.position();
+            let c = match self.bump() {
+                Some(c) => c,
+                None => break,
+            };
 
-        while let Some(c) = self.input.next() {
             match c {
-                '"' => return Ok(Some(Token::String(string))),
+                '"' => {
+                    self.unpaired_surrogate(&mut string, &mut high_surrogate)?;
+                    return Ok(string.into_cow());
+                }
                 '\\' => {
-                    match self.input.next() {
+                    match self.bump() {
+                        Some('u') => {
+                            // Unicode escape sequence
+                            let unit = self.read_unicode_escape(char_start)?;
+                            match (high_surrogate, unit) {
+                                (Some((high, _)), 0xDC00..=0xDFFF) => {
+                                    high_surrogate = None;
+                                    let cp = 0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00);
+                                    string.push(char::from_u32(cp).unwrap());
+                                }
+                                _ => {
+                                    self.unpaired_surrogate(&mut string, &mut high_surrogate)?;
+                                    match unit {
+                                        0xD800..=0xDBFF => {
+                                            high_surrogate = Some((unit, char_start));
+                                        }
+                                        0xDC00..=0xDFFF => {
+                                            let mut low = Some((unit, char_start));
+                                            self.unpaired_surrogate(&mut string, &mut low)?;
+                                        }
+                                        _ => string.push(char::from_u32(unit).unwrap()),
+                                    }
+                                }
+                            }
+                        }
                         Some(escape_char) => {
+                            self.unpaired_surrogate(&mut string, &mut high_surrogate)?;
                             match escape_char {
                                 '"' => string.push('"'),
                                 '\\' => string.push('\\'),
@@ -99,158 +467,434 @@ impl<'a> Lexer<'a> {
                                 'n' => string.push('\n'),
                                 'r' => string.push('\r'),
                                 't' => string.push('\t'),
-                                'u' => {
-                                    // Unicode escape sequence
-                                    let mut code_point = String::new();
-                                    for _ in 0..4 {
-                                        if let Some(hex_digit) = self.input.next() {
-                                            code_point.push(hex_digit);
-                                        } else {
-                                            return Err(JsonError::InvalidUnicodeSequence(
-                                                code_point,
-                                            ));
-                                        }
-                                    }
-
-                                    match u32::from_str_radix(&code_point, 16) {
-                                        Ok(cp) => {
-                                            if let Some(unicode_char) = std::char::from_u32(cp) {
-                                                string.push(unicode_char);
-                                            } else {
-                                                return Err(JsonError::InvalidUnicodeSequence(
-                                                    code_point,
-                                                ));
-                                            }
-                                        }
-                                        Err(_) => {
-                                            return Err(JsonError::InvalidUnicodeSequence(
-                                                code_point,
-                                            ));
-                                        }
-                                    }
-                                }
                                 _ => {
                                     return Err(JsonError::InvalidEscapeSequence(
                                         escape_char.to_string(),
+                                        char_start,
                                     ));
                                 }
                             }
                         }
-                        None => return Err(JsonError::UnexpectedEof),
+                        None => return Err(JsonError::UnexpectedEof(self.position())),
                     }
                 }
-                _ => string.push(c),
+                c if self.options.strict => {
+                    return Err(JsonError::ControlCharacter(c, char_start));
+                }
+                _ => {
+                    self.unpaired_surrogate(&mut string, &mut high_surrogate)?;
+                    string.push_raw(c)
+                }
             }
+
+            self.check_string_length(&string)?;
         }
 
-        Err(JsonError::UnexpectedEof)
+        Err(JsonError::UnexpectedEof(self.position()))
     }
 
-    fn read_null(&mut self) -> Result<Option<Token>> {
-        let expected = "null";
-        self.input.next(); // Consume 'n'
+    fn check_string_length(&self, string: &Text) -> Result<()> {
+        match self.options.limits.max_string_bytes {
+            Some(max) if string.len() > max => Err(JsonError::LimitExceeded(
+                Limit::StringBytes(max),
+                self.token_start,
+            )),
+            _ => Ok(()),
+        }
+    }
 
-        for expected_char in expected.chars().skip(1) {
-            // Skip 'n' as we've already consumed it
-            match self.input.next() {
-                Some(c) if c == expected_char => continue,
-                Some(c) => return Err(JsonError::UnexpectedToken(c.to_string())),
-                None => return Err(JsonError::UnexpectedEof),
+    /// Read the four hex digits of a `\uXXXX` escape whose backslash is at
+    /// `escape_start`, returning the UTF-16 code unit.
+    fn read_unicode_escape(&mut self, escape_start: Position) -> Result<u32> {
+        let mut digits = [0u8; 4];
+        let mut len = 0;
+        while len < digits.len() {
+            match self.peek() {
+                Some(b) if b.is_ascii_hexdigit() => {
+                    self.bump_ascii(1);
+                    digits[len] = b;
+                    len += 1;
+                }
+                _ => {
+                    let digits = String::from_utf8_lossy(&digits[..len]).into_owned();
+                    return Err(JsonError::InvalidUnicodeSequence(digits, escape_start));
+                }
             }
         }
 
-        Ok(Some(Token::Null))
+        // Four hex digits always fit
+        let digits = std::str::from_utf8(&digits).unwrap();
+        Ok(u32::from_str_radix(digits, 16).unwrap())
     }
 
-    fn read_boolean(&mut self) -> Result<Option<Token>> {
-        match self.input.peek() {
-            Some(&'t') => {
-                let expected = "true";
-                for expected_char in expected.chars() {
-                    match self.input.next() {
-                        Some(c) if c == expected_char => continue,
-                        Some(c) => return Err(JsonError::UnexpectedToken(c.to_string())),
-                        None => return Err(JsonError::UnexpectedEof),
-                    }
+    /// Deal with a surrogate escape that has no partner, if there is one.
+    fn unpaired_surrogate(
+        &self,
+        string: &mut Text,
+        surrogate: &mut Option<(u32, Position)>,
+    ) -> Result<()> {
+        match surrogate.take() {
+            None => Ok(()),
+            Some((unit, position)) => match self.options.lone_surrogates {
+                LoneSurrogates::Error => Err(JsonError::LoneSurrogate(unit as u16, position)),
+                LoneSurrogates::Replace => {
+                    string.push(char::REPLACEMENT_CHARACTER);
+                    Ok(())
                 }
+            },
+        }
+    }
 
-                Ok(Some(Token::Boolean(true)))
-            }
-            Some(&'f') => {
-                let expected = "false";
-                for expected_char in expected.chars() {
-                    match self.input.next() {
-                        Some(c) if c == expected_char => continue,
-                        Some(c) => return Err(JsonError::UnexpectedToken(c.to_string())),
-                        None => return Err(JsonError::UnexpectedEof),
-                    }
-                }
+    /// Read the keyword `word`, whose first character is next.
+    fn read_literal(&mut self, word: &str) -> Result<()> {
+        let bytes = self.input.fill(word.len());
+        let matched = bytes
+            .iter()
+            .copied()
+            .zip(word.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        self.bump_ascii(matched);
 
-                Ok(Some(Token::Boolean(false)))
+        if matched == word.len() {
+            return Ok(());
+        }
+        match self.peek_char() {
+            Some(c) => Err(JsonError::UnexpectedToken(
+                Unexpected::Character(c),
+                self.position(),
+            )),
+            None if self.peek().is_some() => Err(self.invalid_utf8()),
+            None => Err(JsonError::UnexpectedEof(self.position())),
+        }
+    }
+
+    #[inline(always)]
+    fn read_number(&mut self) -> Result<Number> {
+        // Get the whole literal into the buffer, so it can be parsed in place
+        let mut needed = 1;
+        let scan = loop {
+            let bytes = self.input.fill(needed);
+            let scan = scan_number(bytes);
+            if scan.len == bytes.len() && bytes.len() >= needed {
+                needed = bytes.len() + 1;
+                continue;
             }
-            _ => Err(JsonError::UnexpectedToken(
-                "Expected 'true' or 'false'".to_string(),
+            break scan;
+        };
+
+        let number = if self.options.strict && scan.leading_zero {
+            Err(JsonError::InvalidNumber(
+                NumberError::LeadingZeros,
+                self.token_start,
+            ))
+        } else if scan.missing_digit {
+            let mut position = self.token_start;
+            position.offset += scan.len;
+            position.column += scan.len;
+            Err(JsonError::InvalidNumber(
+                NumberError::MissingDigit,
+                position,
+            ))
+        } else if self.skipping && !self.options.strict {
+            // Only strict mode looks at the value, to rule out infinity
+            Ok(Number::from(0u64))
+        } else if let Some(magnitude) = scan.integer {
+            Ok(match scan.negative {
+                true => Number::from(0i64.wrapping_sub_unsigned(magnitude)),
+                false => Number::from(magnitude),
+            })
+        } else if let Some(float) = scan.float
+            && !self.options.arbitrary_precision
+        {
+            Ok(Number::from(float))
+        } else {
+            self.number_literal(scan.len)
+        };
+
+        self.bump_ascii(scan.len);
+        number
+    }
+
+    /// Make a number of the `len` bytes of literal next in the input, for
+    /// one the scan couldn't work out the value of.
+    #[cold]
+    fn number_literal(&mut self, len: usize) -> Result<Number> {
+        let bytes = self.input.fill(len);
+        // The scan only takes in ASCII
+        let literal = std::str::from_utf8(&bytes[..len]).expect("ASCII");
+        // Keep integers exact instead of forcing everything through f64
+        match Number::from_literal(literal, self.options.arbitrary_precision) {
+            Some(num) if self.options.strict && !num.is_finite() => Err(JsonError::InvalidNumber(
+                NumberError::OutOfRange(literal.to_string()),
+                self.token_start,
+            )),
+            Some(num) => Ok(num),
+            None => Err(JsonError::InvalidNumber(
+                NumberError::Malformed(literal.to_string()),
+                self.token_start,
             )),
         }
     }
+}
+
+/// How many bytes at the start of `bytes` go into a string as they are:
+/// everything up to the first quote, backslash or control character. Also
+/// whether any of them are non-ASCII, and so need checking.
+fn scan_string(bytes: &[u8]) -> (usize, bool) {
+    const ONES: u64 = 0x0101_0101_0101_0101;
+    const HIGH: u64 = 0x8080_8080_8080_8080;
+    let is_special = |b: u8| b == b'"' || b == b'\\' || b < b' ';
 
-    fn read_number(&mut self) -> Result<Option<Token>> {
-        let mut number_str = String::new();
+    // Eight bytes at a time, flagging the high bit of each byte that is a
+    // quote, a backslash or below 0x20. Bytes after a flagged one may be
+    // flagged wrongly, but only the first matters
+    let mut non_ascii = 0;
+    let mut i = 0;
+    while let Some(chunk) = bytes.get(i..i + 8) {
+        let word = u64::from_le_bytes(chunk.try_into().unwrap());
+        let quote = word ^ (ONES * u64::from(b'"'));
+        let backslash = word ^ (ONES * u64::from(b'\\'));
+        let special = (quote.wrapping_sub(ONES) & !quote)
+            | (backslash.wrapping_sub(ONES) & !backslash)
+            | (word.wrapping_sub(ONES * 0x20) & !word);
+        let special = special & HIGH;
+        if special != 0 {
+            let n = special.trailing_zeros() as usize / 8;
+            let before = word & !(u64::MAX << (n * 8));
+            return (i + n, non_ascii | before & HIGH != 0);
+        }
+        non_ascii |= word & HIGH;
+        i += 8;
+    }
 
-        // Handle negative sign
-        if let Some(&'-') = self.input.peek() {
-            number_str.push(self.input.next().unwrap());
+    let mut non_ascii = non_ascii != 0;
+    while let Some(&b) = bytes.get(i) {
+        if is_special(b) {
+            break;
         }
+        non_ascii |= !b.is_ascii();
+        i += 1;
+    }
+    (i, non_ascii)
+}
+
+/// How much of a buffer a number literal at its start takes up.
+struct NumberScan {
+    /// Up to the end of the literal, or to where a digit was missing.
+    len: usize,
+    missing_digit: bool,
+    /// The integer part has more than one digit and starts with `0`.
+    leading_zero: bool,
+    negative: bool,
+    /// The magnitude of an integer that fits an `i64` or `u64`, worked out
+    /// as the digits go by.
+    integer: Option<u64>,
+    /// The value of any other literal short enough to be worked out exactly
+    /// the same way, rounded just as parsing its text would.
+    float: Option<f64>,
+}
 
-        // Integer part
-        self.read_digits(&mut number_str)?;
+/// Powers of ten that `f64` holds exactly.
+const POWERS_OF_TEN: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
 
-        // Fractional part
-        if let Some(&'.') = self.input.peek() {
-            number_str.push(self.input.next().unwrap());
-            self.read_digits(&mut number_str)?;
+/// Find the end of the number literal at the start of `bytes`, following
+/// the JSON grammar as far as it goes.
+fn scan_number(bytes: &[u8]) -> NumberScan {
+    // Count the digits from `from` on, folding them into `value`. It only
+    // overflows past 19 digits in all, which are then left to
+    // `Number::from_literal`
+    let digits = |from: usize, value: &mut u64| {
+        let mut end = from;
+        while let Some(&d) = bytes.get(end)
+            && d.is_ascii_digit()
+        {
+            *value = value.wrapping_mul(10).wrapping_add(u64::from(d - b'0'));
+            end += 1;
         }
+        end - from
+    };
 
-        // Exponent part
-        if let Some(&c) = self.input.peek() {
-            if c == 'e' || c == 'E' {
-                number_str.push(self.input.next().unwrap());
+    let negative = bytes.first() == Some(&b'-');
+    let missing = |len, leading_zero| NumberScan {
+        len,
+        missing_digit: true,
+        leading_zero,
+        negative,
+        integer: None,
+        float: None,
+    };
+    let mut len = usize::from(negative);
+    let mut value = 0;
+    let int_digits = digits(len, &mut value);
+    if int_digits == 0 {
+        return missing(len, false);
+    }
+    let leading_zero = bytes[len] == b'0' && int_digits > 1;
+    len += int_digits;
 
-                // Handle exponent sign
-                if let Some(&c) = self.input.peek() {
-                    if c == '+' || c == '-' {
-                        number_str.push(self.input.next().unwrap());
-                    }
-                }
+    if !matches!(bytes.get(len), Some(b'.' | b'e' | b'E')) {
+        let integer = match (negative, int_digits <= 19) {
+            (false, true) => Some(value),
+            // `-0` is a float, to keep its sign
+            (true, true) if value > 0 && value <= i64::MIN.unsigned_abs() => Some(value),
+            _ => None,
+        };
+        return NumberScan {
+            len,
+            missing_digit: false,
+            leading_zero,
+            negative,
+            integer,
+            float: None,
+        };
+    }
 
-                self.read_digits(&mut number_str)?;
-            }
+    let mut frac_digits = 0;
+    if bytes.get(len) == Some(&b'.') {
+        len += 1;
+        frac_digits = digits(len, &mut value);
+        if frac_digits == 0 {
+            return missing(len, leading_zero);
         }
+        len += frac_digits;
+    }
 
-        match number_str.parse::<f64>() {
-            Ok(num) => Ok(Some(Token::Number(num))),
-            Err(_) => Err(JsonError::InvalidNumber(number_str)),
+    let mut exponent = 0;
+    if let Some(b'e' | b'E') = bytes.get(len) {
+        len += 1;
+        let exp_negative = bytes.get(len) == Some(&b'-');
+        if let Some(b'+' | b'-') = bytes.get(len) {
+            len += 1;
         }
+        let mut exp = 0;
+        let exp_digits = digits(len, &mut exp);
+        if exp_digits == 0 {
+            return missing(len, leading_zero);
+        }
+        len += exp_digits;
+        // Longer exponents are far out of range below anyway, and may not fit
+        let exp = if exp_digits > 4 { 99_999 } else { exp as i64 };
+        exponent = if exp_negative { -exp } else { exp };
     }
 
-    fn read_digits(&mut self, number_str: &mut String) -> Result<()> {
-        let mut has_digit = false;
+    // With both the digits and the power of ten exact in an `f64`, one
+    // multiplication or division rounds correctly
+    let exponent = exponent - frac_digits as i64;
+    let float = match POWERS_OF_TEN.get(exponent.unsigned_abs() as usize) {
+        Some(&power) if int_digits + frac_digits <= 19 && value < 1 << 53 => {
+            let magnitude = match exponent < 0 {
+                true => value as f64 / power,
+                false => value as f64 * power,
+            };
+            Some(if negative { -magnitude } else { magnitude })
+        }
+        _ => None,
+    };
 
-        while let Some(&c) = self.input.peek() {
-            if c.is_digit(10) {
-                has_digit = true;
-                number_str.push(self.input.next().unwrap());
-            } else {
-                break;
-            }
+    NumberScan {
+        len,
+        missing_digit: false,
+        leading_zero,
+        negative,
+        integer: None,
+        float,
+    }
+}
+
+/// The text of a string token as it is read: a slice of the input for as
+/// long as the two match, copied out at the first escape.
+enum Text<'a> {
+    /// The rest of the input from the start of the text, and how many bytes
+    /// of it the text takes up so far.
+    Str(&'a str, usize),
+    /// The same from bytes, the text all checked to be valid UTF-8.
+    Borrowed(&'a [u8], usize),
+    Owned(String),
+    /// Only the length of a string being skipped over.
+    Skipped(usize),
+}
+
+impl<'a> Text<'a> {
+    #[inline]
+    fn new<S: Source<'a>>(input: &S) -> Self {
+        if let Some(rest) = input.rest() {
+            return Text::Str(rest, 0);
         }
+        match input.rest_bytes() {
+            Some(rest) => Text::Borrowed(rest, 0),
+            None => Text::Owned(String::new()),
+        }
+    }
 
-        if !has_digit {
-            return Err(JsonError::InvalidNumber(
-                "Expected at least one digit".to_string(),
-            ));
+    #[inline]
+    fn len(&self) -> usize {
+        match self {
+            Text::Str(_, len) | Text::Borrowed(_, len) | Text::Skipped(len) => *len,
+            Text::Owned(string) => string.len(),
         }
+    }
 
-        Ok(())
+    /// Add the next stretch of the input, as it is.
+    #[inline]
+    fn push_str_raw(&mut self, text: &str) {
+        match self {
+            Text::Str(_, len) | Text::Borrowed(_, len) | Text::Skipped(len) => *len += text.len(),
+            Text::Owned(string) => string.push_str(text),
+        }
+    }
+
+    /// Add the next stretch of the input, known to be ASCII.
+    #[inline]
+    fn push_ascii(&mut self, bytes: &[u8]) {
+        match self {
+            Text::Str(_, len) | Text::Borrowed(_, len) | Text::Skipped(len) => *len += bytes.len(),
+            Text::Owned(string) => string.push_str(std::str::from_utf8(bytes).expect("ASCII")),
+        }
+    }
+
+    /// Add the next character of the input, as it is.
+    #[inline]
+    fn push_raw(&mut self, c: char) {
+        match self {
+            Text::Str(_, len) | Text::Borrowed(_, len) | Text::Skipped(len) => *len += c.len_utf8(),
+            Text::Owned(string) => string.push(c),
+        }
+    }
+
+    /// Add a character that differs from the input, such as an escape.
+    fn push(&mut self, c: char) {
+        match self {
+            Text::Str(rest, len) => {
+                let mut string = rest[..*len].to_string();
+                string.push(c);
+                *self = Text::Owned(string);
+            }
+            Text::Borrowed(rest, len) => {
+                let mut string = borrowed_str(rest, *len).to_string();
+                string.push(c);
+                *self = Text::Owned(string);
+            }
+            Text::Owned(string) => string.push(c),
+            Text::Skipped(len) => *len += c.len_utf8(),
+        }
     }
+
+    #[inline]
+    fn into_cow(self) -> Cow<'a, str> {
+        match self {
+            Text::Str(rest, len) => Cow::Borrowed(&rest[..len]),
+            Text::Borrowed(rest, len) => Cow::Borrowed(borrowed_str(rest, len)),
+            Text::Owned(string) => Cow::Owned(string),
+            Text::Skipped(_) => Cow::Borrowed(""),
+        }
+    }
+}
+
+/// The first `len` bytes of `rest`, which the lexer has checked.
+fn borrowed_str(rest: &[u8], len: usize) -> &str {
+    std::str::from_utf8(&rest[..len]).expect("strings are checked as they are read")
 }
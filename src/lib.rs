@@ -1,12 +1,53 @@
+pub mod borrowed;
+pub mod diagnostic;
+pub mod diff;
+pub mod document;
 pub mod error;
+pub mod events;
 pub mod json;
+pub mod jsonpath;
 pub mod lexer;
+pub mod map;
+pub mod merge;
+pub mod number;
+pub mod options;
 pub mod parser;
+pub mod patch;
+pub mod pointer;
+pub mod pretty;
+pub mod push;
+pub mod raw;
+mod regex;
+pub mod schema;
+pub mod serializer;
+pub mod source;
 
 // Re-export main types for easier access
-pub use error::{JsonError, Result};
+pub use borrowed::BorrowedValue;
+pub use diagnostic::Diagnostic;
+pub use diff::{Change, Diff, diff};
+pub use document::{Document, Node};
+pub use error::{
+    JsonError, Limit, NumberError, PatchError, PathError, PointerError, Position, Result,
+    SchemaError, SerializeError, Unexpected, ValidationError,
+};
+pub use events::{Event, EventParser, Scalar};
 pub use json::JsonValue;
-pub use parser::parse_json;
+pub use jsonpath::{JsonPath, PathMatch};
+pub use map::Map;
+pub use merge::{create_merge_patch, merge_patch};
+pub use number::Number;
+pub use options::{DuplicateKeys, LoneSurrogates, ParseLimits, ParseOptions};
+pub use parser::{
+    Parser, parse_borrowed, parse_borrowed_with, parse_json, parse_json_recovering,
+    parse_json_with, parse_reader, parse_reader_with, parse_slice, parse_slice_with,
+};
+pub use patch::{Patch, PatchOperation};
+pub use pretty::{PrettyConfig, to_string_pretty, to_writer_pretty};
+pub use push::PushParser;
+pub use raw::{RawValue, parse_raw, parse_raw_with};
+pub use schema::Schema;
+pub use serializer::{to_string, to_writer};
 
 #[cfg(test)]
 mod tests {
@@ -38,17 +79,17 @@ mod tests {
         let input = "123";
         let result = parse_json(input);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), JsonValue::Number(123.0));
+        assert_eq!(result.unwrap(), JsonValue::Number(123.into()));
 
         let input = "-123.456";
         let result = parse_json(input);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), JsonValue::Number(-123.456));
+        assert_eq!(result.unwrap(), JsonValue::Number((-123.456).into()));
 
         let input = "1.23e4";
         let result = parse_json(input);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), JsonValue::Number(12300.0));
+        assert_eq!(result.unwrap(), JsonValue::Number(12300.0.into()));
     }
 
     #[test]
@@ -75,9 +116,9 @@ mod tests {
 
         if let JsonValue::Array(arr) = result.unwrap() {
             assert_eq!(arr.len(), 3);
-            assert_eq!(arr[0], JsonValue::Number(1.0));
-            assert_eq!(arr[1], JsonValue::Number(2.0));
-            assert_eq!(arr[2], JsonValue::Number(3.0));
+            assert_eq!(arr[0], JsonValue::Number(1.into()));
+            assert_eq!(arr[1], JsonValue::Number(2.into()));
+            assert_eq!(arr[2], JsonValue::Number(3.into()));
         } else {
             panic!("Expected array");
         }
@@ -88,7 +129,7 @@ mod tests {
 
         if let JsonValue::Array(arr) = result.unwrap() {
             assert_eq!(arr.len(), 4);
-            assert_eq!(arr[0], JsonValue::Number(1.0));
+            assert_eq!(arr[0], JsonValue::Number(1.into()));
             assert_eq!(arr[1], JsonValue::String("hello".to_string()));
             assert_eq!(arr[2], JsonValue::Boolean(true));
             assert_eq!(arr[3], JsonValue::Null);
@@ -109,7 +150,7 @@ mod tests {
                 obj.get("name"),
                 Some(&JsonValue::String("John".to_string()))
             );
-            assert_eq!(obj.get("age"), Some(&JsonValue::Number(30.0)));
+            assert_eq!(obj.get("age"), Some(&JsonValue::Number(30.into())));
         } else {
             panic!("Expected object");
         }
@@ -182,4 +223,1672 @@ mod tests {
         let result = parse_json(input);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_error_positions() {
+        let input = "{\n  \"a\": 1,\n  \"b\" 2\n}";
+        let err = parse_json(input).unwrap_err();
+        assert_eq!(
+            err.position(),
+            Position {
+                offset: 18,
+                line: 3,
+                column: 7
+            }
+        );
+        assert!(err.to_string().ends_with("at line 3, column 7"));
+
+        let err = parse_json("[1, \"\\x\"]").unwrap_err();
+        assert!(matches!(err, JsonError::InvalidEscapeSequence(_, _)));
+        assert_eq!(err.position().column, 6);
+
+        let err = parse_json("[1, 2").unwrap_err();
+        assert!(matches!(err, JsonError::UnexpectedEof(_)));
+        assert_eq!(err.position().offset, 5);
+    }
+
+    #[test]
+    fn test_diagnostic_rendering() {
+        let input = "{\n  \"a\": 1\n  \"b\": 2\n}";
+        let err = parse_json(input).unwrap_err();
+        let report = Diagnostic::new(input, &err)
+            .with_file_name("config.json")
+            .to_string();
+
+        assert_eq!(
+            report,
+            "error: expected ',' or '}' after object value, got string \"b\"\n \
+             --> config.json:3:3\n  \
+             |\n\
+             3 |   \"b\": 2\n  \
+             |   ^^^ did you forget a comma?\n"
+        );
+
+        // Just the line the error is on renders the same
+        let line = Diagnostic::for_line("  \"b\": 2\n", 11, &err).with_file_name("config.json");
+        assert_eq!(line.to_string(), report);
+    }
+
+    #[test]
+    fn test_recovering_parse() {
+        let input = r#"{"a": 1 "b": [1,, 3], "c": tru, "d": "x\q", "e": 5,}"#;
+        let (value, errors) = parse_json_recovering(input);
+
+        assert_eq!(errors.len(), 5);
+        assert!(errors[0].to_string().contains("after object value"));
+        assert!(matches!(errors[3], JsonError::InvalidEscapeSequence(_, _)));
+        assert!(errors[4].to_string().contains("Trailing comma"));
+
+        if let JsonValue::Object(obj) = value {
+            assert_eq!(obj.len(), 5);
+            assert_eq!(obj.get("a"), Some(&JsonValue::Number(1.into())));
+            assert_eq!(
+                obj.get("b"),
+                Some(&JsonValue::Array(vec![
+                    JsonValue::Number(1.into()),
+                    JsonValue::Null,
+                    JsonValue::Number(3.into())
+                ]))
+            );
+            assert_eq!(obj.get("c"), Some(&JsonValue::Null));
+            assert_eq!(obj.get("d"), Some(&JsonValue::Null));
+            assert_eq!(obj.get("e"), Some(&JsonValue::Number(5.into())));
+        } else {
+            panic!("Expected object");
+        }
+
+        let (value, errors) = parse_json_recovering("[1, 2");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            value,
+            JsonValue::Array(vec![
+                JsonValue::Number(1.into()),
+                JsonValue::Number(2.into())
+            ])
+        );
+
+        let (_, errors) = parse_json_recovering(r#"{"valid": [true, false]}"#);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_object_preserves_key_order() {
+        let input = r#"{"zeta": 1, "alpha": 2, "mid": {"y": true, "x": false}}"#;
+        let value = parse_json(input).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"zeta": 1, "alpha": 2, "mid": {"y": true, "x": false}}"#
+        );
+
+        let JsonValue::Object(mut obj) = value else {
+            panic!("Expected object");
+        };
+        let keys: Vec<&String> = obj.keys().collect();
+        assert_eq!(keys, ["zeta", "alpha", "mid"]);
+
+        // Replacing keeps the position, removing shifts later members down
+        obj.insert("zeta".to_string(), JsonValue::Null);
+        assert_eq!(obj.remove("alpha"), Some(JsonValue::Number(2.into())));
+        obj.insert("new".to_string(), JsonValue::Boolean(true));
+        let keys: Vec<&String> = obj.keys().collect();
+        assert_eq!(keys, ["zeta", "mid", "new"]);
+        assert_eq!(obj["zeta"], JsonValue::Null);
+
+        obj.sort_keys();
+        let keys: Vec<&String> = obj.keys().collect();
+        assert_eq!(keys, ["mid", "new", "zeta"]);
+
+        // Large objects switch to a hash index
+        let mut big = Map::new();
+        for i in (0..20).rev() {
+            big.insert(format!("k{}", i), JsonValue::Number(i.into()));
+        }
+        big.remove("k10");
+        assert_eq!(big.len(), 19);
+        assert_eq!(big.get("k3"), Some(&JsonValue::Number(3.into())));
+        assert_eq!(big.get_index_of("k9"), Some(9));
+        assert_eq!(big.keys().next().unwrap(), "k19");
+        big.shift_insert(0, "first".to_string(), JsonValue::Null);
+        for (i, key) in big.keys().enumerate() {
+            assert_eq!(big.get_index_of(key), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_lossless_numbers() {
+        // Snowflake IDs are well beyond 2^53
+        let value = parse_json("[1234567890123456789, -9223372036854775808, 18446744073709551615]")
+            .unwrap();
+        assert_eq!(
+            value.to_string(),
+            "[1234567890123456789, -9223372036854775808, 18446744073709551615]"
+        );
+        if let JsonValue::Array(arr) = &value {
+            assert_eq!(
+                arr[0],
+                JsonValue::Number(Number::from(1234567890123456789u64))
+            );
+            if let JsonValue::Number(n) = &arr[1] {
+                assert_eq!(n.as_i64(), Some(i64::MIN));
+                assert!(!n.is_u64());
+            }
+        }
+
+        // Too big for u64: falls back to f64 unless arbitrary precision is on
+        let input = "[18446744073709551616, 0.1000000000000000055511151231257827]";
+        let value = parse_json(input).unwrap();
+        assert_eq!(value.to_string(), "[1.8446744073709552e19, 0.1]");
+
+        let options = ParseOptions {
+            arbitrary_precision: true,
+            ..ParseOptions::default()
+        };
+        let value = parse_json_with(input, options).unwrap();
+        assert_eq!(value.to_string(), input);
+        if let JsonValue::Array(arr) = &value
+            && let JsonValue::Number(n) = &arr[0]
+        {
+            assert_eq!(n.as_raw_str(), Some("18446744073709551616"));
+            assert_eq!(n.as_f64(), 18446744073709551616.0);
+        }
+    }
+
+    #[test]
+    fn test_number_equality() {
+        let number = |text: &str, arbitrary_precision| {
+            let options = ParseOptions {
+                arbitrary_precision,
+                ..ParseOptions::default()
+            };
+            parse_json_with(text, options).unwrap()
+        };
+
+        // Whatever a number is held as, printing and reparsing gives it back
+        let input = r#"[0, -0, -00, 1, 1.0, -1.5e-3, 100e-2, 18446744073709551616, -9223372036854775809,
+            0.1000000000000000055511151231257827, 123456789012345678901234567890e-30]"#;
+        for arbitrary_precision in [false, true] {
+            let value = number(input, arbitrary_precision);
+            assert_eq!(number(&value.to_string(), arbitrary_precision), value);
+        }
+        assert_eq!(number("[-0, -00]", false).to_string(), "[-0.0, -0.0]");
+        let value = number("[1e999, -1E-999]", true);
+        assert_eq!(number(&value.to_string(), true), value);
+
+        // Numbers are equal by value, exactly unless both are floats
+        assert_eq!(number("1", false), number("1.0", false));
+        assert_ne!(
+            number("9007199254740993", false),
+            number("9007199254740992.0", false)
+        );
+        assert_eq!(
+            number("9007199254740992.0", false),
+            number("9007199254740992", false)
+        );
+        assert_eq!(
+            number("1152921504606846976.0", false),
+            number("1152921504606846976", false)
+        );
+        assert_ne!(
+            number("18446744073709551615", false),
+            number("18446744073709551615.0", false)
+        );
+        assert_ne!(number("2", false), number("2.5", false));
+        assert_eq!(number("2.5", false), number("25e-1", true));
+        assert_eq!(number("1e400", true), number("1e400", true));
+        assert_ne!(number("1e400", true), number("1e300", false));
+        assert_eq!(number("1.0", true), number("1", false));
+        assert_eq!(number("100e-2", true), number("1", true));
+        assert_eq!(number("-0.0", true), number("0", true));
+        assert_eq!(number("1e999", true), number("10E+998", true));
+        assert_ne!(number("1e999", true), number("1e998", true));
+        assert_ne!(
+            number("18446744073709551616", true),
+            number("18446744073709551617", true)
+        );
+        assert_eq!(
+            number("18446744073709551616", true),
+            number("18446744073709551616", false)
+        );
+        assert_ne!(number("-1.5", true), number("1.5", true));
+        assert_eq!(number("[-0.05]", true), number("[-5e-2]", true));
+    }
+
+    #[test]
+    fn test_serializer_round_trip() {
+        let mut obj = Map::new();
+        obj.insert("quote\"key\\".to_string(), JsonValue::Null);
+        obj.insert(
+            "text".to_string(),
+            JsonValue::String("line\nbreak \"quoted\" back\\slash \t\u{1}\u{1f} é ☃".to_string()),
+        );
+        obj.insert(
+            "numbers".to_string(),
+            JsonValue::Array(vec![
+                JsonValue::Number(0.1.into()),
+                JsonValue::Number(1.0.into()),
+                JsonValue::Number(1e300.into()),
+                JsonValue::Number((-2.5e-8).into()),
+                JsonValue::Number(u64::MAX.into()),
+                JsonValue::Number(i64::MIN.into()),
+            ]),
+        );
+        let value = JsonValue::Object(obj);
+
+        let text = to_string(&value).unwrap();
+        assert_eq!(
+            text,
+            r#"{"quote\"key\\":null,"text":"line\nbreak \"quoted\" back\\slash \t\u0001\u001f é ☃","numbers":[0.1,1.0,1e300,-2.5e-8,18446744073709551615,-9223372036854775808]}"#
+        );
+        assert_eq!(parse_json(&text).unwrap(), value);
+        assert_eq!(parse_json(&value.to_string()).unwrap(), value);
+
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &value).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), text);
+    }
+
+    #[test]
+    fn test_serializer_non_finite() {
+        use serializer::{NonFiniteFloat, SerializeOptions, to_string_with};
+
+        let value = JsonValue::Array(vec![JsonValue::Number(f64::NAN.into())]);
+        assert!(matches!(
+            to_string(&value),
+            Err(SerializeError::NonFiniteNumber(_))
+        ));
+
+        let options = SerializeOptions {
+            non_finite: NonFiniteFloat::Null,
+        };
+        assert_eq!(to_string_with(&value, options).unwrap(), "[null]");
+        assert_eq!(value.to_string(), "[null]");
+    }
+
+    #[test]
+    fn test_pretty_printing() {
+        let value = parse_json(
+            r#"{"name": "demo", "tags": ["a", "b"], "nested": {"deep": [1, 2, {"x": null}]}, "empty": []}"#,
+        )
+        .unwrap();
+
+        let config = PrettyConfig {
+            max_width: 32,
+            ..PrettyConfig::default()
+        };
+        let text = to_string_pretty(&value, &config).unwrap();
+        assert_eq!(
+            text,
+            "{\n  \"name\": \"demo\",\n  \"tags\": [\"a\", \"b\"],\n  \"nested\": {\n    \"deep\": [1, 2, {\"x\": null}]\n  },\n  \"empty\": []\n}"
+        );
+        assert_eq!(parse_json(&text).unwrap(), value);
+
+        // Only the outermost object is too wide for the default 80 columns
+        assert_eq!(
+            format!("{:#}", value),
+            "{\n  \"name\": \"demo\",\n  \"tags\": [\"a\", \"b\"],\n  \"nested\": {\"deep\": [1, 2, {\"x\": null}]},\n  \"empty\": []\n}"
+        );
+
+        let config = PrettyConfig {
+            max_width: 0,
+            use_tabs: true,
+            space_after_colon: false,
+            sort_keys: true,
+            trailing_newline: true,
+            ..PrettyConfig::default()
+        };
+        let value = parse_json(r#"{"b": [1], "a": {}}"#).unwrap();
+        assert_eq!(
+            to_string_pretty(&value, &config).unwrap(),
+            "{\n\t\"a\":{},\n\t\"b\":[\n\t\t1\n\t]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_surrogate_pairs() {
+        let value = parse_json(r#"["\ud83d\ude00", "a\uD834\uDD1Eb", "\u00e9"]"#).unwrap();
+        assert_eq!(
+            value,
+            JsonValue::Array(vec![
+                JsonValue::String("😀".to_string()),
+                JsonValue::String("a𝄞b".to_string()),
+                JsonValue::String("é".to_string()),
+            ])
+        );
+
+        for input in [
+            r#""\ud83d""#,
+            r#""\ud83dx""#,
+            r#""\ud83d\n""#,
+            r#""\ude00""#,
+            r#""\ud83d\ud83d""#,
+        ] {
+            let err = parse_json(input).unwrap_err();
+            assert!(matches!(err, JsonError::LoneSurrogate(_, _)), "{}", input);
+        }
+
+        let err = parse_json(r#"["ok", "x\ude00"]"#).unwrap_err();
+        assert!(matches!(err, JsonError::LoneSurrogate(0xDE00, _)));
+        assert_eq!(err.position().column, 10);
+
+        let options = ParseOptions {
+            lone_surrogates: LoneSurrogates::Replace,
+            ..ParseOptions::default()
+        };
+        let value = parse_json_with(r#""\ud83d😀!\udc00""#, options).unwrap();
+        assert_eq!(value, JsonValue::String("\u{FFFD}😀!\u{FFFD}".to_string()));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let strict = ParseOptions {
+            strict: true,
+            ..ParseOptions::default()
+        };
+
+        // The default mode is forgiving about all of these
+        for input in ["[012]", "\"a\tb\"", "[1,\u{a0}2]", "1e999"] {
+            assert!(parse_json(input).is_ok(), "{:?}", input);
+            assert!(parse_json_with(input, strict).is_err(), "{:?}", input);
+        }
+
+        let err = parse_json_with("[1, -012]", strict).unwrap_err();
+        assert!(matches!(
+            err,
+            JsonError::InvalidNumber(NumberError::LeadingZeros, _)
+        ));
+        assert_eq!(err.position().column, 5);
+
+        let err = parse_json_with("\"a\nb\"", strict).unwrap_err();
+        assert!(matches!(err, JsonError::ControlCharacter('\n', _)));
+        assert_eq!(err.position().column, 3);
+
+        let err = parse_json_with("[\u{a0}1]", strict).unwrap_err();
+        assert!(matches!(
+            err,
+            JsonError::UnexpectedToken(Unexpected::Character('\u{a0}'), _)
+        ));
+        let err = parse_json("[1 2]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected token: Expected ',' or ']' after array element, got number 2 at line 1, column 4"
+        );
+
+        let options = ParseOptions {
+            arbitrary_precision: true,
+            ..strict
+        };
+        let value = parse_json_with("1e999", options).unwrap();
+        assert_eq!(value.to_string(), "1e999");
+
+        let value = parse_json_with(" [0, -0.5e+1, \"\\u0000\"]\r\n", strict).unwrap();
+        assert_eq!(value.to_string(), "[0, -5.0, \"\\u0000\"]");
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let input = format!("{}{}", "[".repeat(129), "]".repeat(129));
+        let err = parse_json(&input).unwrap_err();
+        assert!(matches!(err, JsonError::DepthLimitExceeded(129, _)));
+        assert_eq!(err.position().column, 129);
+        assert!(parse_json(&input[1..input.len() - 1]).is_ok());
+
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        let (value, errors) =
+            Parser::with_options(r#"[[1], [[2, {"a": 3}]], {"b": {}}, 4]"#, options)
+                .parse_recovering();
+        assert_eq!(value.to_string(), r#"[[1], [null], {"b": null}, 4]"#);
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], JsonError::DepthLimitExceeded(3, _)));
+
+        // A raised limit copes with documents far too deep to parse recursively
+        let options = ParseOptions {
+            max_depth: 100_000,
+            ..ParseOptions::default()
+        };
+        let input = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        let value = parse_json_with(&input, options).unwrap();
+        let mut depth = 0;
+        let mut inner = &value;
+        while let JsonValue::Array(items) = inner {
+            depth += 1;
+            match items.first() {
+                Some(item) => inner = item,
+                None => break,
+            }
+        }
+        assert_eq!(depth, 100_000);
+
+        // ...and so does everything else done with them
+        let copy = value.clone();
+        assert_eq!(copy, value);
+        let shallower = parse_json_with(&input[1..input.len() - 1], options).unwrap();
+        assert_ne!(copy, shallower);
+        assert_eq!(to_string(&value).unwrap(), input);
+        assert_eq!(value.to_string(), input);
+        let config = PrettyConfig {
+            indent_width: 0,
+            ..PrettyConfig::default()
+        };
+        let pretty = to_string_pretty(&value, &config).unwrap();
+        assert!(pretty.starts_with("[\n[\n"));
+        let reparsed = parse_json_with(&pretty, options).unwrap();
+        assert_eq!(reparsed, value);
+        for value in [copy, shallower, reparsed, value] {
+            value.drop_iteratively();
+        }
+
+        let borrowed = parse_borrowed_with(&input, options).unwrap();
+        let copy = borrowed.clone();
+        assert_eq!(copy, borrowed);
+        copy.drop_iteratively();
+        let owned = borrowed.into_owned();
+        assert_eq!(owned.to_string(), input);
+        owned.drop_iteratively();
+
+        // A parse that fails past the end of a document that deep doesn't
+        // overflow the stack dropping it either
+        let trailing = format!("{} x", input);
+        assert!(parse_json_with(&trailing, options).is_err());
+        let unclosed = &input[..input.len() - 1];
+        assert!(parse_json_with(unclosed, options).is_err());
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let limited = |limits| ParseOptions {
+            limits,
+            ..ParseOptions::default()
+        };
+        let input = r#"{"name": "abcdef", "tags": [1, 2, 3], "n": null}"#;
+        assert!(parse_json_with(input, limited(ParseLimits::default())).is_ok());
+
+        let cases = [
+            (
+                ParseLimits {
+                    max_input_bytes: Some(20),
+                    ..ParseLimits::default()
+                },
+                Limit::InputBytes(20),
+                20,
+            ),
+            (
+                ParseLimits {
+                    max_string_bytes: Some(5),
+                    ..ParseLimits::default()
+                },
+                Limit::StringBytes(5),
+                10,
+            ),
+            (
+                ParseLimits {
+                    max_array_len: Some(2),
+                    ..ParseLimits::default()
+                },
+                Limit::ArrayLength(2),
+                35,
+            ),
+            (
+                ParseLimits {
+                    max_object_members: Some(2),
+                    ..ParseLimits::default()
+                },
+                Limit::ObjectMembers(2),
+                39,
+            ),
+        ];
+        for (limits, expected, column) in cases {
+            match parse_json_with(input, limited(limits)) {
+                Err(JsonError::LimitExceeded(limit, pos)) => {
+                    assert_eq!(limit, expected);
+                    assert_eq!(pos.column, column, "{:?}", limit);
+                }
+                other => panic!("expected {:?} to be hit, got {:?}", expected, other),
+            }
+        }
+
+        // Sources stop reading just past the input limit, so an endless
+        // string from a reader fails there rather than at the end
+        struct Endless(usize);
+
+        impl std::io::Read for Endless {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                // `["` and then nothing but `x`, assuming reads of two bytes or more
+                buf.fill(b'x');
+                if self.0 == 0 {
+                    buf[..2].copy_from_slice(b"[\"");
+                }
+                self.0 += buf.len();
+                Ok(buf.len())
+            }
+        }
+        let input_limit = |max| {
+            limited(ParseLimits {
+                max_input_bytes: Some(max),
+                ..ParseLimits::default()
+            })
+        };
+        let mut reader = Endless(0);
+        let err = parse_reader_with(&mut reader, input_limit(100_000)).unwrap_err();
+        assert!(matches!(
+            err,
+            JsonError::LimitExceeded(Limit::InputBytes(100_000), _)
+        ));
+        assert_eq!(reader.0, 100_001);
+
+        let mut parser = PushParser::with_options(input_limit(10));
+        parser.feed(format!("[\"{}", "x".repeat(1_000_000)).as_bytes());
+        parser.feed(b"\"]");
+        parser.finish();
+        assert!(matches!(
+            parser.next_value(),
+            Err(JsonError::LimitExceeded(Limit::InputBytes(10), _))
+        ));
+        // A limit as high as it goes is no limit at all
+        let input = r#"{"name": "abcdef", "tags": [1, 2, 3]}"#;
+        let value = parse_reader_with(input.as_bytes(), input_limit(usize::MAX)).unwrap();
+        assert_eq!(value, parse_json(input).unwrap());
+        let mut parser = PushParser::with_options(input_limit(usize::MAX));
+        parser.feed(&input.as_bytes()[..10]);
+        parser.feed(&input.as_bytes()[10..]);
+        parser.finish();
+        assert_eq!(parser.next_value().unwrap(), Some(value));
+        let err = parse_json_with("[\"\u{e9}\u{e9}\u{e9}\"]", input_limit(4)).unwrap_err();
+        assert!(matches!(err, JsonError::LimitExceeded(..)));
+        assert_eq!(err.position().column, 2);
+
+        // Limits end the parse even when recovering from other errors
+        let options = limited(ParseLimits {
+            max_array_len: Some(3),
+            ..ParseLimits::default()
+        });
+        let (_, errors) = Parser::with_options("[1 2, 3, 4, 5, 6]", options).parse_recovering();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[1],
+            JsonError::LimitExceeded(Limit::ArrayLength(3), _)
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let input = r#"{"a": 1, "b": [2], "a": 3, "b": 4, "a": [5]}"#;
+        let parse = |duplicate_keys| {
+            let options = ParseOptions {
+                duplicate_keys,
+                ..ParseOptions::default()
+            };
+            parse_json_with(input, options).map(|value| value.to_string())
+        };
+
+        assert_eq!(
+            parse(DuplicateKeys::LastWins).unwrap(),
+            r#"{"a": [5], "b": 4}"#
+        );
+        assert_eq!(
+            parse(DuplicateKeys::FirstWins).unwrap(),
+            r#"{"a": 1, "b": [2]}"#
+        );
+        assert_eq!(
+            parse(DuplicateKeys::CollectAll).unwrap(),
+            r#"{"a": [1, 3, [5]], "b": [[2], 4]}"#
+        );
+
+        match parse(DuplicateKeys::Error) {
+            Err(JsonError::DuplicateKey(key, first, second)) => {
+                assert_eq!(key, "a");
+                assert_eq!(first.column, 2);
+                assert_eq!(second.column, 20);
+            }
+            other => panic!("expected a duplicate key error, got {:?}", other),
+        }
+
+        // Keys only clash within the same object
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::default()
+        };
+        assert!(parse_json_with(r#"{"a": {"a": 1}, "b": {"a": 2}}"#, options).is_ok());
+    }
+
+    #[test]
+    fn test_parse_reader() {
+        use std::io::{self, Read};
+
+        /// Hands out a few bytes per read so characters split across reads.
+        struct Trickle<'a>(&'a [u8], usize);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() && self.1 > 0 {
+                    return Err(io::Error::other("connection reset"));
+                }
+                let n = buf.len().min(self.0.len()).min(3);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let input = r#"{"name": "Zoë 😀", "values": [1.5, -20, true, null], "nested": {"a": "é"}}"#;
+        let value = parse_reader(Trickle(input.as_bytes(), 0)).unwrap();
+        assert_eq!(value, parse_json(input).unwrap());
+        assert_eq!(parse_reader(input.as_bytes()).unwrap(), value);
+
+        let err = parse_reader(&b"[\"ok\", \"\xe9t\xe9\"]"[..]).unwrap_err();
+        assert!(matches!(err, JsonError::Io(_, _)));
+        assert_eq!(err.position().column, 9);
+
+        // A failed read ends the parse even when recovering
+        let (_, errors) =
+            Parser::from_reader(Trickle(b"[1, 2", 1), ParseOptions::default()).parse_recovering();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], JsonError::Io(e, _) if e.to_string() == "connection reset"));
+    }
+
+    #[test]
+    fn test_parse_slice() {
+        use crate::lexer::{Lexer, Token};
+        use std::borrow::Cow;
+
+        let input = r#"{"name": "Zoë 😀", "values": [1.5, -20, true, null], "nested": {"a": "é"}}"#;
+        assert_eq!(
+            parse_slice(input.as_bytes()).unwrap(),
+            parse_json(input).unwrap()
+        );
+
+        // Only strings are checked, and that as they are read
+        let err = parse_slice(b"[\"ok\", \"\xe9t\xe9\"]").unwrap_err();
+        assert!(matches!(err, JsonError::Io(_, _)));
+        assert_eq!(err.position().column, 9);
+        let err = parse_slice(b"[1, \xff]").unwrap_err();
+        assert!(matches!(err, JsonError::Io(_, _)));
+        assert_eq!(err.position().column, 5);
+        // A character cut off by the end of the input
+        assert!(matches!(
+            parse_slice(b"[\"\xe2\x82"),
+            Err(JsonError::Io(_, _))
+        ));
+
+        // Strings without escapes still borrow from the bytes
+        let mut lexer = Lexer::from_slice("\"Zoë\"".as_bytes(), ParseOptions::default());
+        assert!(matches!(
+            lexer.next_token(),
+            Ok(Some(Token::String(Cow::Borrowed("Zoë"))))
+        ));
+    }
+
+    #[test]
+    fn test_event_parser() {
+        let input = "{\"a\": [1, \"x\"],\n \"b\": {}}";
+        let mut parser = EventParser::new(input);
+        let mut events = Vec::new();
+        while let Some((event, position)) = parser.next_event().unwrap() {
+            let event = match event {
+                Event::Key(key) => format!("Key({})", key),
+                Event::Value(scalar) => format!("Value({})", JsonValue::from(scalar)),
+                event => format!("{:?}", event),
+            };
+            events.push((event, position.line, position.column));
+        }
+        let expected = [
+            ("StartObject", 1, 1),
+            ("Key(a)", 1, 2),
+            ("StartArray", 1, 7),
+            ("Value(1)", 1, 8),
+            ("Value(\"x\")", 1, 11),
+            ("EndArray", 1, 14),
+            ("Key(b)", 2, 2),
+            ("StartObject", 2, 7),
+            ("EndObject", 2, 8),
+            ("EndObject", 2, 9),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(event, line, column)| (event.to_string(), line, column))
+            .collect();
+        assert_eq!(events, expected);
+
+        // No more events once the document, or an error, is reached
+        assert!(parser.next_event().unwrap().is_none());
+        let mut parser = EventParser::new("[1 2]");
+        assert_eq!(parser.next_event().unwrap().unwrap().0, Event::StartArray);
+        assert!(matches!(
+            parser.next_event().unwrap().unwrap().0,
+            Event::Value(Scalar::Number(_))
+        ));
+        assert!(parser.next_event().is_err());
+        assert!(parser.next_event().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_push_parser() {
+        let input = r#"{"name": "Zo\u00eb 😀", "values": [1.5e3, -20, true, null], "x": {}}"#;
+
+        // One byte at a time splits every escape, number and character
+        let mut parser = PushParser::new();
+        let mut value = None;
+        for byte in input.as_bytes() {
+            parser.feed(&[*byte]);
+            if let Some(v) = parser.next_value().unwrap() {
+                value = Some(v);
+            }
+        }
+        // The document is complete before the end of the input is known
+        assert_eq!(value.unwrap(), parse_json(input).unwrap());
+        parser.finish();
+        assert!(parser.next_value().unwrap().is_none());
+
+        // Events and their positions match the pull parser's
+        let mut expected = Vec::new();
+        let mut events = EventParser::new(input);
+        while let Some((event, position)) = events.next_event().unwrap() {
+            expected.push((format!("{:?}", event), position));
+        }
+        let mut parser = PushParser::new();
+        let mut actual = Vec::new();
+        for chunk in input.as_bytes().chunks(5) {
+            parser.feed(chunk);
+            while let Some((event, position)) = parser.next_event().unwrap() {
+                actual.push((format!("{:?}", event), position));
+            }
+        }
+        parser.finish();
+        while let Some((event, position)) = parser.next_event().unwrap() {
+            actual.push((format!("{:?}", event), position));
+        }
+        assert_eq!(actual, expected);
+
+        // A top-level number only ends with the input
+        let mut parser = PushParser::new();
+        parser.feed(b"12");
+        assert!(parser.next_value().unwrap().is_none());
+        parser.feed(b"34");
+        parser.finish();
+        assert_eq!(
+            parser.next_value().unwrap().unwrap(),
+            parse_json("1234").unwrap()
+        );
+
+        // Input cut short is only an error once it is known to be over
+        let mut parser = PushParser::new();
+        parser.feed(b"[1, \"\xc3");
+        assert!(parser.next_value().unwrap().is_none());
+        parser.finish();
+        assert!(matches!(parser.next_value(), Err(JsonError::Io(_, _))));
+
+        let mut parser = PushParser::new();
+        parser.feed(b"[1, 2");
+        parser.finish();
+        assert!(matches!(
+            parser.next_value(),
+            Err(JsonError::UnexpectedEof(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_borrowed() {
+        use std::borrow::Cow;
+
+        let input = r#"{"plain": "text", "escaped": "a\tb", "list": ["é", 1, null]}"#;
+        let value = parse_borrowed(input).unwrap();
+        let BorrowedValue::Object(members) = &value else {
+            panic!("expected an object, got {:?}", value);
+        };
+
+        // Strings and keys without escapes are slices of the input
+        assert!(members.keys().all(|key| matches!(key, Cow::Borrowed(_))));
+        assert!(matches!(
+            &members["plain"],
+            BorrowedValue::String(Cow::Borrowed("text"))
+        ));
+        assert!(matches!(&members["escaped"], BorrowedValue::String(Cow::Owned(s)) if s == "a\tb"));
+        let BorrowedValue::Array(list) = &members["list"] else {
+            panic!("expected an array");
+        };
+        assert!(matches!(
+            &list[0],
+            BorrowedValue::String(Cow::Borrowed("é"))
+        ));
+
+        assert_eq!(value.into_owned(), parse_json(input).unwrap());
+
+        // Options apply as they do to owned values
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::CollectAll,
+            ..ParseOptions::default()
+        };
+        let value = parse_borrowed_with(r#"{"a": 1, "a": "x"}"#, options).unwrap();
+        assert_eq!(
+            JsonValue::from(value),
+            parse_json(r#"{"a": [1, "x"]}"#).unwrap()
+        );
+        assert!(parse_borrowed("[1, 2").is_err());
+    }
+
+    #[test]
+    fn test_lexer_buffers() {
+        use crate::lexer::{Lexer, Token};
+        use crate::source::{ReaderSource, SliceSource, Source, StrSource};
+        use std::borrow::Cow;
+        use std::io::{self, Read};
+
+        /// Hands out a byte per read, so every token is split across reads.
+        struct OneByte<'a>(&'a [u8]);
+
+        impl Read for OneByte<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = buf.len().min(self.0.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        fn lex<'a, S: Source<'a>>(input: S) -> Vec<String> {
+            let mut lexer = Lexer::with_source(input, ParseOptions::default());
+            let mut tokens = Vec::new();
+            loop {
+                match lexer.next_token() {
+                    Ok(Some(token)) => tokens.push(format!("{} at {}", token, lexer.token_start())),
+                    Ok(None) => return tokens,
+                    Err(e) => tokens.push(e.to_string()),
+                }
+            }
+        }
+
+        let long = "x".repeat(20_000);
+        let inputs = [
+            r#"{"name": "Zoë 😀", "values": [1.5e-3, -20, true, false, null]}"#.to_string(),
+            "[\n  \"tab\\there\", \"\\ud83d\\ude00\",\u{A0}12345678901234567890]".to_string(),
+            format!("[\"{}\", \"{}é\"]", long, long),
+            "[1.e5, tru, \"bad \\q escape\", 007, \"unterminated".to_string(),
+        ];
+        for input in &inputs {
+            let expected = lex(StrSource::new(input));
+            assert_eq!(lex(SliceSource::new(input.as_bytes())), expected);
+            assert_eq!(lex(ReaderSource::new(OneByte(input.as_bytes()))), expected);
+            assert_eq!(lex(ReaderSource::new(input.as_bytes())), expected);
+        }
+
+        // Strings without escapes borrow from input held in memory
+        let mut lexer = Lexer::new(r#""plain" "esc\"aped""#);
+        assert!(matches!(
+            lexer.next_token(),
+            Ok(Some(Token::String(Cow::Borrowed("plain"))))
+        ));
+        assert!(matches!(
+            lexer.next_token(),
+            Ok(Some(Token::String(Cow::Owned(s)))) if s == "esc\"aped"
+        ));
+    }
+
+    #[test]
+    fn test_document() {
+        let input =
+            r#"{"name": "Zoë", "tags": ["a\nb", 2.5, true, null], "nested": {"empty": []}}"#;
+        let mut document = Document::new();
+        let root = document.parse(input).unwrap();
+        assert_eq!(root.to_value(), parse_json(input).unwrap());
+        assert_eq!(root.len(), 3);
+        assert_eq!(root.get("name").unwrap().as_str(), Some("Zoë"));
+        let tags = root.get("tags").unwrap();
+        assert_eq!(tags.element(0).unwrap().as_str(), Some("a\nb"));
+        assert_eq!(tags.element(1).unwrap().as_number().unwrap().as_f64(), 2.5);
+        assert_eq!(tags.element(2).unwrap().as_bool(), Some(true));
+        assert!(tags.element(3).unwrap().is_null());
+        assert!(tags.element(4).is_none());
+        assert!(root.get("nested").unwrap().get("empty").unwrap().is_empty());
+        let keys: Vec<_> = root.members().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["name", "tags", "nested"]);
+
+        // Parsing again replaces the document
+        let root = document.parse("[1, [2, 3]]").unwrap();
+        let flat: Vec<_> = root
+            .elements()
+            .flat_map(|e| {
+                if e.is_array() {
+                    e.elements().collect()
+                } else {
+                    vec![e]
+                }
+            })
+            .map(|e| e.as_number().unwrap().as_i64().unwrap())
+            .collect();
+        assert_eq!(flat, [1, 2, 3]);
+
+        // Duplicate keys follow the options, in small and large objects
+        let many: Vec<_> = (0..20).map(|i| format!(r#""k{}": {}"#, i, i)).collect();
+        for input in [
+            r#"{"a": 1, "b": {"a": 2}, "a": [3], "a": 4}"#.to_string(),
+            format!(
+                r#"{{{}, "k3": "again", "k19": {{"k3": 0}}, "k3": null}}"#,
+                many.join(", ")
+            ),
+        ] {
+            for duplicate_keys in [
+                DuplicateKeys::Error,
+                DuplicateKeys::FirstWins,
+                DuplicateKeys::LastWins,
+                DuplicateKeys::CollectAll,
+            ] {
+                let options = ParseOptions {
+                    duplicate_keys,
+                    ..ParseOptions::default()
+                };
+                let expected = parse_json_with(&input, options);
+                match document.parse_with(&input, options) {
+                    Ok(root) => assert_eq!(root.to_value(), expected.unwrap()),
+                    Err(e) => assert_eq!(e.to_string(), expected.unwrap_err().to_string()),
+                }
+            }
+        }
+
+        // A failed parse leaves the document empty
+        assert!(document.parse("[1, 2").is_err());
+        assert!(document.root().is_none());
+    }
+
+    #[test]
+    fn test_raw_value() {
+        let input = "  {\"id\": 7,\n \"name\": \"a\\u00e9\", \"data\": {\"x\" : [1,  2.50, {}]}, \"id\": 8 } ";
+        let raw = parse_raw(input).unwrap();
+        assert_eq!(raw.as_str(), input.trim());
+        assert_eq!(raw.parse().unwrap(), parse_json(input).unwrap());
+        assert_eq!(raw.to_string(), input.trim());
+
+        // Subtrees keep their exact text, and are only parsed on demand
+        let members: Vec<_> = raw
+            .members()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                "id=7",
+                r#"name="a\u00e9""#,
+                r#"data={"x" : [1,  2.50, {}]}"#,
+                "id=8"
+            ]
+        );
+        assert_eq!(raw.get("id").unwrap().as_str(), "8");
+        assert!(raw.get("missing").is_none());
+        let list = raw.get("data").unwrap().get("x").unwrap();
+        let elements: Vec<_> = list.elements().map(|e| e.as_str()).collect();
+        assert_eq!(elements, ["1", "2.50", "{}"]);
+        assert_eq!(
+            raw.get("name").unwrap().parse_borrowed().unwrap(),
+            BorrowedValue::String("aé".into())
+        );
+
+        // Scalars have no members or elements
+        let scalar = parse_raw(" true ").unwrap();
+        assert_eq!(scalar.as_str(), "true");
+        assert_eq!(scalar.members().count(), 0);
+        assert_eq!(scalar.elements().count(), 0);
+
+        // The whole text is checked, even though nothing is built
+        assert!(parse_raw(r#"{"a": [1, 2}"#).is_err());
+        assert!(parse_raw("[1] 2").is_err());
+        assert!(parse_raw("").is_err());
+        let strict = ParseOptions::strict();
+        assert!(parse_raw_with("[01]", strict).is_err());
+
+        // Skipped strings and numbers are checked as thoroughly as parsed ones
+        let invalid = [
+            r#"["bad \q escape"]"#,
+            r#"{"a": "\uD800"}"#,
+            r#"[["\u12"]]"#,
+            "[\"tab\there\"]",
+            "[1e999]",
+            "[1.]",
+        ];
+        for input in invalid {
+            assert_eq!(
+                parse_raw_with(input, strict).unwrap_err().to_string(),
+                parse_json_with(input, strict).unwrap_err().to_string()
+            );
+        }
+        let options = ParseOptions {
+            limits: ParseLimits {
+                max_string_bytes: Some(3),
+                ..ParseLimits::default()
+            },
+            ..ParseOptions::default()
+        };
+        assert!(parse_raw_with(r#"[["a\n\u00e9"]]"#, options).is_err());
+        assert!(parse_raw_with(r#"[["a\u00e9"]]"#, options).is_ok());
+    }
+
+    #[test]
+    fn test_json_pointer() {
+        let mut value =
+            parse_json(r#"{"users": [{"name": "Ann"}], "a/b": 1, "m~n": 2, "": 3}"#).unwrap();
+        let json = |text| parse_json(text).unwrap();
+
+        assert_eq!(value.pointer(""), Some(&value.clone()));
+        assert_eq!(value.pointer("/users/0/name"), Some(&json(r#""Ann""#)));
+        assert_eq!(value.pointer("/a~1b"), Some(&json("1")));
+        assert_eq!(value.pointer("/m~0n"), Some(&json("2")));
+        assert_eq!(value.pointer("/"), Some(&json("3")));
+        assert_eq!(value.pointer("/users/1"), None);
+        assert_eq!(value.pointer("/users/01"), None);
+        assert_eq!(value.pointer("users"), None);
+
+        *value.pointer_mut("/users/0/name").unwrap() = json(r#""Bea""#);
+        assert_eq!(value.insert_at("/users/0/age", json("30")), Ok(None));
+        assert_eq!(value.insert_at("/users/0", json("{}")), Ok(None));
+        assert_eq!(value.insert_at("/users/-", json("null")), Ok(None));
+        assert_eq!(value.insert_at("/a~1b", json("5")), Ok(Some(json("1"))));
+        assert_eq!(value.remove_at("/m~0n"), Ok(json("2")));
+        assert_eq!(value.replace_at("/", json("4")), Ok(json("3")));
+        assert_eq!(
+            value,
+            json(r#"{"users": [{}, {"name": "Bea", "age": 30}, null], "a/b": 5, "": 4}"#)
+        );
+
+        // Each kind of failure has its own error
+        assert_eq!(
+            value.insert_at("users/0", json("1")),
+            Err(PointerError::InvalidPointer("users/0".to_string()))
+        );
+        assert_eq!(
+            value.remove_at("/users/0/x~2"),
+            Err(PointerError::InvalidPointer("/users/0/x~2".to_string()))
+        );
+        assert_eq!(
+            value.replace_at("/missing/x", json("1")),
+            Err(PointerError::MissingKey("missing".to_string()))
+        );
+        assert_eq!(
+            value.remove_at("/users/3"),
+            Err(PointerError::IndexOutOfRange(3, 3))
+        );
+        assert_eq!(
+            value.insert_at("/users/4", json("1")),
+            Err(PointerError::IndexOutOfRange(4, 3))
+        );
+        assert_eq!(
+            value.remove_at("/users/-"),
+            Err(PointerError::IndexOutOfRange(3, 3))
+        );
+        assert_eq!(
+            value.replace_at("/users/first", json("1")),
+            Err(PointerError::InvalidIndex("first".to_string()))
+        );
+        assert_eq!(
+            value.insert_at("/a~1b/c", json("1")),
+            Err(PointerError::NotAContainer("c".to_string()))
+        );
+
+        // The empty pointer is the whole value
+        let old = value.replace_at("", json("[]")).unwrap();
+        assert_eq!(old.pointer("/users/1/age"), Some(&json("30")));
+        assert_eq!(value.remove_at(""), Ok(json("[]")));
+        assert_eq!(value, JsonValue::Null);
+    }
+
+    #[test]
+    fn test_json_path() {
+        // The example document from RFC 9535
+        let store = parse_json(
+            r#"{"store": {
+                "book": [
+                    {"category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95},
+                    {"category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99},
+                    {"category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99},
+                    {"category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99}
+                ],
+                "bicycle": {"color": "red", "price": 399}
+            }}"#,
+        )
+        .unwrap();
+        let paths = |query: &str| -> Vec<String> {
+            store
+                .query(query)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.path)
+                .collect()
+        };
+        let values = |query: &str| -> Vec<String> {
+            let path = JsonPath::parse(query).unwrap();
+            path.select(&store).iter().map(|v| v.to_string()).collect()
+        };
+
+        assert_eq!(
+            values("$.store.book[*].author"),
+            [
+                r#""Nigel Rees""#,
+                r#""Evelyn Waugh""#,
+                r#""Herman Melville""#,
+                r#""J. R. R. Tolkien""#
+            ]
+        );
+        assert_eq!(values("$..author").len(), 4);
+        assert_eq!(
+            paths("$.store.*"),
+            ["$['store']['book']", "$['store']['bicycle']"]
+        );
+        assert_eq!(
+            values("$.store..price"),
+            ["8.95", "12.99", "8.99", "22.99", "399"]
+        );
+        assert_eq!(paths("$..book[2]"), ["$['store']['book'][2]"]);
+        assert_eq!(paths("$..book[-1]"), ["$['store']['book'][3]"]);
+        assert_eq!(paths("$..book[0,1]"), paths("$..book[:2]"));
+        assert_eq!(
+            paths("$..book[::-2]"),
+            ["$['store']['book'][3]", "$['store']['book'][1]"]
+        );
+        assert_eq!(paths("$..book[?@.isbn].title").len(), 2);
+        assert_eq!(
+            values("$..book[?@.price < 10].title"),
+            [r#""Sayings of the Century""#, r#""Moby Dick""#]
+        );
+        assert_eq!(
+            values(r#"$..book[?@.category == "fiction" && !(@.price > 20 || @.isbn)].title"#),
+            [r#""Sword of Honour""#]
+        );
+        assert_eq!(
+            values("$.store.book[?@.price >= $.store.bicycle.price]").len(),
+            0
+        );
+        assert_eq!(values("$..*").len(), 27);
+        assert_eq!(
+            values("$[ 'store' ] ['bicycle']['color', \"price\"]"),
+            [r#""red""#, "399"]
+        );
+        assert!(values("$.store.missing").is_empty());
+
+        // Numbers compare by value, and missing values equal only each other
+        let value = parse_json(r#"[{"n": 1}, {"n": 1.0}, {"n": "1"}, {}, {"n": [1]}]"#).unwrap();
+        let found = |query| {
+            let matches = value.query(query).unwrap();
+            matches.into_iter().map(|m| m.path).collect::<Vec<_>>()
+        };
+        assert_eq!(found("$[?@.n == 1]"), ["$[0]", "$[1]"]);
+        assert_eq!(found("$[?@.n == @.missing]"), ["$[3]"]);
+        assert!(value.query("$[?@.n == [1]]").is_err());
+        assert_eq!(found("$[?@.n[0] == 1]"), ["$[4]"]);
+        assert_eq!(found("$[?@.n > 0.5]"), ["$[0]", "$[1]"]);
+
+        // Normalized paths escape keys
+        let value = parse_json(r#"{"it's": {"a\nb": 1}}"#).unwrap();
+        assert_eq!(value.query("$..*").unwrap()[1].path, r"$['it\'s']['a\nb']");
+
+        // Malformed queries are rejected, saying where
+        for (query, offset) in [
+            ("store", 0),
+            ("$.", 2),
+            ("$[01]", 2),
+            ("$[?@.a == 1 &&]", 14),
+            ("$[?@..a == 1]", 3),
+            ("$[?1]", 3),
+            ("$['a]", 2),
+            ("$[?length(@) > 1]", 3),
+            ("$.a ", 3),
+        ] {
+            let error = JsonPath::parse(query).unwrap_err();
+            assert_eq!(error.offset, offset, "{}: {}", query, error);
+        }
+
+        // Nesting is limited, so deep queries don't overflow the stack
+        let query = |depth| format!("$[?{}@{}]", "(".repeat(depth), ")".repeat(depth));
+        assert!(JsonPath::parse(&query(127)).is_ok());
+        let error = JsonPath::parse(&query(100_000)).unwrap_err();
+        assert_eq!(error.offset, 131);
+        assert!(JsonPath::parse(&format!("$[?{}@]", "@[?".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn test_json_patch() {
+        let json = |text| parse_json(text).unwrap();
+        let mut doc = json(r#"{"a": 1, "b": {"c": [1, 2, 3]}, "d": "x"}"#);
+        let patch = Patch::parse(
+            r#"[
+                {"op": "add", "path": "/e", "value": {"f": null}},
+                {"op": "remove", "path": "/a"},
+                {"op": "replace", "path": "/d", "value": "y"},
+                {"op": "move", "from": "/b/c/0", "path": "/b/c/-"},
+                {"op": "copy", "from": "/b/c", "path": "/e/g"},
+                {"op": "add", "path": "/b/c/1", "value": 9},
+                {"op": "test", "path": "/e/g", "value": [2.0, 3, 1]}
+            ]"#,
+        )
+        .unwrap();
+        patch.apply(&mut doc).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            r#"{"b": {"c": [2, 9, 3, 1]}, "d": "y", "e": {"f": null, "g": [2, 3, 1]}}"#
+        );
+
+        // A failure undoes everything before it, leaving members in order
+        let before = doc.to_string();
+        let patch = Patch::parse(
+            r#"[
+                {"op": "remove", "path": "/b"},
+                {"op": "add", "path": "/", "value": 1},
+                {"op": "move", "from": "/e/g", "path": "/z"},
+                {"op": "replace", "path": "", "value": []},
+                {"op": "add", "path": "/-", "value": 1},
+                {"op": "test", "path": "/0", "value": 2}
+            ]"#,
+        )
+        .unwrap();
+        let err = patch.apply(&mut doc).unwrap_err();
+        assert!(matches!(&err, PatchError::TestFailed(5, pointer) if pointer == "/0"));
+        assert_eq!(doc.to_string(), before);
+
+        // Errors name the operation and the pointer it failed at
+        let err = Patch::parse(r#"[{"op": "test", "path": "/d", "value": "y"}, {"op": "copy", "from": "/nope", "path": "/x"}]"#)
+            .unwrap()
+            .apply(&mut doc)
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            PatchError::Pointer(1, pointer, PointerError::MissingKey(key)) if pointer == "/nope" && key == "nope"
+        ));
+        assert_eq!(
+            err.to_string(),
+            r#"Operation 1 failed at "/nope": No member named "nope""#
+        );
+        let err = Patch::parse(r#"[{"op": "move", "from": "/b", "path": "/b/c/x"}]"#)
+            .unwrap()
+            .apply(&mut doc)
+            .unwrap_err();
+        assert!(matches!(err, PatchError::InvalidOperation(0, _)));
+
+        // Malformed patches are rejected before anything is applied
+        assert!(matches!(Patch::parse("{}"), Err(PatchError::NotAnArray)));
+        assert!(matches!(Patch::parse("[1"), Err(PatchError::Parse(_))));
+        assert!(matches!(
+            Patch::parse(r#"[{"op": "add", "path": "/a"}]"#),
+            Err(PatchError::InvalidOperation(0, _))
+        ));
+        assert!(matches!(
+            Patch::parse(r#"[{"path": "/a"}, {"op": "jump", "path": "/a"}]"#),
+            Err(PatchError::InvalidOperation(0, _))
+        ));
+
+        // A patch reads back from the JSON it writes
+        assert_eq!(Patch::parse(&patch.to_string()).unwrap(), patch);
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        // The examples from RFC 7396, appendix A
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, "null", "null"),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+        ];
+        for (target, patch, expected) in cases {
+            let mut value = parse_json(target).unwrap();
+            merge_patch(&mut value, &parse_json(patch).unwrap());
+            assert_eq!(
+                value,
+                parse_json(expected).unwrap(),
+                "{} + {}",
+                target,
+                patch
+            );
+        }
+
+        // Generated patches are minimal and turn one document into the other
+        let from = parse_json(r#"{"a":1,"b":{"c":[1,null],"d":{"e":true}},"f":"x"}"#).unwrap();
+        let to = parse_json(r#"{"a":1,"b":{"c":[null],"d":{"e":true}},"g":{"h":2}}"#).unwrap();
+        let patch = create_merge_patch(&from, &to).unwrap();
+        assert_eq!(
+            patch,
+            parse_json(r#"{"f":null,"b":{"c":[null]},"g":{"h":2}}"#).unwrap()
+        );
+        let mut value = from.clone();
+        merge_patch(&mut value, &patch);
+        assert_eq!(value, to);
+
+        assert_eq!(
+            create_merge_patch(&from, &from),
+            Some(JsonValue::Object(Map::new()))
+        );
+        assert_eq!(
+            create_merge_patch(&from, &parse_json("[1]").unwrap()),
+            Some(parse_json("[1]").unwrap())
+        );
+
+        // An unchanged null member needs no patch, but a new one can't be made
+        let from = parse_json(r#"{"a":null,"b":1}"#).unwrap();
+        let patch = create_merge_patch(&from, &parse_json(r#"{"a":null,"b":2}"#).unwrap());
+        assert_eq!(patch, Some(parse_json(r#"{"b":2}"#).unwrap()));
+        assert_eq!(
+            create_merge_patch(&from, &parse_json(r#"{"a":null,"b":null}"#).unwrap()),
+            None
+        );
+        assert_eq!(
+            create_merge_patch(&from, &parse_json(r#"{"a":null,"b":{"c":null}}"#).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let value = |text: &str| parse_json(text).unwrap();
+
+        assert!(
+            diff(
+                &value(r#"{"a":[1,{"b":2}]}"#),
+                &value(r#"{"a":[1,{"b":2}]}"#)
+            )
+            .is_empty()
+        );
+
+        // One inserted element is one change, not a change to every later one
+        let changes = diff(&value("[1,2,3,4,5]"), &value("[1,2,9,3,4,5]"));
+        assert_eq!(
+            changes.changes(),
+            [Change::Added {
+                path: "/2".to_string(),
+                value: value("9"),
+            }]
+        );
+
+        // Leftover elements on both sides are compared in place
+        let changes = diff(
+            &value(r#"[{"id":1,"n":"a"},{"id":2},3]"#),
+            &value(r#"[{"id":1,"n":"b"},3,4]"#),
+        );
+        assert_eq!(
+            changes.to_string(),
+            "~ /0/n: \"a\" -> \"b\"\n- /1: {\"id\": 2}\n+ /2: 4"
+        );
+
+        let changes = diff(
+            &value(r#"{"a/b":{"c~":1},"list":[1],"gone":null}"#),
+            &value(r#"{"a/b":{"c~":[1]},"list":{"0":1},"new":true}"#),
+        );
+        assert_eq!(
+            changes
+                .changes()
+                .iter()
+                .map(Change::path)
+                .collect::<Vec<_>>(),
+            ["/gone", "/a~1b/c~0", "/list", "/new"]
+        );
+        assert!(diff(&value("1"), &value("1.0")).is_empty());
+
+        // The patch turns one value into the other
+        let pairs = [
+            (r#"[1,2,3,4,5,6]"#, r#"[0,2,3,7,5,6,8]"#),
+            (r#"[[1,2],[3],[4,5]]"#, r#"[[3],[4,6,5],[1]]"#),
+            (
+                r#"{"a":[{"b":[1,2]},{"c":1}],"d":1}"#,
+                r#"{"a":[{"c":1},{"b":[2,1]}],"e":[]}"#,
+            ),
+            (r#"[]"#, r#"[1,[2],{"3":3}]"#),
+            (r#"{"x":[1,2,3]}"#, r#"[]"#),
+        ];
+        for (from, to) in pairs {
+            let (from, to) = (value(from), value(to));
+            let mut patched = from.clone();
+            diff(&from, &to).to_patch().apply(&mut patched).unwrap();
+            assert_eq!(patched, to, "{} -> {}", from, to);
+        }
+    }
+
+    #[test]
+    fn test_schema() {
+        let value = |text: &str| parse_json(text).unwrap();
+        let schema = |text: &str| Schema::compile(&value(text)).unwrap();
+        let failures = |schema: &Schema, instance: &str| {
+            schema
+                .validate(&value(instance))
+                .err()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|e| format!("{} {} {}", e.instance_path, e.schema_path, e.message))
+                .collect::<Vec<_>>()
+        };
+
+        let s = schema(r#"{"type": ["integer", "null"], "minimum": 0, "exclusiveMaximum": 10}"#);
+        assert!(s.is_valid(&value("0")) && s.is_valid(&value("9.0")) && s.is_valid(&value("null")));
+        assert_eq!(
+            failures(&s, "10"),
+            [" /exclusiveMaximum must be less than 10"]
+        );
+        assert_eq!(
+            failures(&s, "-1.5"),
+            [
+                " /type expected integer or null, found number",
+                " /minimum must be at least 0"
+            ]
+        );
+        let s = schema(r#"{"multipleOf": 0.1}"#);
+        assert!(s.is_valid(&value("0.3")) && !s.is_valid(&value("0.35")));
+        let s = schema(r#"{"enum": [1, "a", [true]], "not": {"const": 1.0}}"#);
+        assert!(s.is_valid(&value(r#""a""#)) && s.is_valid(&value("[true]")));
+        assert_eq!(
+            failures(&s, "1"),
+            [" /not must not match the schema in not"]
+        );
+        assert_eq!(
+            failures(&s, "2"),
+            [r#" /enum must be one of 1, "a", [true]"#]
+        );
+
+        // Lengths count characters, and patterns are ECMA-262 regexes that
+        // match anywhere unless anchored
+        let s = schema(r#"{"minLength": 2, "maxLength": 3, "pattern": "^(?:[a-z]\\d|é+)(?!x)"}"#);
+        assert!(s.is_valid(&value(r#""a1""#)) && s.is_valid(&value(r#""éé""#)));
+        assert_eq!(
+            failures(&s, r#""a1x""#),
+            [r#" /pattern must match the pattern "^(?:[a-z]\\d|é+)(?!x)""#]
+        );
+        assert!(!s.is_valid(&value(r#""é""#)));
+        let patterns = [
+            (r"^\w+@[^\s@]+\.[a-z]{2,}$", "me@example.com", true),
+            (r"^\w+@[^\s@]+\.[a-z]{2,}$", "me@example.c", false),
+            (r"^(ab|a)*?c$", "ababac", true),
+            (r"\bcat\b", "a cat sat", true),
+            (r"\bcat\b", "concatenate", false),
+            (r"^[^\d-]{1,3}x{2}?$", "abcxx", true),
+            (r"^é\x41\u{1F600}$", "éA😀", true),
+            (r"^.$", "\n", false),
+            (r"^a{2,3}$", "aaaa", false),
+            (r"^(a+)+$", &"a".repeat(10_000), true),
+            (r"^(ab)*$", &"ab".repeat(100_000), true),
+            (r"^(a|aa)+b", &"a".repeat(10_000), false),
+            (r"^(?=.*\d)(?!.*x)\w{3}$", "a1b", true),
+            (r"^(?=.*\d)(?!.*x)\w{3}$", "x1b", false),
+            (r"a(?=(?!c)b)", "acab", true),
+            (r"(?=a*b)", &"a".repeat(100_000), false),
+            (
+                r"^(?:(?=a*b)a)*b$",
+                &format!("{}b", "a".repeat(100_000)),
+                true,
+            ),
+            (r"^(){4000000000}$", "", true),
+            (r"^((?:a{0}){18446744073709551615}){3,}$", "", true),
+        ];
+        for (pattern, text, expected) in patterns {
+            let s = Schema::compile(&JsonValue::Object(Map::from_iter([(
+                "pattern".to_string(),
+                JsonValue::String(pattern.to_string()),
+            )])))
+            .unwrap();
+            let text = JsonValue::String(text.to_string());
+            assert_eq!(s.is_valid(&text), expected, "{:?}", pattern);
+        }
+
+        let s = schema(
+            r#"{
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer"},
+                    "name": true,
+                    "point": {"prefixItems": [{"type": "number"}], "items": {"type": "string"}},
+                    "tags": {"uniqueItems": true, "contains": {"const": "x"}, "maxContains": 1}
+                },
+                "patternProperties": {"^x-": true},
+                "additionalProperties": false,
+                "required": ["id", "name"],
+                "dependentRequired": {"point": ["tags"]},
+                "propertyNames": {"maxLength": 5}
+            }"#,
+        );
+        assert!(s.is_valid(&value(
+            r#"{"id": 1, "name": 0, "point": [1, "a"], "tags": ["x", "y"], "x-a": 0}"#
+        )));
+        assert_eq!(
+            failures(
+                &s,
+                r#"{"id": "1", "point": [1, 2], "tags": ["y", "y"], "other": 1, "x-toolong": 0}"#
+            ),
+            [
+                "/id /properties/id/type expected integer, found string",
+                "/point/1 /properties/point/items/type expected string, found number",
+                "/tags /properties/tags/uniqueItems items 0 and 1 must not be equal",
+                "/tags /properties/tags/contains must contain at least 1 matching items, but has 0",
+                "/other /additionalProperties no value is allowed here",
+                " /required missing required property \"name\"",
+                "/x-toolong /propertyNames/maxLength must be at most 5 characters long",
+            ]
+        );
+        assert_eq!(
+            failures(&s, r#"{"id": 1, "name": 0, "point": []}"#),
+            [" /dependentRequired missing property \"tags\", which \"point\" requires"]
+        );
+
+        let s = schema(
+            r#"{
+                "oneOf": [{"type": "number"}, {"minimum": 5}],
+                "anyOf": [{"type": "number"}, {"type": "string"}],
+                "if": {"type": "string"},
+                "then": {"minLength": 1},
+                "else": {"maximum": 5}
+            }"#,
+        );
+        assert!(s.is_valid(&value("3")) && s.is_valid(&value(r#""a""#)));
+        assert_eq!(
+            failures(&s, r#""""#),
+            [" /then/minLength must be at least 1 characters long"]
+        );
+        assert_eq!(
+            failures(&s, "6"),
+            [
+                " /oneOf must match exactly one schema in oneOf, but matches 2",
+                " /else/maximum must be at most 5"
+            ]
+        );
+        assert_eq!(
+            failures(&s, "true"),
+            [" /anyOf must match at least one schema in anyOf"]
+        );
+
+        // Recursive references, resolved through $defs and percent-encoding
+        let s = schema(
+            r##"{
+                "$ref": "#/$defs/a%20node",
+                "$defs": {
+                    "a node": {
+                        "type": "object",
+                        "properties": {"children": {"items": {"$ref": "#/$defs/a%20node"}}},
+                        "required": ["value"]
+                    }
+                }
+            }"##,
+        );
+        assert_eq!(
+            failures(
+                &s,
+                r#"{"value": 1, "children": [{"value": 2, "children": [{}]}]}"#
+            ),
+            ["/children/0/children/0 /$defs/a node/required missing required property \"value\""]
+        );
+        let error = s.validate(&value("[]")).unwrap_err().remove(0);
+        assert_eq!(
+            error.to_string(),
+            "Invalid value at \"\": expected object, found array (schema \"/$defs/a node/type\")"
+        );
+
+        let errors = [
+            (r#"{"minLength": -1}"#, "/minLength"),
+            (r#"{"type": "text"}"#, "/type"),
+            (r#"{"type": []}"#, "/type"),
+            (r#"{"pattern": "a("}"#, "/pattern"),
+            (r#"{"pattern": "(a{1000}){1000}"}"#, "/pattern"),
+            (
+                r#"{"patternProperties": {"(?<=a)b": true}}"#,
+                "/patternProperties/(?<=a)b",
+            ),
+            (
+                r#"{"additionalProperties": false, "patternProperties": {"(?<=a)b": true}}"#,
+                "/patternProperties/(?<=a)b",
+            ),
+            (r#"{"properties": {"a": 1}}"#, "/properties/a"),
+            (r##"{"$ref": "#/$defs/missing"}"##, "/$ref"),
+            (r#"{"$ref": "other.json#/a"}"#, "/$ref"),
+            (
+                r##"{"$defs": {"a": {"$ref": "#"}}, "allOf": [{"$ref": "#/$defs/a"}]}"##,
+                "/$defs/a/$ref",
+            ),
+        ];
+        for (text, path) in errors {
+            let error = Schema::compile(&value(text)).unwrap_err();
+            assert_eq!(error.schema_path, path, "{}", text);
+        }
+
+        // So do patterns nested too deeply to compile without overflowing the stack
+        let nested = |depth| {
+            let pattern = format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+            Schema::compile(&JsonValue::Object(
+                [("pattern".to_string(), JsonValue::String(pattern))]
+                    .into_iter()
+                    .collect(),
+            ))
+        };
+        assert!(nested(128).unwrap().is_valid(&value(r#""a""#)));
+        assert_eq!(nested(129).unwrap_err().schema_path, "/pattern");
+        assert_eq!(nested(200_000).unwrap_err().schema_path, "/pattern");
+
+        // Values nested too deeply to check fail instead of overflowing the stack
+        let s = schema(r##"{"items": {"$ref": "#"}}"##);
+        let nested = |depth: usize| {
+            let options = ParseOptions {
+                max_depth: depth,
+                ..ParseOptions::default()
+            };
+            let text = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+            parse_json_with(&text, options).unwrap()
+        };
+        assert!(s.is_valid(&nested(Schema::MAX_DEPTH + 1)));
+        let deep = nested(100_000);
+        let errors = s.validate(&deep).unwrap_err();
+        deep.drop_iteratively();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].instance_path, "/0".repeat(Schema::MAX_DEPTH + 1));
+        assert_eq!(errors[0].schema_path, "/items/$ref");
+        assert_eq!(errors[0].message, "nested more than 256 levels deep");
+    }
 }
@@ -1,15 +1,94 @@
-use json_parser::parse_json;
+use json_parser::{
+    Diagnostic, JsonError, ParseOptions, Parser, PrettyConfig, parse_json_recovering,
+    to_string_pretty,
+};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Parse the value of a `--name=N` flag, exiting on a bad number.
+fn numeric_flag(arg: &str) -> usize {
+    let (name, value) = arg.split_once('=').unwrap_or((arg, ""));
+    match value.parse() {
+        Ok(n) => n,
+        Err(_) => {
+            eprintln!("Invalid value for {}: {:?}", name, value);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// The lines of the file at `path` that `errors` are on, by line number,
+/// each with the byte offset it starts at. The file is read through once,
+/// holding on to only those lines.
+fn error_lines(path: &str, errors: &[JsonError]) -> io::Result<HashMap<usize, (usize, String)>> {
+    let mut wanted: HashMap<usize, Vec<u8>> = errors
+        .iter()
+        .map(|e| (e.position().line, Vec::new()))
+        .collect();
+    let last = wanted.keys().copied().max().unwrap_or(0);
+
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut offsets = HashMap::new();
+    let (mut line, mut offset) = (1, 0);
+    while line <= last {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        let (len, ends_line) = match buffer.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (buffer.len(), false),
+        };
+        if let Some(text) = wanted.get_mut(&line) {
+            text.extend_from_slice(&buffer[..len]);
+            offsets.entry(line).or_insert(offset);
+        }
+        reader.consume(len);
+        offset += len;
+        if ends_line {
+            line += 1;
+        }
+    }
+
+    let lines = offsets.into_iter().map(|(line, offset)| {
+        let text = String::from_utf8_lossy(&wanted[&line]).into_owned();
+        (line, (offset, text))
+    });
+    Ok(lines.collect())
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    let json_str = if args.len() > 1 {
-        // Read JSON from file
-        match fs::read_to_string(&args[1]) {
-            Ok(content) => content,
+    // Any layout flag turns on pretty printing
+    let mut pretty: Option<PrettyConfig> = None;
+    let mut path: Option<&str> = None;
+    let mut stdin_text = String::new();
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--pretty" => {
+                pretty.get_or_insert_with(PrettyConfig::default);
+            }
+            "--tabs" => pretty.get_or_insert_with(PrettyConfig::default).use_tabs = true,
+            "--sort-keys" => pretty.get_or_insert_with(PrettyConfig::default).sort_keys = true,
+            _ if arg.starts_with("--indent=") => {
+                pretty
+                    .get_or_insert_with(PrettyConfig::default)
+                    .indent_width = numeric_flag(arg);
+            }
+            _ if arg.starts_with("--width=") => {
+                pretty.get_or_insert_with(PrettyConfig::default).max_width = numeric_flag(arg);
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    let (json, errors) = if let Some(path) = path {
+        // Stream JSON from the file rather than reading it all in first
+        match fs::File::open(path) {
+            Ok(file) => Parser::from_reader(file, ParseOptions::default()).parse_recovering(),
             Err(e) => {
                 eprintln!("File reading error: {}", e);
                 return;
@@ -20,16 +99,42 @@ fn main() {
         println!("Enter JSON (end with Ctrl+D):");
         let mut buffer = String::new();
         match io::stdin().read_to_string(&mut buffer) {
-            Ok(_) => buffer,
+            Ok(_) => {
+                let (json, errors) = parse_json_recovering(&buffer);
+                stdin_text = buffer;
+                (json, errors)
+            }
             Err(e) => {
                 eprintln!("Standard input reading error: {}", e);
                 return;
             }
         }
     };
+    if errors.is_empty() {
+        match pretty {
+            Some(config) => match to_string_pretty(&json, &config) {
+                Ok(text) => println!("{}", text),
+                Err(e) => eprintln!("JSON writing error: {}", e),
+            },
+            None => println!("Parsed JSON: {}", json),
+        }
+    } else {
+        // Only now are the lines the errors are on needed, to show them
+        let lines = match path {
+            Some(path) => error_lines(path, &errors).unwrap_or_default(),
+            None => HashMap::new(),
+        };
 
-    match parse_json(&json_str) {
-        Ok(json) => println!("Parsed JSON: {}", json),
-        Err(e) => eprintln!("JSON parsing error: {}", e),
+        // Report every problem at once rather than just the first
+        let file_name = path.unwrap_or("<stdin>");
+        for e in &errors {
+            let diagnostic = match lines.get(&e.position().line) {
+                Some((offset, line)) => Diagnostic::for_line(line, *offset, e),
+                // Past the last line, or from standard input
+                None if path.is_some() => Diagnostic::for_line("", e.position().offset, e),
+                None => Diagnostic::new(&stdin_text, e),
+            };
+            eprintln!("{}", diagnostic.with_file_name(file_name));
+        }
     }
 }
@@ -0,0 +1,301 @@
+use crate::json::JsonValue;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::ops::{Index, IndexMut};
+
+/// Objects with at most this many members are searched linearly; larger
+/// ones get a hash index.
+const INDEX_THRESHOLD: usize = 8;
+
+/// The members of a JSON object, kept in insertion order.
+///
+/// Parsing preserves the order keys appear in the document, so printing a
+/// parsed value reproduces that order. Use [`Map::sort_keys`] or convert into
+/// a `BTreeMap`/`HashMap` when another ordering is wanted.
+///
+/// Keys are `String`s and values `JsonValue`s unless other types are given,
+/// as `BorrowedValue` does.
+#[derive(Debug, Clone)]
+pub struct Map<K = String, V = JsonValue> {
+    entries: Vec<(K, V)>,
+    index: Option<HashMap<K, usize>>,
+}
+
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Map {
+            entries: Vec::new(),
+            index: None,
+        }
+    }
+}
+
+impl<K, V> Map<K, V> {
+    pub fn new() -> Self {
+        Map::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Map {
+            entries: Vec::with_capacity(capacity),
+            index: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index = None;
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            inner: self.entries.iter_mut(),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+}
+
+impl<K: Borrow<str> + Hash + Eq + Clone, V> Map<K, V> {
+    /// The position of `key` in document order.
+    pub fn get_index_of(&self, key: &str) -> Option<usize> {
+        match &self.index {
+            Some(index) => index.get(key).copied(),
+            None => self.entries.iter().position(|(k, _)| k.borrow() == key),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.get_index_of(key).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.get_index_of(key).map(|i| &mut self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get_index_of(key).is_some()
+    }
+
+    /// Insert a member, returning the previous value for `key`.
+    ///
+    /// A new key goes at the end; replacing an existing key keeps its
+    /// original position.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(i) = self.get_index_of(key.borrow()) {
+            return Some(std::mem::replace(&mut self.entries[i].1, value));
+        }
+
+        if let Some(index) = &mut self.index {
+            index.insert(key.clone(), self.entries.len());
+        }
+        self.entries.push((key, value));
+
+        if self.index.is_none() && self.entries.len() > INDEX_THRESHOLD {
+            self.rebuild_index();
+        }
+        None
+    }
+
+    /// Insert a member with a new key at `index`, shifting later members up.
+    pub(crate) fn shift_insert(&mut self, index: usize, key: K, value: V) {
+        if let Some(positions) = &mut self.index {
+            for (k, _) in &self.entries[index..] {
+                *positions.get_mut(k.borrow()).expect("indexed key") += 1;
+            }
+            positions.insert(key.clone(), index);
+        }
+        self.entries.insert(index, (key, value));
+        if self.index.is_none() && self.entries.len() > INDEX_THRESHOLD {
+            self.rebuild_index();
+        }
+    }
+
+    /// Remove a member, shifting later members down to keep the order.
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let i = self.get_index_of(key)?;
+        let (_, value) = self.entries.remove(i);
+        // Only the members after the gap move
+        if let Some(positions) = &mut self.index {
+            positions.remove(key);
+            for (k, _) in &self.entries[i..] {
+                *positions.get_mut(k.borrow()).expect("indexed key") -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    /// Reorder the members by key.
+    pub fn sort_keys(&mut self) {
+        self.entries
+            .sort_by(|(a, _), (b, _)| a.borrow().cmp(b.borrow()));
+        if self.index.is_some() {
+            self.rebuild_index();
+        }
+    }
+
+    fn rebuild_index(&mut self) {
+        self.index = Some(
+            self.entries
+                .iter()
+                .enumerate()
+                .map(|(i, (k, _))| (k.clone(), i))
+                .collect(),
+        );
+    }
+}
+
+/// Two objects are equal when they have the same members, in any order.
+impl<K: Borrow<str> + Hash + Eq + Clone, V: PartialEq> PartialEq for Map<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k.borrow()) == Some(v))
+    }
+}
+
+impl<K: Borrow<str> + Hash + Eq + Clone, V> Index<&str> for Map<K, V> {
+    type Output = V;
+
+    fn index(&self, key: &str) -> &V {
+        self.get(key).expect("key not found in JSON object")
+    }
+}
+
+impl<K: Borrow<str> + Hash + Eq + Clone, V> IndexMut<&str> for Map<K, V> {
+    fn index_mut(&mut self, key: &str) -> &mut V {
+        self.get_mut(key).expect("key not found in JSON object")
+    }
+}
+
+pub struct Iter<'a, K = String, V = JsonValue> {
+    inner: std::slice::Iter<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+pub struct IterMut<'a, K = String, V = JsonValue> {
+    inner: std::slice::IterMut<'a, (K, V)>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<'a, K, V> IntoIterator for &'a Map<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut Map<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V> IntoIterator for Map<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<K: Borrow<str> + Hash + Eq + Clone, V> FromIterator<(K, V)> for Map<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Borrow<str> + Hash + Eq + Clone, V> Extend<(K, V)> for Map<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl From<HashMap<String, JsonValue>> for Map {
+    fn from(map: HashMap<String, JsonValue>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<BTreeMap<String, JsonValue>> for Map {
+    fn from(map: BTreeMap<String, JsonValue>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<Map> for HashMap<String, JsonValue> {
+    fn from(map: Map) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<Map> for BTreeMap<String, JsonValue> {
+    fn from(map: Map) -> Self {
+        map.into_iter().collect()
+    }
+}
@@ -0,0 +1,102 @@
+use crate::json::JsonValue;
+use crate::map::Map;
+
+/// Apply a JSON Merge Patch (RFC 7396) to `target`.
+///
+/// An object patch is merged into the target member by member: a `null`
+/// member removes the key, an object member is merged recursively, and
+/// anything else replaces the target's member. A patch that is not an object
+/// replaces the target outright, so arrays are always replaced whole.
+///
+/// ```
+/// use json_parser::{merge_patch, parse_json};
+///
+/// let mut doc = parse_json(r#"{"title": "Hi", "author": {"name": "A", "email": "a@x"}}"#).unwrap();
+/// let patch = parse_json(r#"{"title": "Hello", "author": {"email": null}}"#).unwrap();
+/// merge_patch(&mut doc, &patch);
+/// assert_eq!(doc, parse_json(r#"{"title": "Hello", "author": {"name": "A"}}"#).unwrap());
+/// ```
+pub fn merge_patch(target: &mut JsonValue, patch: &JsonValue) {
+    let JsonValue::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !matches!(target, JsonValue::Object(_)) {
+        *target = JsonValue::Object(Map::new());
+    }
+    let JsonValue::Object(members) = target else {
+        unreachable!("the target was just made an object");
+    };
+
+    for (key, value) in patch {
+        if let JsonValue::Null = value {
+            members.remove(key);
+        } else if let Some(member) = members.get_mut(key) {
+            merge_patch(member, value);
+        } else {
+            let mut member = JsonValue::Null;
+            merge_patch(&mut member, value);
+            members.insert(key.clone(), member);
+        }
+    }
+}
+
+/// The smallest merge patch that turns `from` into `to`, or `None` if
+/// there isn't one.
+///
+/// A merge patch can't give an object a member whose value is `null`, since
+/// `null` removes the member instead, so there is no patch when `to` has one
+/// that `from` doesn't.
+///
+/// ```
+/// use json_parser::{create_merge_patch, merge_patch, parse_json};
+///
+/// let from = parse_json(r#"{"a": 1, "b": {"c": 2, "d": 3}, "e": [1]}"#).unwrap();
+/// let to = parse_json(r#"{"a": 1, "b": {"c": 4}, "e": [1, 2]}"#).unwrap();
+/// let patch = create_merge_patch(&from, &to).unwrap();
+/// assert_eq!(patch, parse_json(r#"{"b": {"c": 4, "d": null}, "e": [1, 2]}"#).unwrap());
+///
+/// let mut doc = from.clone();
+/// merge_patch(&mut doc, &patch);
+/// assert_eq!(doc, to);
+/// ```
+pub fn create_merge_patch(from: &JsonValue, to: &JsonValue) -> Option<JsonValue> {
+    let JsonValue::Object(to_members) = to else {
+        return Some(to.clone());
+    };
+    let JsonValue::Object(from_members) = from else {
+        // Merged into an empty object, so nulls would be lost
+        return is_mergeable(to).then(|| to.clone());
+    };
+
+    let mut patch = Map::new();
+    for key in from_members.keys() {
+        if !to_members.contains_key(key) {
+            patch.insert(key.clone(), JsonValue::Null);
+        }
+    }
+    for (key, value) in to_members {
+        match from_members.get(key) {
+            Some(old) if old == value => {}
+            Some(old) if matches!(value, JsonValue::Object(_)) => {
+                patch.insert(key.clone(), create_merge_patch(old, value)?);
+            }
+            _ if matches!(value, JsonValue::Null) || !is_mergeable(value) => return None,
+            _ => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Some(JsonValue::Object(patch))
+}
+
+/// Whether merging `value` into nothing gives `value` back: none of its
+/// objects, outside arrays, has a `null` member.
+fn is_mergeable(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Object(members) => members
+            .values()
+            .all(|value| !matches!(value, JsonValue::Null) && is_mergeable(value)),
+        _ => true,
+    }
+}
@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A JSON number.
+///
+/// Integers are kept exactly as `i64` or `u64` when they fit. Everything else
+/// is an `f64`, unless the number was parsed in arbitrary-precision mode, in
+/// which case its original text is kept so printing reproduces it digit for
+/// digit.
+///
+/// Numbers are equal when their values are, however they are held, so `1`
+/// equals `1.0` and an arbitrary-precision `1e2` equals `100`.
+#[derive(Debug, Clone)]
+pub struct Number {
+    n: N,
+}
+
+#[derive(Debug, Clone)]
+enum N {
+    PosInt(u64),
+    /// Always less than zero.
+    NegInt(i64),
+    Float(f64),
+    /// Valid JSON number text that did not fit an integer type.
+    Raw(String),
+}
+
+impl Number {
+    /// Build a number from the text of a JSON number literal.
+    ///
+    /// `text` must already be valid JSON number syntax. With
+    /// `arbitrary_precision`, numbers that are not exact `i64`/`u64` integers
+    /// keep their text instead of being rounded to `f64`.
+    pub(crate) fn from_literal(text: &str, arbitrary_precision: bool) -> Option<Number> {
+        let is_integer = !text.contains(['.', 'e', 'E']);
+
+        if is_integer {
+            if let Ok(n) = text.parse::<u64>() {
+                return Some(Number { n: N::PosInt(n) });
+            }
+            // `-0`, or `-00` outside strict mode, is a negative zero float
+            if let Ok(n) = text.parse::<i64>()
+                && n < 0
+            {
+                return Some(Number { n: N::NegInt(n) });
+            }
+        }
+
+        if arbitrary_precision {
+            return Some(Number {
+                n: N::Raw(text.to_string()),
+            });
+        }
+
+        text.parse::<f64>().ok().map(Number::from)
+    }
+
+    pub fn is_i64(&self) -> bool {
+        match self.n {
+            N::PosInt(n) => n <= i64::MAX as u64,
+            N::NegInt(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_u64(&self) -> bool {
+        matches!(self.n, N::PosInt(_))
+    }
+
+    /// Whether the number is held as a float or as arbitrary-precision text.
+    pub fn is_f64(&self) -> bool {
+        matches!(self.n, N::Float(_) | N::Raw(_))
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.n {
+            N::PosInt(n) => i64::try_from(n).ok(),
+            N::NegInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.n {
+            N::PosInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The value as an `f64`, rounding if necessary.
+    pub fn as_f64(&self) -> f64 {
+        match &self.n {
+            N::PosInt(n) => *n as f64,
+            N::NegInt(n) => *n as f64,
+            N::Float(n) => *n,
+            N::Raw(text) => text.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Whether the number can be written as JSON, i.e. is not NaN or infinite.
+    pub fn is_finite(&self) -> bool {
+        match &self.n {
+            N::Float(n) => n.is_finite(),
+            _ => true,
+        }
+    }
+
+    /// Order two numbers by value, exactly unless both are floats, which
+    /// are compared as `f64`s.
+    pub(crate) fn compare(&self, other: &Number) -> Option<Ordering> {
+        if let (Some(a), Some(b)) = (self.as_i64(), other.as_i64()) {
+            return Some(a.cmp(&b));
+        }
+        if let (Some(a), Some(b)) = (self.as_u64(), other.as_u64()) {
+            return Some(a.cmp(&b));
+        }
+        match (&self.n, &other.n) {
+            (N::Float(a), N::Float(b)) => a.partial_cmp(b),
+            (N::Float(a), _) => compare_float(*a, other),
+            (_, N::Float(b)) => compare_float(*b, self).map(Ordering::reverse),
+            _ => Some(Decimal::new(&self.to_string()).cmp(&Decimal::new(&other.to_string()))),
+        }
+    }
+
+    /// The original text of an arbitrary-precision number.
+    pub fn as_raw_str(&self) -> Option<&str> {
+        match &self.n {
+            N::Raw(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.compare(other) == Some(Ordering::Equal)
+    }
+}
+
+/// The value of a JSON number literal as `0.digits × 10^exponent`, with no
+/// leading or trailing zeros in `digits`, so that equal values compare equal
+/// however they were written. Zero has no digits and is never negative.
+#[derive(PartialEq, Eq)]
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    exponent: i64,
+}
+
+impl Decimal {
+    fn new(text: &str) -> Decimal {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = text.split_once(['e', 'E']).unwrap_or((text, "0"));
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let exponent = match exponent.strip_prefix('-') {
+            Some(digits) => -saturating_parse(digits),
+            None => saturating_parse(exponent.trim_start_matches('+')),
+        };
+        let digits: Vec<u8> = integer.bytes().chain(fraction.bytes()).collect();
+        let leading = digits.iter().take_while(|&&d| d == b'0').count();
+        let trailing = digits.iter().rev().take_while(|&&d| d == b'0').count();
+        if leading == digits.len() {
+            return Decimal {
+                negative: false,
+                digits: Vec::new(),
+                exponent: 0,
+            };
+        }
+        Decimal {
+            negative,
+            digits: digits[leading..digits.len() - trailing].to_vec(),
+            exponent: exponent.saturating_add(integer.len() as i64 - leading as i64),
+        }
+    }
+}
+
+/// Order a float against a number that isn't one.
+fn compare_float(float: f64, other: &Number) -> Option<Ordering> {
+    if float.is_nan() {
+        return None;
+    }
+    if float.is_infinite() {
+        return Some(if float > 0.0 {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        });
+    }
+    // The shortest text for a large whole float, like `1.152921504606847e18`
+    // for 2^60, leaves off digits its value has
+    let text = if float.fract() == 0.0 && float.abs() < 2f64.powi(127) {
+        (float as i128).to_string()
+    } else {
+        format!("{:?}", float)
+    };
+    Some(Decimal::new(&text).cmp(&Decimal::new(&other.to_string())))
+}
+
+/// Parse a string of digits, stopping at `i64::MAX` rather than overflowing.
+fn saturating_parse(digits: &str) -> i64 {
+    digits.bytes().fold(0i64, |n, d| {
+        n.saturating_mul(10).saturating_add(i64::from(d - b'0'))
+    })
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let sign = |d: &Decimal| match (d.negative, d.digits.is_empty()) {
+            (true, _) => -1,
+            (false, true) => 0,
+            (false, false) => 1,
+        };
+        let magnitude = (self.exponent, &self.digits).cmp(&(other.exponent, &other.digits));
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal if self.negative => magnitude.reverse(),
+            Ordering::Equal if self.digits.is_empty() => Ordering::Equal,
+            Ordering::Equal => magnitude,
+            unequal => unequal,
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Self {
+        Number { n: N::Float(n) }
+    }
+}
+
+impl From<u64> for Number {
+    fn from(n: u64) -> Self {
+        Number { n: N::PosInt(n) }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        if n < 0 {
+            Number { n: N::NegInt(n) }
+        } else {
+            Number {
+                n: N::PosInt(n as u64),
+            }
+        }
+    }
+}
+
+impl From<i32> for Number {
+    fn from(n: i32) -> Self {
+        Number::from(n as i64)
+    }
+}
+
+impl From<u32> for Number {
+    fn from(n: u32) -> Self {
+        Number::from(n as u64)
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.n {
+            N::PosInt(n) => write!(f, "{}", n),
+            N::NegInt(n) => write!(f, "{}", n),
+            // Debug gives the shortest text that reads back as the same f64
+            N::Float(n) => write!(f, "{:?}", n),
+            N::Raw(text) => f.write_str(text),
+        }
+    }
+}
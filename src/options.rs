@@ -0,0 +1,107 @@
+/// Settings that control how the lexer and parser read a document.
+///
+/// ```
+/// use json_parser::{ParseOptions, parse_json_with};
+///
+/// let options = ParseOptions {
+///     arbitrary_precision: true,
+///     ..ParseOptions::default()
+/// };
+/// let value = parse_json_with("3.141592653589793238462643383279", options).unwrap();
+/// assert_eq!(value.to_string(), "3.141592653589793238462643383279");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Keep the original text of numbers that are not exact `i64`/`u64`
+    /// integers instead of rounding them to `f64`.
+    pub arbitrary_precision: bool,
+    /// How to treat a `\uXXXX` surrogate escape that is not part of a
+    /// high/low pair.
+    pub lone_surrogates: LoneSurrogates,
+    /// Follow RFC 8259 to the letter: reject leading zeros, raw control
+    /// characters in strings, whitespace other than space, tab, newline and
+    /// carriage return, and numbers too large for an `f64`.
+    pub strict: bool,
+    /// How many arrays and objects may be nested inside each other. Deeper
+    /// documents fail with `JsonError::DepthLimitExceeded`.
+    pub max_depth: usize,
+    /// Size caps for documents from untrusted sources.
+    pub limits: ParseLimits,
+    /// What to do when an object has the same key more than once.
+    pub duplicate_keys: DuplicateKeys,
+}
+
+const DEFAULT: ParseOptions = ParseOptions {
+    arbitrary_precision: false,
+    lone_surrogates: LoneSurrogates::Error,
+    strict: false,
+    max_depth: 128,
+    limits: ParseLimits {
+        max_input_bytes: None,
+        max_string_bytes: None,
+        max_array_len: None,
+        max_object_members: None,
+    },
+    duplicate_keys: DuplicateKeys::LastWins,
+};
+
+impl ParseOptions {
+    /// The defaults, but following RFC 8259 to the letter.
+    pub const fn strict() -> Self {
+        ParseOptions {
+            strict: true,
+            ..DEFAULT
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        DEFAULT
+    }
+}
+
+/// Caps on how much memory a document can make the parser allocate. `None`
+/// means no limit, which is the default for all of them.
+///
+/// Breaking a limit fails with `JsonError::LimitExceeded`, which ends the
+/// parse even in recovering mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseLimits {
+    /// Total length of the input in bytes. Input is read no further than
+    /// just past it.
+    pub max_input_bytes: Option<usize>,
+    /// Length in bytes of a string or object key, after unescaping.
+    pub max_string_bytes: Option<usize>,
+    /// Number of elements in a single array.
+    pub max_array_len: Option<usize>,
+    /// Number of members in a single object.
+    pub max_object_members: Option<usize>,
+}
+
+/// What to do with an unpaired UTF-16 surrogate such as `"\uD800"`, which
+/// JavaScript can produce but a Rust `String` cannot hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoneSurrogates {
+    /// Fail with `JsonError::LoneSurrogate`.
+    #[default]
+    Error,
+    /// Substitute U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+}
+
+/// How to handle an object with the same key more than once, such as
+/// `{"a": 1, "a": 2}`. RFC 8259 leaves this up to the parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Fail with `JsonError::DuplicateKey`.
+    Error,
+    /// Keep the first value and ignore the rest.
+    FirstWins,
+    /// Keep the last value, at the position of the first key.
+    #[default]
+    LastWins,
+    /// Keep every value, in document order, in an array under the key.
+    /// Keys that appear once keep their plain value.
+    CollectAll,
+}
@@ -1,195 +1,405 @@
-use crate::error::{JsonError, Result};
+use crate::borrowed::BorrowedValue;
+use crate::error::{JsonError, Position, Result};
+use crate::events::{EventParser, Step};
 use crate::json::JsonValue;
-use crate::lexer::{Lexer, Token};
-use std::collections::HashMap;
+use crate::lexer::Token;
+use crate::map::Map;
+use crate::options::{DuplicateKeys, ParseOptions};
+use crate::source::{ReaderSource, SliceSource, Source, StrSource};
+use std::borrow::{Borrow, Cow};
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::io::Read;
 
-pub struct Parser<'a> {
-    lexer: Lexer<'a>,
-    current_token: Option<Token>,
+/// Builds a `JsonValue` from the events of an [`EventParser`].
+pub struct Parser<'a, S> {
+    events: EventParser<'a, S>,
+    options: ParseOptions,
 }
 
-impl<'a> Parser<'a> {
-    fn new(input: &'a str) -> Result<Self> {
-        let mut lexer = Lexer::new(input);
-        let current_token = lexer.next_token()?;
+impl<'a> Parser<'a, StrSource<'a>> {
+    pub fn new(input: &'a str) -> Self {
+        Parser::with_options(input, ParseOptions::default())
+    }
 
-        Ok(Parser {
-            lexer,
-            current_token,
-        })
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
+        Parser::with_source(StrSource::new(input), options)
     }
 
-    fn parse(&mut self) -> Result<JsonValue> {
-        let value = self.parse_value()?;
+    /// Parse the whole input into a value that borrows its strings from it,
+    /// stopping at the first error.
+    pub fn parse_borrowed(mut self) -> Result<BorrowedValue<'a>> {
+        self.parse_document()
+    }
+}
 
-        // Ensure we've consumed all tokens
-        if self.current_token.is_some() {
-            return Err(JsonError::UnexpectedToken(
-                "Expected end of input".to_string(),
-            ));
+impl<'a> Parser<'a, SliceSource<'a>> {
+    /// Parse UTF-8 bytes, checking strings as they are read rather than
+    /// the whole input up front.
+    pub fn from_slice(input: &'a [u8], options: ParseOptions) -> Self {
+        Parser::with_source(SliceSource::new(input), options)
+    }
+}
+
+impl<R: Read> Parser<'_, ReaderSource<R>> {
+    /// Parse UTF-8 read from `reader`, a buffer at a time.
+    pub fn from_reader(reader: R, options: ParseOptions) -> Self {
+        Parser::with_source(ReaderSource::new(reader), options)
+    }
+}
+
+impl<'a, S: Source<'a>> Parser<'a, S> {
+    pub fn with_source(input: S, options: ParseOptions) -> Self {
+        Parser {
+            events: EventParser::with_source(input, options),
+            options,
         }
+    }
 
-        Ok(value)
+    /// Parse the whole input, stopping at the first error.
+    pub fn parse(mut self) -> Result<JsonValue> {
+        self.parse_document()
     }
 
-    fn advance_token(&mut self) -> Result<()> {
-        self.current_token = self.lexer.next_token()?;
-        Ok(())
+    /// Parse the whole input, reporting every syntax error found.
+    ///
+    /// After an error the parser resynchronizes at the next `,`, `}` or `]`
+    /// and carries on. Values that could not be parsed are replaced by
+    /// `JsonValue::Null` in the returned document.
+    pub fn parse_recovering(mut self) -> (JsonValue, Vec<JsonError>) {
+        self.events.recover_errors();
+
+        // Only a broken limit or a failed read returns `Err` while recovering
+        let result = self.parse_document();
+        let mut errors = self.events.take_errors();
+        let value = result.unwrap_or_else(|e| {
+            errors.push(e);
+            JsonValue::Null
+        });
+        (value, errors)
     }
 
-    fn parse_value(&mut self) -> Result<JsonValue> {
-        match &self.current_token {
-            Some(Token::Null) => {
-                self.advance_token()?;
-                Ok(JsonValue::Null)
-            }
-            Some(Token::Boolean(b)) => {
-                let value = *b;
-                self.advance_token()?;
-                Ok(JsonValue::Boolean(value))
-            }
-            Some(Token::Number(n)) => {
-                let value = *n;
-                self.advance_token()?;
-                Ok(JsonValue::Number(value))
+    fn parse_document<V: Tree<'a>>(&mut self) -> Result<V> {
+        let mut builder = TreeBuilder::new(self.options);
+        let mut document = V::scalar(None);
+
+        let result = loop {
+            let (step, position) = match self.events.next_step() {
+                Ok(Some(next)) => next,
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            };
+            let token = self.events.take_held();
+            match builder.push(step, token, position) {
+                Ok(Some(value)) => document = value,
+                Ok(None) => {}
+                Err(e) => {
+                    if let Err(e) = self.events.error(e) {
+                        break Err(e);
+                    }
+                }
             }
-            Some(Token::String(s)) => {
-                let value = s.clone();
-                self.advance_token()?;
-                Ok(JsonValue::String(value))
+        };
+
+        match result {
+            Ok(()) => Ok(document),
+            Err(e) => {
+                // The document may be complete, and too deep to drop recursively
+                document.drop_iteratively();
+                Err(e)
             }
-            Some(Token::LeftBrace) => self.parse_object(),
-            Some(Token::LeftBracket) => self.parse_array(),
-            Some(token) => Err(JsonError::UnexpectedToken(format!("{:?}", token))),
-            None => Err(JsonError::UnexpectedEof),
         }
     }
+}
 
-    fn parse_object(&mut self) -> Result<JsonValue> {
-        // Consume the opening brace
-        self.advance_token()?;
+/// A kind of document `TreeBuilder` can build, from tokens whose strings
+/// may borrow from input that lives for `'a`.
+pub(crate) trait Tree<'a>: Sized {
+    type Key: Borrow<str> + Hash + Eq + Clone;
 
-        let mut object = HashMap::new();
+    fn key(key: Cow<'a, str>) -> Self::Key;
+
+    /// The value of a scalar token, or null for anything else.
+    fn scalar(token: Option<Token<'a>>) -> Self;
+
+    fn array(values: Vec<Self>) -> Self;
+
+    fn object(members: Map<Self::Key, Self>) -> Self;
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>>;
+
+    fn drop_iteratively(self);
+}
 
-        // Handle empty object
-        if let Some(Token::RightBrace) = &self.current_token {
-            self.advance_token()?;
-            return Ok(JsonValue::Object(object));
+impl<'a> Tree<'a> for JsonValue {
+    type Key = String;
+
+    fn key(key: Cow<'a, str>) -> String {
+        key.into_owned()
+    }
+
+    fn scalar(token: Option<Token<'a>>) -> Self {
+        match token {
+            Some(Token::Boolean(b)) => JsonValue::Boolean(b),
+            Some(Token::Number(n)) => JsonValue::Number(n),
+            Some(Token::String(s)) => JsonValue::String(s.into_owned()),
+            _ => JsonValue::Null,
         }
+    }
 
-        loop {
-            // Parse key (must be a string)
-            let key = match &self.current_token {
-                Some(Token::String(s)) => {
-                    let key = s.clone();
-                    self.advance_token()?;
-                    key
-                }
-                Some(token) => {
-                    return Err(JsonError::UnexpectedToken(format!(
-                        "Expected string key, got {:?}",
-                        token
-                    )))
-                }
-                None => return Err(JsonError::UnexpectedEof),
-            };
+    fn array(values: Vec<Self>) -> Self {
+        JsonValue::Array(values)
+    }
 
-            // Parse colon
-            match &self.current_token {
-                Some(Token::Colon) => self.advance_token()?,
-                Some(token) => {
-                    return Err(JsonError::UnexpectedToken(format!(
-                        "Expected ':', got {:?}",
-                        token
-                    )))
-                }
-                None => return Err(JsonError::UnexpectedEof),
-            }
+    fn object(members: Map) -> Self {
+        JsonValue::Object(members)
+    }
 
-            // Parse value
-            let value = self.parse_value()?;
-
-            // Add key-value pair to object
-            object.insert(key, value);
-
-            // Check for comma or closing brace
-            match &self.current_token {
-                Some(Token::Comma) => {
-                    self.advance_token()?;
-                    // Handle trailing comma (not allowed in JSON)
-                    if let Some(Token::RightBrace) = &self.current_token {
-                        return Err(JsonError::UnexpectedToken(
-                            "Trailing comma in object".to_string(),
-                        ));
-                    }
-                }
-                Some(Token::RightBrace) => {
-                    self.advance_token()?;
-                    break;
-                }
-                Some(token) => {
-                    return Err(JsonError::UnexpectedToken(format!(
-                        "Expected ',' or '}}', got {:?}",
-                        token
-                    )))
-                }
-                None => return Err(JsonError::UnexpectedEof),
-            }
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
         }
+    }
 
-        Ok(JsonValue::Object(object))
+    fn drop_iteratively(self) {
+        JsonValue::drop_iteratively(self)
     }
+}
 
-    fn parse_array(&mut self) -> Result<JsonValue> {
-        // Consume the opening bracket
-        self.advance_token()?;
+impl<'a> Tree<'a> for BorrowedValue<'a> {
+    type Key = Cow<'a, str>;
 
-        let mut array = Vec::new();
+    fn key(key: Cow<'a, str>) -> Cow<'a, str> {
+        key
+    }
 
-        // Handle empty array
-        if let Some(Token::RightBracket) = &self.current_token {
-            self.advance_token()?;
-            return Ok(JsonValue::Array(array));
+    fn scalar(token: Option<Token<'a>>) -> Self {
+        match token {
+            Some(Token::Boolean(b)) => BorrowedValue::Boolean(b),
+            Some(Token::Number(n)) => BorrowedValue::Number(n),
+            Some(Token::String(s)) => BorrowedValue::String(s),
+            _ => BorrowedValue::Null,
         }
+    }
 
-        loop {
-            // Parse value
-            let value = self.parse_value()?;
-
-            // Add value to array
-            array.push(value);
-
-            // Check for comma or closing bracket
-            match &self.current_token {
-                Some(Token::Comma) => {
-                    self.advance_token()?;
-                    // Handle trailing comma (not allowed in JSON)
-                    if let Some(Token::RightBracket) = &self.current_token {
-                        return Err(JsonError::UnexpectedToken(
-                            "Trailing comma in array".to_string(),
-                        ));
-                    }
+    fn array(values: Vec<Self>) -> Self {
+        BorrowedValue::Array(values)
+    }
+
+    fn object(members: Map<Cow<'a, str>, Self>) -> Self {
+        BorrowedValue::Object(members)
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            BorrowedValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    fn drop_iteratively(self) {
+        BorrowedValue::drop_iteratively(self)
+    }
+}
+
+/// Assembles a document from events, one at a time.
+pub(crate) struct TreeBuilder<'a, V: Tree<'a>> {
+    /// Containers we are inside of, innermost last
+    stack: Vec<Frame<'a, V>>,
+    duplicate_keys: DuplicateKeys,
+}
+
+impl<'a, V: Tree<'a>> TreeBuilder<'a, V> {
+    pub(crate) fn new(options: ParseOptions) -> Self {
+        TreeBuilder {
+            stack: Vec::new(),
+            duplicate_keys: options.duplicate_keys,
+        }
+    }
+
+    /// Take the next step, with the token a `Key` or `Value` step is made
+    /// of, returning the document once it is complete.
+    ///
+    /// An error is a duplicate key the options reject. The first value for
+    /// the key is kept, and the builder can carry on from there.
+    pub(crate) fn push(
+        &mut self,
+        step: Step,
+        token: Option<Token<'a>>,
+        position: Position,
+    ) -> Result<Option<V>> {
+        let value = match step {
+            Step::StartObject => {
+                self.stack.push(Frame::Object(ObjectFrame {
+                    object: Map::new(),
+                    key: None,
+                    key_position: position,
+                    key_positions: Vec::new(),
+                    collected: None,
+                }));
+                return Ok(None);
+            }
+            Step::StartArray => {
+                self.stack.push(Frame::Array(Vec::new()));
+                return Ok(None);
+            }
+            Step::Key => {
+                if let Some(Frame::Object(frame)) = self.stack.last_mut()
+                    && let Some(Token::String(key)) = token
+                {
+                    frame.key = Some(V::key(key));
+                    frame.key_position = position;
                 }
-                Some(Token::RightBracket) => {
-                    self.advance_token()?;
-                    break;
+                return Ok(None);
+            }
+            Step::Value => V::scalar(token),
+            Step::EndObject | Step::EndArray => match self.stack.pop() {
+                Some(Frame::Array(array)) => V::array(array),
+                Some(Frame::Object(frame)) => V::object(frame.object),
+                None => unreachable!("events are balanced"),
+            },
+        };
+
+        // Hand the finished value to its container
+        match self.stack.last_mut() {
+            None => return Ok(Some(value)),
+            Some(Frame::Array(array)) => array.push(value),
+            Some(Frame::Object(frame)) => frame.insert(value, self.duplicate_keys)?,
+        }
+        Ok(None)
+    }
+}
+
+/// Values already finished may be nested deeper than dropping them
+/// recursively copes with, such as when a parse fails partway through.
+impl<'a, V: Tree<'a>> Drop for TreeBuilder<'a, V> {
+    fn drop(&mut self) {
+        for frame in self.stack.drain(..) {
+            match frame {
+                Frame::Array(values) => values.into_iter().for_each(V::drop_iteratively),
+                Frame::Object(frame) => frame
+                    .object
+                    .into_iter()
+                    .for_each(|(_, value)| value.drop_iteratively()),
+            }
+        }
+    }
+}
+
+/// A container whose members are still being parsed.
+enum Frame<'a, V: Tree<'a>> {
+    Array(Vec<V>),
+    Object(ObjectFrame<'a, V>),
+}
+
+struct ObjectFrame<'a, V: Tree<'a>> {
+    object: Map<V::Key, V>,
+    /// The key of the member whose value is being parsed, and where it is.
+    key: Option<V::Key>,
+    key_position: Position,
+    /// Where each member's key is, in the same order as `object`; only kept
+    /// for `DuplicateKeys::Error`, which reports it.
+    key_positions: Vec<Position>,
+    /// Indices of members whose values `DuplicateKeys::CollectAll` has
+    /// gathered into an array, made at its first duplicate.
+    collected: Option<HashSet<usize>>,
+}
+
+impl<'a, V: Tree<'a>> ObjectFrame<'a, V> {
+    /// Add the member whose value has just been parsed to the object,
+    /// following `duplicate_keys` if the key is already there.
+    fn insert(&mut self, value: V, duplicate_keys: DuplicateKeys) -> Result<()> {
+        let key = self.key.take().expect("a key comes before every member");
+        let index = match self.object.get_index_of(key.borrow()) {
+            Some(index) => index,
+            None => {
+                self.object.insert(key, value);
+                if duplicate_keys == DuplicateKeys::Error {
+                    self.key_positions.push(self.key_position);
                 }
-                Some(token) => {
-                    return Err(JsonError::UnexpectedToken(format!(
-                        "Expected ',' or ']', got {:?}",
-                        token
-                    )))
+                return Ok(());
+            }
+        };
+
+        match duplicate_keys {
+            DuplicateKeys::Error => {
+                let first = self.key_positions[index];
+                let key = key.borrow().to_string();
+                return Err(JsonError::DuplicateKey(key, first, self.key_position));
+            }
+            DuplicateKeys::FirstWins => {}
+            DuplicateKeys::LastWins => {
+                self.object.insert(key, value);
+            }
+            DuplicateKeys::CollectAll => {
+                let existing = &mut self.object[key.borrow()];
+                if self
+                    .collected
+                    .get_or_insert_with(HashSet::new)
+                    .insert(index)
+                {
+                    let first = std::mem::replace(existing, V::scalar(None));
+                    *existing = V::array(vec![first, value]);
+                } else if let Some(values) = existing.as_array_mut() {
+                    values.push(value);
                 }
-                None => return Err(JsonError::UnexpectedEof),
             }
         }
-
-        Ok(JsonValue::Array(array))
+        Ok(())
     }
 }
 
 /// Parse a JSON string into a JsonValue
 pub fn parse_json(input: &str) -> Result<JsonValue> {
-    let mut parser = Parser::new(input)?;
-    parser.parse()
+    Parser::new(input).parse()
+}
+
+/// Parse a JSON string into a JsonValue using the given options
+pub fn parse_json_with(input: &str, options: ParseOptions) -> Result<JsonValue> {
+    Parser::with_options(input, options).parse()
+}
+
+/// Parse JSON read from `reader`, without holding the whole text in memory.
+///
+/// Reads are buffered internally, so there is no need to wrap `reader` in a
+/// `BufReader`.
+pub fn parse_reader<R: Read>(reader: R) -> Result<JsonValue> {
+    parse_reader_with(reader, ParseOptions::default())
+}
+
+/// Parse JSON read from `reader` using the given options
+pub fn parse_reader_with<R: Read>(reader: R, options: ParseOptions) -> Result<JsonValue> {
+    Parser::from_reader(reader, options).parse()
+}
+
+/// Parse JSON held as UTF-8 bytes.
+///
+/// There is no need to check the bytes with `std::str::from_utf8` first:
+/// only strings can hold anything but ASCII, and they are checked as they
+/// are read. Invalid UTF-8 fails with `JsonError::Io`.
+pub fn parse_slice(input: &[u8]) -> Result<JsonValue> {
+    parse_slice_with(input, ParseOptions::default())
+}
+
+/// Parse JSON held as UTF-8 bytes using the given options
+pub fn parse_slice_with(input: &[u8], options: ParseOptions) -> Result<JsonValue> {
+    Parser::from_slice(input, options).parse()
+}
+
+/// Parse a JSON string into a value whose strings borrow from `input`
+/// wherever they have no escapes.
+pub fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>> {
+    Parser::new(input).parse_borrowed()
+}
+
+/// Parse a JSON string into a borrowed value using the given options
+pub fn parse_borrowed_with(input: &str, options: ParseOptions) -> Result<BorrowedValue<'_>> {
+    Parser::with_options(input, options).parse_borrowed()
+}
+
+/// Parse a JSON string, collecting every syntax error rather than stopping
+/// at the first one. See [`Parser::parse_recovering`].
+pub fn parse_json_recovering(input: &str) -> (JsonValue, Vec<JsonError>) {
+    Parser::new(input).parse_recovering()
 }
@@ -0,0 +1,380 @@
+use crate::error::{PatchError, PointerError};
+use crate::json::JsonValue;
+use crate::map::Map;
+use crate::parser::parse_json;
+use crate::pointer::{lookup, parse_pointer};
+use std::fmt;
+
+type Result<T> = std::result::Result<T, PatchError>;
+
+/// One operation of a JSON Patch. Paths are JSON Pointers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    /// Add a member, insert an array element, or replace the whole value.
+    Add {
+        path: String,
+        value: JsonValue,
+    },
+    Remove {
+        path: String,
+    },
+    /// Replace a value that must already exist.
+    Replace {
+        path: String,
+        value: JsonValue,
+    },
+    /// Remove the value at `from` and add it at `path`.
+    Move {
+        from: String,
+        path: String,
+    },
+    /// Add a copy of the value at `from` at `path`.
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// Fail unless the value at `path` equals `value`.
+    Test {
+        path: String,
+        value: JsonValue,
+    },
+}
+
+/// A JSON Patch (RFC 6902): a list of operations applied in order.
+///
+/// Applying a patch is all or nothing. If an operation fails, the ones
+/// before it are undone and the target is left as it was.
+///
+/// ```
+/// use json_parser::{Patch, parse_json};
+///
+/// let mut config = parse_json(r#"{"retries": 3, "hosts": ["a"]}"#).unwrap();
+/// let patch = Patch::parse(r#"[
+///     {"op": "test", "path": "/retries", "value": 3},
+///     {"op": "replace", "path": "/retries", "value": 5},
+///     {"op": "add", "path": "/hosts/-", "value": "b"}
+/// ]"#).unwrap();
+/// patch.apply(&mut config).unwrap();
+/// assert_eq!(config, parse_json(r#"{"retries": 5, "hosts": ["a", "b"]}"#).unwrap());
+///
+/// // The test fails now, so nothing is changed
+/// let before = config.clone();
+/// assert!(patch.apply(&mut config).is_err());
+/// assert_eq!(config, before);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Patch {
+    operations: Vec<PatchOperation>,
+}
+
+impl Patch {
+    pub fn new(operations: Vec<PatchOperation>) -> Self {
+        Patch { operations }
+    }
+
+    /// Parse a patch from JSON text.
+    pub fn parse(text: &str) -> Result<Patch> {
+        let value = parse_json(text).map_err(PatchError::Parse)?;
+        Patch::from_value(&value)
+    }
+
+    /// Read a patch from its JSON form: an array of operation objects.
+    pub fn from_value(value: &JsonValue) -> Result<Patch> {
+        let JsonValue::Array(operations) = value else {
+            return Err(PatchError::NotAnArray);
+        };
+        let operations = operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| read_operation(index, operation))
+            .collect::<Result<_>>()?;
+        Ok(Patch { operations })
+    }
+
+    /// The patch in its JSON form.
+    pub fn to_value(&self) -> JsonValue {
+        JsonValue::Array(self.operations.iter().map(write_operation).collect())
+    }
+
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.operations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Apply every operation to `target` in turn, or, if one fails, none of
+    /// them.
+    pub fn apply(&self, target: &mut JsonValue) -> Result<()> {
+        let mut undo = Vec::new();
+        for (index, operation) in self.operations.iter().enumerate() {
+            if let Err(e) = apply_operation(target, index, operation, &mut undo) {
+                for step in undo.into_iter().rev() {
+                    step.revert(target);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<PatchOperation>> for Patch {
+    fn from(operations: Vec<PatchOperation>) -> Self {
+        Patch::new(operations)
+    }
+}
+
+/// Prints the patch as JSON.
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_value(), f)
+    }
+}
+
+fn read_operation(index: usize, operation: &JsonValue) -> Result<PatchOperation> {
+    let JsonValue::Object(members) = operation else {
+        return Err(PatchError::InvalidOperation(
+            index,
+            "an operation must be an object".to_string(),
+        ));
+    };
+    let string = |name: &str| match members.get(name) {
+        Some(JsonValue::String(s)) => Ok(s.clone()),
+        Some(_) => Err(PatchError::InvalidOperation(
+            index,
+            format!("\"{}\" must be a string", name),
+        )),
+        None => Err(PatchError::InvalidOperation(
+            index,
+            format!("missing \"{}\"", name),
+        )),
+    };
+    let value = || {
+        members
+            .get("value")
+            .cloned()
+            .ok_or_else(|| PatchError::InvalidOperation(index, "missing \"value\"".to_string()))
+    };
+
+    let path = string("path")?;
+    Ok(match string("op")?.as_str() {
+        "add" => PatchOperation::Add {
+            path,
+            value: value()?,
+        },
+        "remove" => PatchOperation::Remove { path },
+        "replace" => PatchOperation::Replace {
+            path,
+            value: value()?,
+        },
+        "move" => PatchOperation::Move {
+            from: string("from")?,
+            path,
+        },
+        "copy" => PatchOperation::Copy {
+            from: string("from")?,
+            path,
+        },
+        "test" => PatchOperation::Test {
+            path,
+            value: value()?,
+        },
+        op => {
+            return Err(PatchError::InvalidOperation(
+                index,
+                format!("unknown op {:?}", op),
+            ));
+        }
+    })
+}
+
+fn write_operation(operation: &PatchOperation) -> JsonValue {
+    let string = |s: &str| JsonValue::String(s.to_string());
+    let mut members = Map::new();
+    let (op, path) = match operation {
+        PatchOperation::Add { path, .. } => ("add", path),
+        PatchOperation::Remove { path } => ("remove", path),
+        PatchOperation::Replace { path, .. } => ("replace", path),
+        PatchOperation::Move { path, .. } => ("move", path),
+        PatchOperation::Copy { path, .. } => ("copy", path),
+        PatchOperation::Test { path, .. } => ("test", path),
+    };
+    members.insert("op".to_string(), string(op));
+    match operation {
+        PatchOperation::Move { from, .. } | PatchOperation::Copy { from, .. } => {
+            members.insert("from".to_string(), string(from));
+        }
+        _ => {}
+    }
+    members.insert("path".to_string(), string(path));
+    match operation {
+        PatchOperation::Add { value, .. }
+        | PatchOperation::Replace { value, .. }
+        | PatchOperation::Test { value, .. } => {
+            members.insert("value".to_string(), value.clone());
+        }
+        _ => {}
+    }
+    JsonValue::Object(members)
+}
+
+/// What to do to take back one change an operation made.
+enum Undo {
+    /// Remove what was added at the pointer.
+    Remove(String),
+    /// Put an array element back at the pointer.
+    Insert(String, JsonValue),
+    /// Put an object member back at its old place among the members of the
+    /// object at the pointer.
+    Restore {
+        object: String,
+        index: usize,
+        key: String,
+        value: JsonValue,
+    },
+    /// Put back the value replaced at the pointer.
+    Set(String, JsonValue),
+}
+
+impl Undo {
+    fn revert(self, target: &mut JsonValue) {
+        // Each change is undone in the state it left the target in
+        const UNDONE: &str = "undoing a change that was made";
+        match self {
+            Undo::Remove(pointer) => {
+                target.remove_at(&pointer).expect(UNDONE);
+            }
+            Undo::Insert(pointer, value) => {
+                target.insert_at(&pointer, value).expect(UNDONE);
+            }
+            Undo::Restore {
+                object,
+                index,
+                key,
+                value,
+            } => match target.pointer_mut(&object) {
+                Some(JsonValue::Object(members)) => members.shift_insert(index, key, value),
+                _ => unreachable!("{}", UNDONE),
+            },
+            Undo::Set(pointer, value) => {
+                target.replace_at(&pointer, value).expect(UNDONE);
+            }
+        }
+    }
+}
+
+fn apply_operation(
+    target: &mut JsonValue,
+    index: usize,
+    operation: &PatchOperation,
+    undo: &mut Vec<Undo>,
+) -> Result<()> {
+    let failed = |pointer: &str| {
+        let pointer = pointer.to_string();
+        move |e| PatchError::Pointer(index, pointer, e)
+    };
+
+    match operation {
+        PatchOperation::Add { path, value } => {
+            add(target, path, value.clone(), undo).map_err(failed(path))
+        }
+        PatchOperation::Remove { path } => {
+            remove(target, path, undo).map_err(failed(path))?;
+            Ok(())
+        }
+        PatchOperation::Replace { path, value } => {
+            let old = target
+                .replace_at(path, value.clone())
+                .map_err(failed(path))?;
+            undo.push(Undo::Set(path.clone(), old));
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(PatchError::InvalidOperation(
+                    index,
+                    format!("cannot move {:?} into itself at {:?}", from, path),
+                ));
+            }
+            let value = remove(target, from, undo).map_err(failed(from))?;
+            add(target, path, value, undo).map_err(failed(path))
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = lookup(target, from).map_err(failed(from))?.clone();
+            add(target, path, value, undo).map_err(failed(path))
+        }
+        PatchOperation::Test { path, value } => {
+            let actual = lookup(target, path).map_err(failed(path))?;
+            if actual == value {
+                Ok(())
+            } else {
+                Err(PatchError::TestFailed(index, path.clone()))
+            }
+        }
+    }
+}
+
+/// The pointer to the container the last token of `pointer` is looked up
+/// in, with that token unescaped, or `None` for the empty pointer.
+fn split_pointer(pointer: &str) -> std::result::Result<Option<(&str, String)>, PointerError> {
+    let tokens = parse_pointer(pointer)?;
+    Ok(tokens.last().map(|last| {
+        let parent = &pointer[..pointer.rfind('/').expect("pointers start with '/'")];
+        (parent, last.to_string())
+    }))
+}
+
+fn add(
+    target: &mut JsonValue,
+    path: &str,
+    value: JsonValue,
+    undo: &mut Vec<Undo>,
+) -> std::result::Result<(), PointerError> {
+    let Some((parent, last)) = split_pointer(path)? else {
+        let old = std::mem::replace(target, value);
+        undo.push(Undo::Set(String::new(), old));
+        return Ok(());
+    };
+    let appended_at = match target.pointer(parent) {
+        Some(JsonValue::Array(elements)) if last == "-" => Some(elements.len()),
+        _ => None,
+    };
+
+    let replaced = target.insert_at(path, value)?;
+    undo.push(match (replaced, appended_at) {
+        (Some(old), _) => Undo::Set(path.to_string(), old),
+        (None, Some(index)) => Undo::Remove(format!("{}/{}", parent, index)),
+        (None, None) => Undo::Remove(path.to_string()),
+    });
+    Ok(())
+}
+
+fn remove(
+    target: &mut JsonValue,
+    path: &str,
+    undo: &mut Vec<Undo>,
+) -> std::result::Result<JsonValue, PointerError> {
+    let Some((parent, last)) = split_pointer(path)? else {
+        let old = target.remove_at(path)?;
+        undo.push(Undo::Set(String::new(), old.clone()));
+        return Ok(old);
+    };
+    let member_index = match target.pointer(parent) {
+        Some(JsonValue::Object(members)) => members.get_index_of(&last),
+        _ => None,
+    };
+
+    let value = target.remove_at(path)?;
+    undo.push(match member_index {
+        Some(index) => Undo::Restore {
+            object: parent.to_string(),
+            index,
+            key: last,
+            value: value.clone(),
+        },
+        None => Undo::Insert(path.to_string(), value.clone()),
+    });
+    Ok(value)
+}
@@ -0,0 +1,191 @@
+use crate::error::PointerError;
+use crate::json::JsonValue;
+use std::borrow::Cow;
+
+type Result<T> = std::result::Result<T, PointerError>;
+
+impl JsonValue {
+    /// The value `pointer` refers to, or `None` if there isn't one or the
+    /// pointer is malformed.
+    ///
+    /// A JSON Pointer (RFC 6901) is a series of reference tokens, each after
+    /// a `/`. A token names an object member, or an array element by its
+    /// index; `-` stands for the position just past the last element. Within
+    /// a token, `~1` stands for `/` and `~0` for `~`. The empty pointer is
+    /// the whole value.
+    ///
+    /// ```
+    /// use json_parser::parse_json;
+    ///
+    /// let value = parse_json(r#"{"a/b": [10, {"c": true}]}"#).unwrap();
+    /// assert_eq!(value.pointer("/a~1b/1/c"), Some(&parse_json("true").unwrap()));
+    /// assert_eq!(value.pointer("/a~1b/2"), None);
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        lookup(self, pointer).ok()
+    }
+
+    /// Like `pointer`, but mutable.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue> {
+        let tokens = parse_pointer(pointer).ok()?;
+        walk_mut(self, &tokens).ok()
+    }
+
+    /// Add `value` where `pointer` refers to, returning the value it
+    /// replaced, if any.
+    ///
+    /// A new object member is added at the end, and an existing one has its
+    /// value replaced. An array element is inserted at its index, shifting
+    /// later elements along; `-` appends. The empty pointer replaces the
+    /// whole value. Everything but the last token must already exist.
+    pub fn insert_at(&mut self, pointer: &str, value: JsonValue) -> Result<Option<JsonValue>> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+        match walk_mut(self, parents)? {
+            JsonValue::Object(members) => Ok(members.insert(last.to_string(), value)),
+            JsonValue::Array(elements) => {
+                let index = array_index(last, elements.len())?;
+                if index > elements.len() {
+                    return Err(PointerError::IndexOutOfRange(index, elements.len()));
+                }
+                elements.insert(index, value);
+                Ok(None)
+            }
+            _ => Err(PointerError::NotAContainer(last.to_string())),
+        }
+    }
+
+    /// Take out the value `pointer` refers to.
+    ///
+    /// Later array elements shift down to fill the gap. With the empty
+    /// pointer, the whole value is taken, leaving `JsonValue::Null`.
+    pub fn remove_at(&mut self, pointer: &str) -> Result<JsonValue> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(std::mem::replace(self, JsonValue::Null));
+        };
+        match walk_mut(self, parents)? {
+            JsonValue::Object(members) => members
+                .remove(last)
+                .ok_or_else(|| PointerError::MissingKey(last.to_string())),
+            JsonValue::Array(elements) => {
+                let index = array_index(last, elements.len())?;
+                if index >= elements.len() {
+                    return Err(PointerError::IndexOutOfRange(index, elements.len()));
+                }
+                Ok(elements.remove(index))
+            }
+            _ => Err(PointerError::NotAContainer(last.to_string())),
+        }
+    }
+
+    /// Put `value` in place of the one `pointer` refers to, which must
+    /// exist, returning the old one.
+    pub fn replace_at(&mut self, pointer: &str, value: JsonValue) -> Result<JsonValue> {
+        let tokens = parse_pointer(pointer)?;
+        let target = walk_mut(self, &tokens)?;
+        Ok(std::mem::replace(target, value))
+    }
+}
+
+/// The value `pointer` refers to in `value`, or why there isn't one.
+pub(crate) fn lookup<'v>(value: &'v JsonValue, pointer: &str) -> Result<&'v JsonValue> {
+    parse_pointer(pointer)?
+        .iter()
+        .try_fold(value, |value, token| step(value, token))
+}
+
+/// Split `pointer` into its reference tokens, unescaped.
+pub(crate) fn parse_pointer(pointer: &str) -> Result<Vec<Cow<'_, str>>> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(PointerError::InvalidPointer(pointer.to_string()));
+    };
+    rest.split('/')
+        .map(|token| {
+            unescape(token).ok_or_else(|| PointerError::InvalidPointer(pointer.to_string()))
+        })
+        .collect()
+}
+
+fn unescape(token: &str) -> Option<Cow<'_, str>> {
+    if !token.contains('~') {
+        return Some(Cow::Borrowed(token));
+    }
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next()? {
+                '0' => unescaped.push('~'),
+                '1' => unescaped.push('/'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+    Some(Cow::Owned(unescaped))
+}
+
+/// Escape `key` for use as a reference token.
+pub fn escape(key: &str) -> Cow<'_, str> {
+    if key.contains(['~', '/']) {
+        Cow::Owned(key.replace('~', "~0").replace('/', "~1"))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// The index `token` stands for in an array of length `len`, which may be
+/// past its end.
+fn array_index(token: &str, len: usize) -> Result<usize> {
+    if token == "-" {
+        return Ok(len);
+    }
+    // Digits only, without leading zeros
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    match token.parse() {
+        Ok(index) if valid => Ok(index),
+        _ => Err(PointerError::InvalidIndex(token.to_string())),
+    }
+}
+
+fn step<'v>(value: &'v JsonValue, token: &str) -> Result<&'v JsonValue> {
+    match value {
+        JsonValue::Object(members) => members
+            .get(token)
+            .ok_or_else(|| PointerError::MissingKey(token.to_string())),
+        JsonValue::Array(elements) => {
+            let index = array_index(token, elements.len())?;
+            elements
+                .get(index)
+                .ok_or(PointerError::IndexOutOfRange(index, elements.len()))
+        }
+        _ => Err(PointerError::NotAContainer(token.to_string())),
+    }
+}
+
+fn walk_mut<'v>(mut value: &'v mut JsonValue, tokens: &[Cow<str>]) -> Result<&'v mut JsonValue> {
+    for token in tokens {
+        value = match value {
+            JsonValue::Object(members) => members
+                .get_mut(token)
+                .ok_or_else(|| PointerError::MissingKey(token.to_string()))?,
+            JsonValue::Array(elements) => {
+                let len = elements.len();
+                let index = array_index(token, len)?;
+                elements
+                    .get_mut(index)
+                    .ok_or(PointerError::IndexOutOfRange(index, len))?
+            }
+            _ => return Err(PointerError::NotAContainer(token.to_string())),
+        };
+    }
+    Ok(value)
+}
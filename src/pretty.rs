@@ -0,0 +1,275 @@
+use crate::error::SerializeError;
+use crate::json::JsonValue;
+use crate::serializer::{IoSink, NonFiniteFloat, Sink, escape_str, write_number};
+use std::io;
+
+/// Layout settings for [`to_string_pretty`] and [`to_writer_pretty`].
+///
+/// Arrays and objects are printed on one line when they fit within
+/// `max_width` columns and broken one member per line otherwise, deciding
+/// separately at each level of nesting.
+#[derive(Debug, Clone, Copy)]
+pub struct PrettyConfig {
+    /// Columns per indentation level. With `use_tabs`, each level is one tab
+    /// that counts as this many columns when measuring line width.
+    pub indent_width: usize,
+    pub use_tabs: bool,
+    /// Write `"key": value` rather than `"key":value`.
+    pub space_after_colon: bool,
+    pub sort_keys: bool,
+    pub trailing_newline: bool,
+    /// The line width to fit containers into. Use `0` to break every
+    /// non-empty container.
+    pub max_width: usize,
+    pub non_finite: NonFiniteFloat,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            indent_width: 2,
+            use_tabs: false,
+            space_after_colon: true,
+            sort_keys: false,
+            trailing_newline: false,
+            max_width: 80,
+            non_finite: NonFiniteFloat::Error,
+        }
+    }
+}
+
+/// Serialize `value` as indented JSON.
+pub fn to_string_pretty(
+    value: &JsonValue,
+    config: &PrettyConfig,
+) -> Result<String, SerializeError> {
+    let mut out = String::new();
+    write_pretty(&mut out, value, config)?;
+    Ok(out)
+}
+
+/// Serialize `value` as indented JSON into `writer`.
+pub fn to_writer_pretty<W: io::Write>(
+    writer: W,
+    value: &JsonValue,
+    config: &PrettyConfig,
+) -> Result<(), SerializeError> {
+    write_pretty(&mut IoSink(writer), value, config)
+}
+
+fn write_pretty<S: Sink>(
+    out: &mut S,
+    value: &JsonValue,
+    config: &PrettyConfig,
+) -> Result<(), SerializeError> {
+    let docs = build(value, config)?;
+    Printer::new(out, config).print(&docs)?;
+    if config.trailing_newline {
+        out.write("\n")?;
+    }
+    Ok(())
+}
+
+/// A document in the style of Wadler's "prettier printer", flattened into a
+/// stream: text plus line breaks that each group lays out either all flat
+/// or all broken.
+///
+/// Keeping it flat, rather than as a tree, lets building and printing it
+/// work without recursion however deeply the value is nested.
+enum Doc {
+    Text(String),
+    /// A space when flat, a newline when broken.
+    Line,
+    /// Nothing when flat, a newline when broken.
+    SoftLine,
+    /// Indent the line breaks that follow by one more level.
+    Indent,
+    Dedent,
+    GroupStart,
+    GroupEnd,
+}
+
+/// The members of an array or object still to be built, and whether any
+/// have been already.
+enum Open<'v> {
+    Array(std::slice::Iter<'v, JsonValue>, bool),
+    Object(std::vec::IntoIter<(&'v String, &'v JsonValue)>, bool),
+}
+
+/// Lay out `value` as `open item, item, ... close` for each non-empty
+/// container, broken one item per line if it won't fit.
+fn build(value: &JsonValue, config: &PrettyConfig) -> Result<Vec<Doc>, SerializeError> {
+    let colon = if config.space_after_colon { ": " } else { ":" };
+    let mut docs = Vec::new();
+    let mut open = Vec::new();
+    let mut next = Some(value);
+
+    loop {
+        match next.take() {
+            None => {}
+            Some(JsonValue::Array(arr)) if arr.is_empty() => docs.push(Doc::Text("[]".to_string())),
+            Some(JsonValue::Object(obj)) if obj.is_empty() => {
+                docs.push(Doc::Text("{}".to_string()))
+            }
+            Some(JsonValue::Array(arr)) => {
+                docs.extend([Doc::GroupStart, Doc::Text("[".to_string()), Doc::Indent]);
+                open.push(Open::Array(arr.iter(), false));
+            }
+            Some(JsonValue::Object(obj)) => {
+                let mut members: Vec<_> = obj.iter().collect();
+                if config.sort_keys {
+                    members.sort_by_key(|&(key, _)| key);
+                }
+                docs.extend([Doc::GroupStart, Doc::Text("{".to_string()), Doc::Indent]);
+                open.push(Open::Object(members.into_iter(), false));
+            }
+            Some(scalar) => {
+                let mut text = String::new();
+                match scalar {
+                    JsonValue::Number(n) => write_number(&mut text, n, config.non_finite)?,
+                    JsonValue::String(s) => escape_str(s, |chunk| text.write(chunk))?,
+                    JsonValue::Boolean(b) => text.push_str(if *b { "true" } else { "false" }),
+                    _ => text.push_str("null"),
+                }
+                docs.push(Doc::Text(text));
+            }
+        }
+
+        // Move on to the next item, closing the containers that are done
+        let (item, started) = match open.last_mut() {
+            None => return Ok(docs),
+            Some(Open::Array(items, started)) => (items.next().map(|val| (None, val)), started),
+            Some(Open::Object(members, started)) => {
+                (members.next().map(|(key, val)| (Some(key), val)), started)
+            }
+        };
+        match item {
+            Some((key, val)) => {
+                if std::mem::replace(started, true) {
+                    docs.extend([Doc::Text(",".to_string()), Doc::Line]);
+                } else {
+                    docs.push(Doc::SoftLine);
+                }
+                if let Some(key) = key {
+                    let mut key_text = String::new();
+                    escape_str(key, |chunk| key_text.write(chunk))?;
+                    key_text.push_str(colon);
+                    docs.push(Doc::Text(key_text));
+                }
+                next = Some(val);
+            }
+            None => {
+                let close = match open.pop() {
+                    Some(Open::Array(..)) => "]",
+                    _ => "}",
+                };
+                docs.extend([
+                    Doc::Dedent,
+                    Doc::SoftLine,
+                    Doc::Text(close.to_string()),
+                    Doc::GroupEnd,
+                ]);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+struct Printer<'a, S> {
+    out: &'a mut S,
+    config: &'a PrettyConfig,
+    /// Characters written on the current line so far.
+    column: usize,
+}
+
+impl<'a, S: Sink> Printer<'a, S> {
+    fn new(out: &'a mut S, config: &'a PrettyConfig) -> Self {
+        Printer {
+            out,
+            config,
+            column: 0,
+        }
+    }
+
+    fn print(&mut self, docs: &[Doc]) -> Result<(), SerializeError> {
+        let mut level = 0;
+        // The mode of each group we're inside, innermost last
+        let mut modes = Vec::new();
+
+        for (i, doc) in docs.iter().enumerate() {
+            let mode = modes.last().copied().unwrap_or(Mode::Break);
+            match doc {
+                Doc::Text(text) => {
+                    self.out.write(text)?;
+                    self.column += text.chars().count();
+                }
+                Doc::Line if mode == Mode::Flat => {
+                    self.out.write(" ")?;
+                    self.column += 1;
+                }
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine => self.newline(level)?,
+                Doc::Indent => level += 1,
+                Doc::Dedent => level -= 1,
+                Doc::GroupStart => {
+                    let remaining = self.config.max_width.saturating_sub(self.column);
+                    if mode == Mode::Flat || fits(&docs[i + 1..], remaining) {
+                        modes.push(Mode::Flat);
+                    } else {
+                        modes.push(Mode::Break);
+                    }
+                }
+                Doc::GroupEnd => {
+                    modes.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn newline(&mut self, level: usize) -> Result<(), SerializeError> {
+        self.out.write("\n")?;
+        if self.config.use_tabs {
+            for _ in 0..level {
+                self.out.write("\t")?;
+            }
+            self.column = level * self.config.indent_width;
+        } else {
+            let indent = level * self.config.indent_width;
+            self.out.write(&" ".repeat(indent))?;
+            self.column = indent;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the group that `docs` starts inside, laid out flat, plus
+/// whatever follows it up to the next line break, fits in `width` columns.
+fn fits(docs: &[Doc], width: usize) -> bool {
+    let mut remaining = isize::try_from(width).unwrap_or(isize::MAX);
+    // How many groups deep we are, or 0 once past the end of this one
+    let mut depth = 1;
+
+    for doc in docs {
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line if depth > 0 => remaining -= 1,
+            Doc::SoftLine if depth > 0 => {}
+            // The line ends here, so everything before it fit
+            Doc::Line | Doc::SoftLine => return true,
+            Doc::GroupStart if depth > 0 => depth += 1,
+            Doc::GroupEnd if depth > 0 => depth -= 1,
+            Doc::GroupStart | Doc::GroupEnd | Doc::Indent | Doc::Dedent => {}
+        }
+
+        if remaining < 0 {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,166 @@
+use crate::error::{Position, Result};
+use crate::events::{Event, EventParser, Step};
+use crate::json::JsonValue;
+use crate::options::ParseOptions;
+use crate::parser::TreeBuilder;
+use crate::source::ChunkSource;
+
+/// A parser that is handed its input a chunk at a time, as it arrives, and
+/// never waits for more.
+///
+/// Events come out as soon as the input for them is in; when more is needed,
+/// `next_event` returns `None` until the next chunk is fed. A chunk may end
+/// anywhere, even in the middle of a token or a UTF-8 character.
+///
+/// ```
+/// use json_parser::{Event, PushParser};
+///
+/// let mut parser = PushParser::new();
+/// parser.feed(br#"{"greeting": "hel"#);
+/// assert_eq!(parser.next_event().unwrap().unwrap().0, Event::StartObject);
+/// assert_eq!(parser.next_event().unwrap().unwrap().0, Event::Key("greeting"));
+/// assert!(parser.next_event().unwrap().is_none());
+///
+/// parser.feed(br#"lo"}"#);
+/// parser.finish();
+/// let mut rest = Vec::new();
+/// while let Some((event, _)) = parser.next_event().unwrap() {
+///     rest.push(format!("{:?}", event));
+/// }
+/// assert_eq!(rest, [r#"Value(String("hello"))"#, "EndObject"]);
+/// ```
+pub struct PushParser {
+    events: EventParser<'static, ChunkSource>,
+    /// Builds the document for `next_value`.
+    builder: TreeBuilder<'static, JsonValue>,
+    boundaries: Boundaries,
+    /// How many bytes had been fed when the input last ran out mid-event.
+    stalled_at: Option<usize>,
+    finished: bool,
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        PushParser::with_options(ParseOptions::default())
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        PushParser {
+            events: EventParser::with_source(ChunkSource::new(), options),
+            builder: TreeBuilder::new(options),
+            boundaries: Boundaries::default(),
+            stalled_at: None,
+            finished: false,
+        }
+    }
+
+    /// Hand over the next chunk of UTF-8 input.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after `finish`.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        assert!(!self.finished, "PushParser::feed called after finish");
+        self.boundaries.scan(chunk);
+        self.events.source_mut().push(chunk);
+    }
+
+    /// Mark the end of the input, so whatever is left can be parsed.
+    pub fn finish(&mut self) {
+        self.finished = true;
+        self.events.source_mut().finish();
+    }
+
+    /// The next event and where in the input it starts, or `None` if more
+    /// input is needed.
+    ///
+    /// Once `finish` has been called, `None` means the whole document has
+    /// been read; keep calling until then to catch errors at the end of the
+    /// input.
+    pub fn next_event(&mut self) -> Result<Option<(Event<'_>, Position)>> {
+        let step = self.next_step()?;
+        Ok(step.map(|(step, position)| (self.events.event(step), position)))
+    }
+
+    /// The whole document, as soon as its last event is in, or `None` if
+    /// more input is needed.
+    ///
+    /// This reads events itself, so use it instead of `next_event`, not
+    /// alongside it. After the document is returned, further calls check the
+    /// rest of the input the same way `next_event` does.
+    pub fn next_value(&mut self) -> Result<Option<JsonValue>> {
+        while let Some((step, position)) = self.next_step()? {
+            let token = self.events.take_held();
+            if let Some(value) = self.builder.push(step, token, position)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the next event, or take back everything read towards it if the
+    /// input runs out first.
+    fn next_step(&mut self) -> Result<Option<(Step, Position)>> {
+        // Without a new token boundary, the event would run out again
+        if !self.finished
+            && self
+                .stalled_at
+                .is_some_and(|fed| self.boundaries.last <= fed)
+        {
+            return Ok(None);
+        }
+
+        let checkpoint = self.events.checkpoint();
+        let mark = self.events.source_mut().mark();
+        let step = self.events.next_step();
+        if self.events.source_mut().starved() {
+            self.events.restore(checkpoint);
+            self.events.source_mut().rewind(mark);
+            self.stalled_at = Some(self.boundaries.fed);
+            return Ok(None);
+        }
+        self.stalled_at = None;
+        step
+    }
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new()
+    }
+}
+
+/// Tracks where in the input a token could end, so a parser that has run out
+/// of input only tries again once a chunk could let it finish the event.
+#[derive(Default)]
+struct Boundaries {
+    /// How many bytes have been scanned.
+    fed: usize,
+    /// How many bytes come up to and including the last possible end of a
+    /// token: whitespace or punctuation outside a string, or a closing quote.
+    last: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl Boundaries {
+    fn scan(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            self.fed += 1;
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    self.last = self.fed;
+                }
+            } else if byte == b'"' {
+                self.in_string = true;
+            } else if byte.is_ascii_whitespace() || b"{}[]:,".contains(&byte) {
+                self.last = self.fed;
+            }
+        }
+    }
+}
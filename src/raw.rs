@@ -0,0 +1,147 @@
+use crate::borrowed::BorrowedValue;
+use crate::error::Result;
+use crate::events::{EventParser, Step};
+use crate::json::JsonValue;
+use crate::lexer::Token;
+use crate::options::ParseOptions;
+use crate::parser::Parser;
+use crate::source::StrSource;
+use std::borrow::Cow;
+use std::fmt;
+
+/// A JSON value kept as the exact text it was parsed from, made by
+/// [`parse_raw`].
+///
+/// The text has been checked to be valid JSON, but nothing has been built
+/// from it. Look inside with `members` and `elements`, which leave the
+/// values they return raw too, parse it when its contents are needed, or
+/// pass it on byte for byte with `as_str`.
+///
+/// ```
+/// use json_parser::parse_raw;
+///
+/// let raw = parse_raw(r#"{"id": 7, "payload": {"big": [1, 2, 3]}}"#).unwrap();
+/// let id = raw.get("id").unwrap().parse().unwrap();
+/// let payload = raw.get("payload").unwrap();
+/// assert_eq!(id.to_string(), "7");
+/// assert_eq!(payload.as_str(), r#"{"big": [1, 2, 3]}"#);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RawValue<'a> {
+    text: &'a str,
+    options: ParseOptions,
+}
+
+impl<'a> RawValue<'a> {
+    /// The text of the value, without the whitespace around it.
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    /// Parse the value, with the options it was checked with.
+    ///
+    /// Duplicate keys are only looked for here, so `DuplicateKeys::Error`
+    /// can still fail.
+    pub fn parse(&self) -> Result<JsonValue> {
+        Parser::with_options(self.text, self.options).parse()
+    }
+
+    /// Parse the value, borrowing its strings from the text.
+    pub fn parse_borrowed(&self) -> Result<BorrowedValue<'a>> {
+        Parser::with_options(self.text, self.options).parse_borrowed()
+    }
+
+    /// The members of an object, in order, with their values still raw;
+    /// nothing for anything else.
+    pub fn members(self) -> impl Iterator<Item = (Cow<'a, str>, RawValue<'a>)> {
+        let mut events = self.children(Step::StartObject);
+        std::iter::from_fn(move || {
+            let events = events.as_mut()?;
+            let Ok(Some((Step::Key, _))) = events.next_step() else {
+                return None;
+            };
+            let key = match events.take_held() {
+                Some(Token::String(key)) => key,
+                _ => unreachable!("keys are always strings"),
+            };
+            Some((key, self.child(events)))
+        })
+    }
+
+    /// The elements of an array, in order, still raw; nothing for anything
+    /// else.
+    pub fn elements(self) -> impl Iterator<Item = RawValue<'a>> {
+        let mut events = self.children(Step::StartArray);
+        std::iter::from_fn(move || {
+            let events = events.as_mut()?;
+            let value = self.child(events);
+            (!value.text.is_empty()).then_some(value)
+        })
+    }
+
+    /// The value of the last member with `key`, which is the one parsing
+    /// keeps by default.
+    pub fn get(self, key: &str) -> Option<RawValue<'a>> {
+        self.members()
+            .filter(|(k, _)| k == key)
+            .map(|(_, value)| value)
+            .last()
+    }
+
+    /// Events for the value's text, with the one opening it read, if it is
+    /// the container `start` opens.
+    fn children(&self, start: Step) -> Option<EventParser<'a, StrSource<'a>>> {
+        let mut events = EventParser::with_options(self.text, self.options);
+        match (events.next_step(), start) {
+            (Ok(Some((Step::StartObject, _))), Step::StartObject)
+            | (Ok(Some((Step::StartArray, _))), Step::StartArray) => Some(events),
+            _ => None,
+        }
+    }
+
+    /// The next value `events` reads, or an empty one if the container ends
+    /// first.
+    fn child(&self, events: &mut EventParser<'a, StrSource<'a>>) -> RawValue<'a> {
+        // The text has been checked, so reading it again can't fail
+        let text = match events.skip_value() {
+            Ok(Some((Step::StartObject | Step::StartArray | Step::Value, start))) => {
+                &self.text[start.offset..events.offset()]
+            }
+            _ => "",
+        };
+        RawValue {
+            text,
+            options: self.options,
+        }
+    }
+}
+
+/// Prints the text exactly as it was parsed.
+impl fmt::Display for RawValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+/// Check that `input` is a single JSON value, without building anything
+/// from it.
+pub fn parse_raw(input: &str) -> Result<RawValue<'_>> {
+    parse_raw_with(input, ParseOptions::default())
+}
+
+/// Check that `input` is a single JSON value using the given options,
+/// without building anything from it.
+pub fn parse_raw_with(input: &str, options: ParseOptions) -> Result<RawValue<'_>> {
+    let mut events = EventParser::with_options(input, options);
+    let start = match events.skip_value()? {
+        Some((_, start)) => start.offset,
+        None => 0,
+    };
+    let end = events.offset();
+    // Make sure nothing but whitespace follows
+    while events.next_step()?.is_some() {}
+    Ok(RawValue {
+        text: &input[start..end],
+        options,
+    })
+}
@@ -0,0 +1,781 @@
+/// A regular expression in the ECMA-262 dialect JSON Schema uses for
+/// `pattern`.
+///
+/// Everything but backreferences, lookbehind and Unicode property escapes
+/// is supported. Patterns are not anchored: they match if they match
+/// anywhere in the text, unless they use `^` and `$`.
+///
+/// Matching runs every way through the pattern side by side rather than
+/// backtracking, so it takes time in proportion to the length of the text
+/// whatever the pattern, and never recurses along it. A lookahead is worked
+/// out for every position at once, in one pass back from the end of the
+/// text, the first time it is reached.
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    program: Program,
+}
+
+/// Above this many instructions, a pattern is rejected; counted repeats
+/// like `(a{1000}){1000}` would otherwise compile to millions.
+const PROGRAM_LIMIT: usize = 100_000;
+
+/// How deeply groups may nest within a pattern, so parsing and compiling
+/// it can't overflow the stack.
+const MAX_NESTING: usize = 128;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    /// Any character but a line terminator.
+    Any,
+    Class(Class),
+    Start,
+    End,
+    /// `\b`, or `\B` when false.
+    WordBoundary(bool),
+    /// `(?=...)`, or `(?!...)` when negated.
+    Lookahead {
+        node: Box<Node>,
+        negated: bool,
+    },
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// A set of characters, as sorted inclusive ranges.
+#[derive(Debug, Clone)]
+struct Class {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl Class {
+    fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|&(low, high)| low <= c && c <= high) != self.negated
+    }
+}
+
+const DIGITS: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[
+    ('\t', '\r'),
+    (' ', ' '),
+    ('\u{a0}', '\u{a0}'),
+    ('\u{1680}', '\u{1680}'),
+    ('\u{2000}', '\u{200a}'),
+    ('\u{2028}', '\u{2029}'),
+    ('\u{202f}', '\u{202f}'),
+    ('\u{205f}', '\u{205f}'),
+    ('\u{3000}', '\u{3000}'),
+    ('\u{feff}', '\u{feff}'),
+];
+
+/// The characters not in `ranges`, which must be sorted.
+fn complement(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut result = Vec::new();
+    let mut next = Some('\0');
+    for &(low, high) in ranges {
+        if let Some(start) = next
+            && start < low
+        {
+            result.push((start, before(low)));
+        }
+        next = after(high);
+    }
+    if let Some(start) = next {
+        result.push((start, char::MAX));
+    }
+    result
+}
+
+/// The character before `c`, skipping surrogates; `c` must not be `'\0'`.
+fn before(c: char) -> char {
+    match c {
+        '\u{e000}' => '\u{d7ff}',
+        c => char::from_u32(c as u32 - 1).expect("not a surrogate"),
+    }
+}
+
+/// The character after `c`, skipping surrogates.
+fn after(c: char) -> Option<char> {
+    match c {
+        '\u{d7ff}' => Some('\u{e000}'),
+        c => char::from_u32(c as u32 + 1),
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+impl Regex {
+    /// Compile `pattern`, or say what is wrong with it.
+    pub(crate) fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = RegexParser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.peek().is_some() {
+            return Err("unmatched ')'".to_string());
+        }
+        Ok(Regex {
+            program: Program::compile(&node)?,
+        })
+    }
+
+    /// Whether the pattern matches anywhere in `text`.
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        self.program.run(&text)
+    }
+}
+
+struct RegexParser {
+    chars: Vec<char>,
+    pos: usize,
+    /// How many groups the current position is inside of.
+    depth: usize,
+}
+
+impl RegexParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or("unexpected end of pattern")?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.concat()?];
+        while self.eat('|') {
+            branches.push(self.concat()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Node::Alternation(branches)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        Ok(match self.next()? {
+            '(' => self.group()?,
+            '[' => Node::Class(self.class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => self.escape()?,
+            '*' | '+' | '?' => return Err("nothing to repeat".to_string()),
+            '{' if self.counts().is_some() => return Err("nothing to repeat".to_string()),
+            c => Node::Char(c),
+        })
+    }
+
+    fn group(&mut self) -> Result<Node, String> {
+        let lookahead = |negated| {
+            move |node| Node::Lookahead {
+                node: Box::new(node),
+                negated,
+            }
+        };
+        let wrap: Box<dyn Fn(Node) -> Node> = if self.eat('?') {
+            match self.next()? {
+                ':' => Box::new(|node| node),
+                '=' => Box::new(lookahead(false)),
+                '!' => Box::new(lookahead(true)),
+                '<' if matches!(self.peek(), Some('=' | '!')) => {
+                    return Err("lookbehind is not supported".to_string());
+                }
+                '<' => {
+                    // Named groups match like any other; nothing is captured
+                    while self.next()? != '>' {}
+                    Box::new(|node| node)
+                }
+                c => return Err(format!("invalid group '(?{}'", c)),
+            }
+        } else {
+            Box::new(|node| node)
+        };
+        if self.depth == MAX_NESTING {
+            return Err("groups are nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let node = self.alternation()?;
+        self.depth -= 1;
+        if !self.eat(')') {
+            return Err("missing ')'".to_string());
+        }
+        Ok(wrap(node))
+    }
+
+    /// The bounds of a `{n}`, `{n,}` or `{n,m}` at the current position,
+    /// just after its `{`, consuming it.
+    fn counts(&mut self) -> Option<(usize, Option<usize>)> {
+        let start = self.pos;
+        let number = |parser: &mut Self| {
+            let from = parser.pos;
+            while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+                parser.pos += 1;
+            }
+            let digits: String = parser.chars[from..parser.pos].iter().collect();
+            digits.parse::<usize>().ok()
+        };
+        let bounds = number(self).and_then(|min| {
+            if !self.eat(',') {
+                return Some((min, Some(min)));
+            }
+            match self.peek() {
+                Some('}') => Some((min, None)),
+                _ => number(self).map(|max| (min, Some(max))),
+            }
+        });
+        match bounds {
+            Some(bounds) if self.eat('}') => Some(bounds),
+            _ => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+
+    fn quantifier(&mut self, node: Node) -> Result<Node, String> {
+        let bounds = match self.peek() {
+            Some('*') => Some((0, None)),
+            Some('+') => Some((1, None)),
+            Some('?') => Some((0, Some(1))),
+            _ => None,
+        };
+        let (min, max) = match bounds {
+            Some(bounds) => {
+                self.pos += 1;
+                bounds
+            }
+            None if self.eat('{') => match self.counts() {
+                Some(bounds) => bounds,
+                None => {
+                    // A '{' that doesn't start a quantifier is read as itself
+                    self.pos -= 1;
+                    return Ok(node);
+                }
+            },
+            None => return Ok(node),
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err("numbers out of order in {} quantifier".to_string());
+        }
+        if matches!(node, Node::Start | Node::End | Node::WordBoundary(_)) {
+            return Err("nothing to repeat".to_string());
+        }
+        // Laziness changes which match is found, not whether there is one
+        self.eat('?');
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    fn escape(&mut self) -> Result<Node, String> {
+        Ok(match self.next()? {
+            'b' => Node::WordBoundary(true),
+            'B' => Node::WordBoundary(false),
+            '1'..='9' | 'k' => return Err("backreferences are not supported".to_string()),
+            c => match self.class_escape(c)? {
+                Ok(c) => Node::Char(c),
+                Err(class) => Node::Class(class),
+            },
+        })
+    }
+
+    /// The character, or the class, an escape ending in `c` stands for,
+    /// wherever it appears.
+    fn class_escape(&mut self, c: char) -> Result<Result<char, Class>, String> {
+        let class = |ranges: &[(char, char)], negated| {
+            Ok(Err(Class {
+                ranges: ranges.to_vec(),
+                negated,
+            }))
+        };
+        Ok(Ok(match c {
+            'd' => return class(DIGITS, false),
+            'D' => return class(DIGITS, true),
+            'w' => return class(WORD, false),
+            'W' => return class(WORD, true),
+            's' => return class(SPACE, false),
+            'S' => return class(SPACE, true),
+            'p' | 'P' => return Err("Unicode property escapes are not supported".to_string()),
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            '0' if !self.peek().is_some_and(|c| c.is_ascii_digit()) => '\0',
+            'c' => match self.next()? {
+                c if c.is_ascii_alphabetic() => char::from(c as u8 % 32),
+                _ => return Err("invalid control escape".to_string()),
+            },
+            'x' => self.hex(2)?,
+            'u' => self.unicode_escape()?,
+            c if c.is_ascii_punctuation() || c == ' ' => c,
+            c => return Err(format!("invalid escape '\\{}'", c)),
+        }))
+    }
+
+    fn hex_digits(&mut self, count: usize) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..count {
+            let digit = self
+                .next()?
+                .to_digit(16)
+                .ok_or("invalid hexadecimal escape")?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn hex(&mut self, count: usize) -> Result<char, String> {
+        let value = self.hex_digits(count)?;
+        char::from_u32(value).ok_or_else(|| "invalid hexadecimal escape".to_string())
+    }
+
+    /// A `\u` escape, after the `u`: four digits, possibly half of a
+    /// surrogate pair, or any number in braces.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let invalid = || "invalid Unicode escape".to_string();
+        if self.eat('{') {
+            let mut value: u32 = 0;
+            while !self.eat('}') {
+                let digit = self.next()?.to_digit(16).ok_or_else(invalid)?;
+                value = value
+                    .checked_mul(16)
+                    .and_then(|v| v.checked_add(digit))
+                    .ok_or_else(invalid)?;
+            }
+            return char::from_u32(value).ok_or_else(invalid);
+        }
+        let high = self.hex_digits(4)?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(invalid);
+        }
+        let start = self.pos;
+        if self.eat('\\') && self.eat('u') {
+            let low = self.hex_digits(4)?;
+            if (0xDC00..0xE000).contains(&low) {
+                let c = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return char::from_u32(c).ok_or_else(invalid);
+            }
+        }
+        self.pos = start;
+        Err("unpaired surrogate in Unicode escape".to_string())
+    }
+
+    /// A character class, after its `[`.
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        while !self.eat(']') {
+            let low = match self.class_atom()? {
+                Ok(c) => c,
+                Err(class) => {
+                    add_class(&mut ranges, class);
+                    continue;
+                }
+            };
+            // A '-' just before the ']' is itself
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                match self.class_atom()? {
+                    Ok(high) if low <= high => ranges.push((low, high)),
+                    Ok(_) => return Err("range out of order in character class".to_string()),
+                    Err(_) => return Err("invalid range in character class".to_string()),
+                }
+            } else {
+                ranges.push((low, low));
+            }
+        }
+        ranges.sort_unstable();
+        Ok(Class { ranges, negated })
+    }
+
+    fn class_atom(&mut self) -> Result<Result<char, Class>, String> {
+        match self.next() {
+            Ok('\\') => match self.next()? {
+                'b' => Ok(Ok('\u{8}')),
+                '-' => Ok(Ok('-')),
+                c => self.class_escape(c),
+            },
+            Ok(c) => Ok(Ok(c)),
+            Err(_) => Err("missing ']'".to_string()),
+        }
+    }
+}
+
+/// Add the characters of `class` to `ranges`.
+fn add_class(ranges: &mut Vec<(char, char)>, class: Class) {
+    if class.negated {
+        ranges.extend(complement(&class.ranges));
+    } else {
+        ranges.extend(class.ranges);
+    }
+}
+
+/// A pattern compiled for matching: a program for a machine that follows
+/// every thread through it at once, one character of the text at a time.
+#[derive(Debug, Clone)]
+struct Program {
+    instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    /// Consume a character `Node::Char`, `Node::Any` or `Node::Class`
+    /// accepts.
+    Consume(Node),
+    /// Go on only if the `Node::Start`, `Node::End` or
+    /// `Node::WordBoundary` holds here.
+    Assert(Node),
+    /// Go on only if the program matches from here, or, when negated,
+    /// doesn't.
+    Lookahead(Box<Program>, bool),
+    /// Go on at both places.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Program {
+    fn compile(node: &Node) -> Result<Program, String> {
+        let mut program = Program {
+            instructions: Vec::new(),
+        };
+        program.emit(node)?;
+        program.push(Instruction::Match)?;
+        Ok(program)
+    }
+
+    fn push(&mut self, instruction: Instruction) -> Result<usize, String> {
+        if self.instructions.len() >= PROGRAM_LIMIT {
+            return Err("pattern is too large".to_string());
+        }
+        self.instructions.push(instruction);
+        Ok(self.instructions.len() - 1)
+    }
+
+    /// A `Split` or `Jump` whose targets are filled in later.
+    fn placeholder(&mut self) -> Result<usize, String> {
+        self.push(Instruction::Jump(usize::MAX))
+    }
+
+    fn emit(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Char(_) | Node::Any | Node::Class(_) => {
+                self.push(Instruction::Consume(node.clone()))?;
+            }
+            Node::Start | Node::End | Node::WordBoundary(_) => {
+                self.push(Instruction::Assert(node.clone()))?;
+            }
+            Node::Lookahead { node, negated } => {
+                let program = Program::compile(node)?;
+                self.push(Instruction::Lookahead(Box::new(program), *negated))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.emit(node)?;
+                }
+            }
+            Node::Alternation(branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.emit(branch)?;
+                        break;
+                    }
+                    let split = self.placeholder()?;
+                    self.emit(branch)?;
+                    jumps.push(self.placeholder()?);
+                    self.instructions[split] =
+                        Instruction::Split(split + 1, self.instructions.len());
+                }
+                let end = self.instructions.len();
+                for jump in jumps {
+                    self.instructions[jump] = Instruction::Jump(end);
+                }
+            }
+            // Repeating nothing, like `(){4000000000}`, is nothing too
+            Node::Repeat { node, .. } if is_empty(node) => {}
+            Node::Repeat { node, min, max } => {
+                for _ in 0..*min {
+                    self.emit(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.placeholder()?;
+                        self.emit(node)?;
+                        self.push(Instruction::Jump(split))?;
+                        self.instructions[split] =
+                            Instruction::Split(split + 1, self.instructions.len());
+                    }
+                    Some(max) => {
+                        // Each optional copy may skip the rest
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.placeholder()?);
+                            self.emit(node)?;
+                        }
+                        let end = self.instructions.len();
+                        for split in splits {
+                            self.instructions[split] = Instruction::Split(split + 1, end);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the program matches anywhere in `text`.
+    fn run(&self, text: &[char]) -> bool {
+        let mut current = Threads::new(self.instructions.len());
+        let mut next = Threads::new(self.instructions.len());
+        let mut lookaheads = Lookaheads::new(self.instructions.len());
+        for pos in 0..=text.len() {
+            if self.add(&mut current, &mut lookaheads, 0, text, pos) {
+                return true;
+            }
+            let Some(&c) = text.get(pos) else {
+                break;
+            };
+            for &pc in &current.list {
+                if let Instruction::Consume(node) = &self.instructions[pc]
+                    && accepts(node, c)
+                    && self.add(&mut next, &mut lookaheads, pc + 1, text, pos + 1)
+                {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        false
+    }
+
+    /// Add the threads that reach `Consume` instructions from `pc` without
+    /// consuming anything, at `pos`, returning whether one reaches `Match`.
+    fn add(
+        &self,
+        threads: &mut Threads,
+        lookaheads: &mut Lookaheads,
+        pc: usize,
+        text: &[char],
+        pos: usize,
+    ) -> bool {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if !threads.insert(pc) {
+                continue;
+            }
+            match &self.instructions[pc] {
+                Instruction::Consume(_) => threads.list.push(pc),
+                Instruction::Assert(node) => {
+                    if holds(node, text, pos) {
+                        stack.push(pc + 1);
+                    }
+                }
+                Instruction::Lookahead(program, negated) => {
+                    if lookaheads.get(pc, program, text)[pos] != *negated {
+                        stack.push(pc + 1);
+                    }
+                }
+                Instruction::Split(first, second) => {
+                    stack.push(*second);
+                    stack.push(*first);
+                }
+                Instruction::Jump(target) => stack.push(*target),
+                Instruction::Match => return true,
+            }
+        }
+        false
+    }
+
+    /// For each position in `text`, and the end, whether the program
+    /// matches starting there.
+    ///
+    /// Rather than running from every position, this goes back from the end
+    /// of the text, finding at each position the instructions from which
+    /// `Match` can be reached, from those found at the next.
+    fn matches_from(&self, text: &[char]) -> Vec<bool> {
+        let len = self.instructions.len();
+        // The instructions that go on to each one without consuming anything
+        let mut sources = vec![Vec::new(); len];
+        for (pc, instruction) in self.instructions.iter().enumerate() {
+            match instruction {
+                Instruction::Assert(_) | Instruction::Lookahead(..) => sources[pc + 1].push(pc),
+                Instruction::Split(first, second) => {
+                    sources[*first].push(pc);
+                    sources[*second].push(pc);
+                }
+                Instruction::Jump(target) => sources[*target].push(pc),
+                Instruction::Consume(_) | Instruction::Match => {}
+            }
+        }
+
+        let mut lookaheads = Lookaheads::new(len);
+        let mut matches = vec![false; text.len() + 1];
+        let mut here = vec![false; len];
+        let mut after = vec![false; len];
+        let mut stack = Vec::new();
+        for pos in (0..=text.len()).rev() {
+            std::mem::swap(&mut here, &mut after);
+            here.fill(false);
+            for (pc, instruction) in self.instructions.iter().enumerate() {
+                let reaches = match instruction {
+                    Instruction::Consume(node) => {
+                        text.get(pos).is_some_and(|&c| accepts(node, c)) && after[pc + 1]
+                    }
+                    Instruction::Match => true,
+                    _ => false,
+                };
+                if reaches {
+                    here[pc] = true;
+                    stack.push(pc);
+                }
+            }
+            while let Some(pc) = stack.pop() {
+                for &source in &sources[pc] {
+                    let passes = !here[source]
+                        && match &self.instructions[source] {
+                            Instruction::Assert(node) => holds(node, text, pos),
+                            Instruction::Lookahead(program, negated) => {
+                                lookaheads.get(source, program, text)[pos] != *negated
+                            }
+                            _ => true,
+                        };
+                    if passes {
+                        here[source] = true;
+                        stack.push(source);
+                    }
+                }
+            }
+            matches[pos] = here[0];
+        }
+        matches
+    }
+}
+
+/// The result of each lookahead at every position in the text, by the
+/// `pc` of its instruction, worked out when first needed.
+struct Lookaheads {
+    results: Vec<Option<Vec<bool>>>,
+}
+
+impl Lookaheads {
+    fn new(len: usize) -> Lookaheads {
+        Lookaheads {
+            results: vec![None; len],
+        }
+    }
+
+    fn get(&mut self, pc: usize, program: &Program, text: &[char]) -> &[bool] {
+        self.results[pc].get_or_insert_with(|| program.matches_from(text))
+    }
+}
+
+/// The threads at one position: the `Consume` instructions waiting for the
+/// next character, and every instruction already visited on the way.
+struct Threads {
+    list: Vec<usize>,
+    seen: Vec<bool>,
+    visited: Vec<usize>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Threads {
+        Threads {
+            list: Vec::new(),
+            seen: vec![false; len],
+            visited: Vec::new(),
+        }
+    }
+
+    /// Mark `pc` visited, returning whether it wasn't already.
+    fn insert(&mut self, pc: usize) -> bool {
+        if self.seen[pc] {
+            return false;
+        }
+        self.seen[pc] = true;
+        self.visited.push(pc);
+        true
+    }
+
+    fn clear(&mut self) {
+        for pc in self.visited.drain(..) {
+            self.seen[pc] = false;
+        }
+        self.list.clear();
+    }
+}
+
+/// Whether `node` compiles to no instructions at all.
+fn is_empty(node: &Node) -> bool {
+    match node {
+        Node::Concat(nodes) => nodes.iter().all(is_empty),
+        Node::Repeat { node, max, .. } => *max == Some(0) || is_empty(node),
+        _ => false,
+    }
+}
+
+fn accepts(node: &Node, c: char) -> bool {
+    match node {
+        Node::Char(expected) => c == *expected,
+        Node::Any => !is_line_terminator(c),
+        Node::Class(class) => class.contains(c),
+        _ => false,
+    }
+}
+
+fn holds(node: &Node, text: &[char], pos: usize) -> bool {
+    match node {
+        Node::Start => pos == 0,
+        Node::End => pos == text.len(),
+        Node::WordBoundary(expected) => {
+            let word_before = pos > 0 && is_word(text[pos - 1]);
+            let word_after = text.get(pos).is_some_and(|&c| is_word(c));
+            (word_before != word_after) == *expected
+        }
+        _ => false,
+    }
+}
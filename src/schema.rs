@@ -0,0 +1,855 @@
+use crate::error::{SchemaError, ValidationError};
+use crate::json::JsonValue;
+use crate::map::Map;
+use crate::number::Number;
+use crate::pointer::{escape, lookup};
+use crate::regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, SchemaError>;
+
+/// A compiled JSON Schema (draft 2020-12), to validate values against.
+///
+/// The keywords checked are `type`, `enum` and `const`; `multipleOf`,
+/// `maximum`, `exclusiveMaximum`, `minimum` and `exclusiveMinimum`;
+/// `maxLength`, `minLength` and `pattern`; `prefixItems`, `items`,
+/// `maxItems`, `minItems`, `uniqueItems` and `contains` with
+/// `maxContains` and `minContains`; `properties`, `patternProperties`,
+/// `additionalProperties`, `propertyNames`, `required`, `maxProperties`,
+/// `minProperties` and `dependentRequired`; `allOf`, `anyOf`, `oneOf`,
+/// `not` and `if` with `then` and `else`; and `$ref`. Anything else, such
+/// as `format` or `title`, is ignored.
+///
+/// References must point within the schema, with a JSON Pointer fragment
+/// like `#/$defs/name`; `#` on its own is the whole schema.
+///
+/// ```
+/// use json_parser::{Schema, parse_json};
+///
+/// let schema = Schema::compile(&parse_json(r##"{
+///     "type": "object",
+///     "properties": {
+///         "name": {"type": "string", "minLength": 1},
+///         "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}
+///     },
+///     "required": ["name"],
+///     "$defs": {"tag": {"type": "string", "pattern": "^[a-z]+$"}}
+/// }"##).unwrap()).unwrap();
+///
+/// assert!(schema.is_valid(&parse_json(r#"{"name": "a", "tags": ["x"]}"#).unwrap()));
+///
+/// let errors = schema.validate(&parse_json(r#"{"tags": ["x", "Y"]}"#).unwrap()).unwrap_err();
+/// assert_eq!(errors[0].instance_path, "/tags/1");
+/// assert_eq!(errors[0].schema_path, "/$defs/tag/pattern");
+/// assert_eq!(errors[1].message, "missing required property \"name\"");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Schema {
+    /// The keywords of every subschema, the whole schema's first. They
+    /// refer to each other by index.
+    schemas: Vec<Vec<Keyword>>,
+}
+
+/// A keyword to check, and the JSON Pointer to it in the schema.
+#[derive(Debug, Clone)]
+struct Keyword {
+    path: String,
+    check: Check,
+}
+
+/// What a keyword checks. Subschemas are indices into `Schema::schemas`.
+#[derive(Debug, Clone)]
+enum Check {
+    /// The `false` schema.
+    False,
+    Type(Vec<String>),
+    Enum(Vec<JsonValue>),
+    Const(JsonValue),
+    MultipleOf(Number),
+    Maximum(Number),
+    ExclusiveMaximum(Number),
+    Minimum(Number),
+    ExclusiveMinimum(Number),
+    MaxLength(usize),
+    MinLength(usize),
+    Pattern(Regex, String),
+    PrefixItems(Vec<usize>),
+    /// `items`, which skips the elements `prefixItems` covers.
+    Items {
+        schema: usize,
+        skip: usize,
+    },
+    MaxItems(usize),
+    MinItems(usize),
+    UniqueItems,
+    Contains {
+        schema: usize,
+        min: usize,
+        max: Option<usize>,
+    },
+    Properties(Vec<(String, usize)>),
+    PatternProperties(Vec<(Regex, usize)>),
+    /// `additionalProperties`, which skips the members `properties` and
+    /// `patternProperties` cover.
+    AdditionalProperties {
+        schema: usize,
+        known: Vec<String>,
+        patterns: Vec<Regex>,
+    },
+    PropertyNames(usize),
+    Required(Vec<String>),
+    MaxProperties(usize),
+    MinProperties(usize),
+    DependentRequired(Vec<(String, Vec<String>)>),
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+    OneOf(Vec<usize>),
+    Not(usize),
+    If {
+        condition: usize,
+        then: Option<usize>,
+        otherwise: Option<usize>,
+    },
+    Ref(usize),
+}
+
+const TYPES: [&str; 7] = [
+    "null", "boolean", "object", "array", "number", "string", "integer",
+];
+
+impl Schema {
+    /// How deeply nested a value can be validated. Parts of a value nested
+    /// more deeply fail with an error.
+    pub const MAX_DEPTH: usize = 256;
+
+    /// Compile a schema, checking that its keywords are well formed and its
+    /// references resolve.
+    pub fn compile(schema: &JsonValue) -> Result<Schema> {
+        let mut compiler = Compiler {
+            root: schema,
+            schemas: Vec::new(),
+            compiled: HashMap::new(),
+        };
+        compiler.subschema(schema, String::new())?;
+        let schema = Schema {
+            schemas: compiler.schemas,
+        };
+        schema.check_loops()?;
+        Ok(schema)
+    }
+
+    /// Check `instance` against the schema, returning every way it fails.
+    pub fn validate(&self, instance: &JsonValue) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.check(0, instance, &mut String::new(), &mut errors, 0);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &JsonValue) -> bool {
+        self.validate(instance).is_ok()
+    }
+
+    /// Make sure no subschema leads back to itself without moving into the
+    /// value, which would never finish.
+    fn check_loops(&self) -> Result<()> {
+        // 0: not seen, 1: being followed, 2: done
+        let mut state = vec![0u8; self.schemas.len()];
+        for index in 0..self.schemas.len() {
+            self.follow(index, &mut state)?;
+        }
+        Ok(())
+    }
+
+    fn follow(&self, index: usize, state: &mut [u8]) -> Result<()> {
+        if state[index] == 2 {
+            return Ok(());
+        }
+        state[index] = 1;
+        for keyword in &self.schemas[index] {
+            let in_place = match &keyword.check {
+                Check::AllOf(schemas) | Check::AnyOf(schemas) | Check::OneOf(schemas) => {
+                    schemas.clone()
+                }
+                Check::Not(schema) | Check::Ref(schema) => vec![*schema],
+                Check::If {
+                    condition,
+                    then,
+                    otherwise,
+                } => [Some(*condition), *then, *otherwise]
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+                _ => Vec::new(),
+            };
+            for next in in_place {
+                if state[next] == 1 {
+                    return Err(invalid(
+                        &keyword.path,
+                        "the schema refers back to itself without checking anything",
+                    ));
+                }
+                self.follow(next, state)?;
+            }
+        }
+        state[index] = 2;
+        Ok(())
+    }
+
+    /// Add the ways `value`, at `path` and `depth` levels down in the
+    /// instance, fails the subschema `index` to `errors`.
+    ///
+    /// Each kind of keyword is checked in a function of its own, so that the
+    /// frames this recurses through stay small.
+    fn check(
+        &self,
+        index: usize,
+        value: &JsonValue,
+        path: &mut String,
+        errors: &mut Vec<ValidationError>,
+        depth: usize,
+    ) {
+        for keyword in &self.schemas[index] {
+            let mut failures = Vec::new();
+            match &keyword.check {
+                Check::PrefixItems(_)
+                | Check::Items { .. }
+                | Check::Contains { .. }
+                | Check::Properties(_)
+                | Check::PatternProperties(_)
+                | Check::AdditionalProperties { .. }
+                | Check::PropertyNames(_) => {
+                    self.check_members(&keyword.check, value, path, errors, depth, &mut failures)
+                }
+                Check::AllOf(_)
+                | Check::AnyOf(_)
+                | Check::OneOf(_)
+                | Check::Not(_)
+                | Check::If { .. }
+                | Check::Ref(_) => {
+                    self.check_in_place(&keyword.check, value, path, errors, depth, &mut failures)
+                }
+                check => check_value(check, value, &mut failures),
+            }
+            for message in failures {
+                errors.push(ValidationError {
+                    instance_path: path.clone(),
+                    schema_path: keyword.path.clone(),
+                    message,
+                });
+            }
+        }
+    }
+
+    /// Check the keywords that apply subschemas to the elements or members
+    /// of `value`.
+    fn check_members(
+        &self,
+        check: &Check,
+        value: &JsonValue,
+        path: &mut String,
+        errors: &mut Vec<ValidationError>,
+        depth: usize,
+        failures: &mut Vec<String>,
+    ) {
+        let mut fail = |message: String| failures.push(message);
+        match (check, value) {
+            (Check::PrefixItems(schemas), JsonValue::Array(elements)) => {
+                for (i, (schema, element)) in schemas.iter().zip(elements).enumerate() {
+                    self.check_at(*schema, element, path, &i.to_string(), errors, depth);
+                }
+            }
+            (Check::Items { schema, skip }, JsonValue::Array(elements)) => {
+                for (i, element) in elements.iter().enumerate().skip(*skip) {
+                    self.check_at(*schema, element, path, &i.to_string(), errors, depth);
+                }
+            }
+            (Check::Contains { schema, min, max }, JsonValue::Array(elements)) => {
+                let count = elements
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, element)| {
+                        let mut errors = Vec::new();
+                        self.check_at(*schema, element, path, &i.to_string(), &mut errors, depth);
+                        errors.is_empty()
+                    })
+                    .count();
+                if count < *min {
+                    fail(format!(
+                        "must contain at least {} matching items, but has {}",
+                        min, count
+                    ));
+                } else if max.is_some_and(|max| count > max) {
+                    fail(format!(
+                        "must contain at most {} matching items, but has {}",
+                        max.unwrap(),
+                        count
+                    ));
+                }
+            }
+            (Check::Properties(schemas), JsonValue::Object(members)) => {
+                for (key, schema) in schemas {
+                    if let Some(member) = members.get(key) {
+                        self.check_at(*schema, member, path, key, errors, depth);
+                    }
+                }
+            }
+            (Check::PatternProperties(schemas), JsonValue::Object(members)) => {
+                for (key, member) in members {
+                    for (regex, schema) in schemas {
+                        if regex.is_match(key) {
+                            self.check_at(*schema, member, path, key, errors, depth);
+                        }
+                    }
+                }
+            }
+            (
+                Check::AdditionalProperties {
+                    schema,
+                    known,
+                    patterns,
+                },
+                JsonValue::Object(members),
+            ) => {
+                for (key, member) in members {
+                    if !known.contains(key) && !patterns.iter().any(|r| r.is_match(key)) {
+                        self.check_at(*schema, member, path, key, errors, depth);
+                    }
+                }
+            }
+            (Check::PropertyNames(schema), JsonValue::Object(members)) => {
+                for key in members.keys() {
+                    let name = JsonValue::String(key.clone());
+                    self.check_at(*schema, &name, path, key, errors, depth);
+                }
+            }
+            // The keyword doesn't apply to this type of value
+            _ => {}
+        }
+    }
+
+    /// Check the keywords that apply subschemas to `value` itself.
+    fn check_in_place(
+        &self,
+        check: &Check,
+        value: &JsonValue,
+        path: &mut String,
+        errors: &mut Vec<ValidationError>,
+        depth: usize,
+        failures: &mut Vec<String>,
+    ) {
+        let mut fail = |message: String| failures.push(message);
+        match check {
+            Check::AllOf(schemas) => {
+                for schema in schemas {
+                    self.check(*schema, value, path, errors, depth);
+                }
+            }
+            Check::AnyOf(schemas)
+                if !schemas
+                    .iter()
+                    .any(|schema| self.matches(*schema, value, path, depth)) =>
+            {
+                fail("must match at least one schema in anyOf".to_string());
+            }
+            Check::OneOf(schemas) => {
+                let count = schemas
+                    .iter()
+                    .filter(|schema| self.matches(**schema, value, path, depth))
+                    .count();
+                if count != 1 {
+                    fail(format!(
+                        "must match exactly one schema in oneOf, but matches {}",
+                        count
+                    ));
+                }
+            }
+            Check::Not(schema) if self.matches(*schema, value, path, depth) => {
+                fail("must not match the schema in not".to_string());
+            }
+            Check::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let branch = if self.matches(*condition, value, path, depth) {
+                    then
+                } else {
+                    otherwise
+                };
+                if let Some(schema) = branch {
+                    self.check(*schema, value, path, errors, depth);
+                }
+            }
+            Check::Ref(schema) => self.check(*schema, value, path, errors, depth),
+            // The keyword holds
+            _ => {}
+        }
+    }
+
+    /// Check `value`, a member or element named `token`, with `path`
+    /// extended to it.
+    ///
+    /// Checking recurses through the value, so values nested more than
+    /// `MAX_DEPTH` deep fail here rather than overflow the stack.
+    fn check_at(
+        &self,
+        index: usize,
+        value: &JsonValue,
+        path: &mut String,
+        token: &str,
+        errors: &mut Vec<ValidationError>,
+        depth: usize,
+    ) {
+        let len = path.len();
+        path.push('/');
+        path.push_str(&escape(token));
+        if depth < Self::MAX_DEPTH {
+            self.check(index, value, path, errors, depth + 1);
+        } else if let Some(keyword) = self.schemas[index].first() {
+            errors.push(ValidationError {
+                instance_path: path.clone(),
+                schema_path: keyword.path.clone(),
+                message: format!("nested more than {} levels deep", Self::MAX_DEPTH),
+            });
+        }
+        path.truncate(len);
+    }
+
+    fn matches(&self, index: usize, value: &JsonValue, path: &mut String, depth: usize) -> bool {
+        let mut errors = Vec::new();
+        self.check(index, value, path, &mut errors, depth);
+        errors.is_empty()
+    }
+}
+
+/// Add the ways `value` fails `check`, one of the keywords that look only at
+/// the value itself, to `failures`.
+fn check_value(check: &Check, value: &JsonValue, failures: &mut Vec<String>) {
+    let mut fail = |message: String| failures.push(message);
+    match (check, value) {
+        (Check::False, _) => fail("no value is allowed here".to_string()),
+        (Check::Type(types), _) if !types.iter().any(|name| has_type(value, name)) => {
+            fail(format!(
+                "expected {}, found {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+        }
+        (Check::Enum(values), _) if !values.iter().any(|v| v == value) => {
+            let values: Vec<_> = values.iter().map(|v| v.to_string()).collect();
+            fail(format!("must be one of {}", values.join(", ")));
+        }
+        (Check::Const(expected), _) if expected != value => {
+            fail(format!("must be {}", expected));
+        }
+        (Check::MultipleOf(divisor), JsonValue::Number(n)) if !is_multiple(n, divisor) => {
+            fail(format!("must be a multiple of {}", divisor));
+        }
+        (Check::Maximum(limit), JsonValue::Number(n))
+            if n.compare(limit) == Some(Ordering::Greater) =>
+        {
+            fail(format!("must be at most {}", limit));
+        }
+        (Check::ExclusiveMaximum(limit), JsonValue::Number(n))
+            if n.compare(limit) != Some(Ordering::Less) =>
+        {
+            fail(format!("must be less than {}", limit));
+        }
+        (Check::Minimum(limit), JsonValue::Number(n))
+            if n.compare(limit) == Some(Ordering::Less) =>
+        {
+            fail(format!("must be at least {}", limit));
+        }
+        (Check::ExclusiveMinimum(limit), JsonValue::Number(n))
+            if n.compare(limit) != Some(Ordering::Greater) =>
+        {
+            fail(format!("must be greater than {}", limit));
+        }
+        (Check::MaxLength(limit), JsonValue::String(s)) if s.chars().count() > *limit => {
+            fail(format!("must be at most {} characters long", limit));
+        }
+        (Check::MinLength(limit), JsonValue::String(s)) if s.chars().count() < *limit => {
+            fail(format!("must be at least {} characters long", limit));
+        }
+        (Check::Pattern(regex, pattern), JsonValue::String(s)) if !regex.is_match(s) => {
+            fail(format!("must match the pattern {:?}", pattern));
+        }
+        (Check::MaxItems(limit), JsonValue::Array(elements)) if elements.len() > *limit => {
+            fail(format!("must have at most {} items", limit));
+        }
+        (Check::MinItems(limit), JsonValue::Array(elements)) if elements.len() < *limit => {
+            fail(format!("must have at least {} items", limit));
+        }
+        (Check::UniqueItems, JsonValue::Array(elements)) => {
+            let duplicate = elements.iter().enumerate().find_map(|(j, b)| {
+                let i = elements[..j].iter().position(|a| a == b)?;
+                Some((i, j))
+            });
+            if let Some((i, j)) = duplicate {
+                fail(format!("items {} and {} must not be equal", i, j));
+            }
+        }
+        (Check::Required(keys), JsonValue::Object(members)) => {
+            for key in keys {
+                if !members.contains_key(key) {
+                    fail(format!("missing required property {:?}", key));
+                }
+            }
+        }
+        (Check::MaxProperties(limit), JsonValue::Object(members)) if members.len() > *limit => {
+            fail(format!("must have at most {} properties", limit));
+        }
+        (Check::MinProperties(limit), JsonValue::Object(members)) if members.len() < *limit => {
+            fail(format!("must have at least {} properties", limit));
+        }
+        (Check::DependentRequired(dependencies), JsonValue::Object(members)) => {
+            for (key, required) in dependencies {
+                if !members.contains_key(key) {
+                    continue;
+                }
+                for other in required {
+                    if !members.contains_key(other) {
+                        fail(format!(
+                            "missing property {:?}, which {:?} requires",
+                            other, key
+                        ));
+                    }
+                }
+            }
+        }
+        // The keyword holds, or doesn't apply to this type of value
+        _ => {}
+    }
+}
+
+struct Compiler<'s> {
+    root: &'s JsonValue,
+    schemas: Vec<Vec<Keyword>>,
+    /// The index of each subschema compiled so far, by its path, so that a
+    /// subschema referred to more than once is compiled once.
+    compiled: HashMap<String, usize>,
+}
+
+impl<'s> Compiler<'s> {
+    fn subschema(&mut self, value: &'s JsonValue, path: String) -> Result<usize> {
+        if let Some(&index) = self.compiled.get(&path) {
+            return Ok(index);
+        }
+        // Claim the index first, so references back to here find it
+        let index = self.schemas.len();
+        self.schemas.push(Vec::new());
+        self.compiled.insert(path.clone(), index);
+        self.schemas[index] = self.keywords(value, &path)?;
+        Ok(index)
+    }
+
+    fn keywords(&mut self, value: &'s JsonValue, path: &str) -> Result<Vec<Keyword>> {
+        let members = match value {
+            JsonValue::Object(members) => members,
+            JsonValue::Boolean(true) => return Ok(Vec::new()),
+            JsonValue::Boolean(false) => {
+                return Ok(vec![Keyword {
+                    path: path.to_string(),
+                    check: Check::False,
+                }]);
+            }
+            _ => return Err(invalid(path, "a schema must be an object or a boolean")),
+        };
+
+        let mut keywords = Vec::new();
+        for (name, value) in members {
+            let at = child(path, name);
+            let check = match name.as_str() {
+                "type" => Check::Type(types(value, &at)?),
+                "enum" => match value {
+                    JsonValue::Array(values) => Check::Enum(values.clone()),
+                    _ => return Err(invalid(&at, "must be an array")),
+                },
+                "const" => Check::Const(value.clone()),
+                "multipleOf" => match number(value, &at)? {
+                    n if n.as_f64() > 0.0 => Check::MultipleOf(n),
+                    _ => return Err(invalid(&at, "must be greater than 0")),
+                },
+                "maximum" => Check::Maximum(number(value, &at)?),
+                "exclusiveMaximum" => Check::ExclusiveMaximum(number(value, &at)?),
+                "minimum" => Check::Minimum(number(value, &at)?),
+                "exclusiveMinimum" => Check::ExclusiveMinimum(number(value, &at)?),
+                "maxLength" => Check::MaxLength(count(value, &at)?),
+                "minLength" => Check::MinLength(count(value, &at)?),
+                "pattern" => {
+                    let pattern = string(value, &at)?;
+                    Check::Pattern(regex(pattern, &at)?, pattern.to_string())
+                }
+                "prefixItems" => Check::PrefixItems(self.list(value, &at)?),
+                "items" => Check::Items {
+                    schema: self.subschema(value, at.clone())?,
+                    skip: match members.get("prefixItems") {
+                        Some(JsonValue::Array(prefix)) => prefix.len(),
+                        _ => 0,
+                    },
+                },
+                "maxItems" => Check::MaxItems(count(value, &at)?),
+                "minItems" => Check::MinItems(count(value, &at)?),
+                "uniqueItems" => match value {
+                    JsonValue::Boolean(true) => Check::UniqueItems,
+                    JsonValue::Boolean(false) => continue,
+                    _ => return Err(invalid(&at, "must be a boolean")),
+                },
+                "contains" => Check::Contains {
+                    schema: self.subschema(value, at.clone())?,
+                    min: match members.get("minContains") {
+                        Some(min) => count(min, &child(path, "minContains"))?,
+                        None => 1,
+                    },
+                    max: match members.get("maxContains") {
+                        Some(max) => Some(count(max, &child(path, "maxContains"))?),
+                        None => None,
+                    },
+                },
+                "properties" => {
+                    let mut schemas = Vec::new();
+                    for (key, schema) in object(value, &at)? {
+                        schemas.push((key.clone(), self.subschema(schema, child(&at, key))?));
+                    }
+                    Check::Properties(schemas)
+                }
+                "patternProperties" => {
+                    let mut schemas = Vec::new();
+                    for (key, schema) in object(value, &at)? {
+                        let key_at = child(&at, key);
+                        schemas.push((regex(key, &key_at)?, self.subschema(schema, key_at)?));
+                    }
+                    Check::PatternProperties(schemas)
+                }
+                "additionalProperties" => Check::AdditionalProperties {
+                    schema: self.subschema(value, at.clone())?,
+                    known: match members.get("properties") {
+                        Some(JsonValue::Object(properties)) => properties.keys().cloned().collect(),
+                        _ => Vec::new(),
+                    },
+                    // Filled in below from patternProperties
+                    patterns: Vec::new(),
+                },
+                "propertyNames" => Check::PropertyNames(self.subschema(value, at.clone())?),
+                "required" => Check::Required(strings(value, &at)?),
+                "maxProperties" => Check::MaxProperties(count(value, &at)?),
+                "minProperties" => Check::MinProperties(count(value, &at)?),
+                "dependentRequired" => {
+                    let mut dependencies = Vec::new();
+                    for (key, required) in object(value, &at)? {
+                        dependencies.push((key.clone(), strings(required, &child(&at, key))?));
+                    }
+                    Check::DependentRequired(dependencies)
+                }
+                "allOf" => Check::AllOf(self.list(value, &at)?),
+                "anyOf" => Check::AnyOf(self.list(value, &at)?),
+                "oneOf" => Check::OneOf(self.list(value, &at)?),
+                "not" => Check::Not(self.subschema(value, at.clone())?),
+                "if" => Check::If {
+                    condition: self.subschema(value, at.clone())?,
+                    then: self.optional(members, path, "then")?,
+                    otherwise: self.optional(members, path, "else")?,
+                },
+                "$ref" => Check::Ref(self.reference(value, &at)?),
+                // Annotations, keywords read along with others above, and
+                // ones this validator doesn't know
+                _ => continue,
+            };
+            keywords.push(Keyword { path: at, check });
+        }
+
+        let patterns: Vec<Regex> = keywords
+            .iter()
+            .find_map(|keyword| match &keyword.check {
+                Check::PatternProperties(schemas) => {
+                    Some(schemas.iter().map(|(regex, _)| regex.clone()).collect())
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+        for keyword in &mut keywords {
+            if let Check::AdditionalProperties { patterns: p, .. } = &mut keyword.check {
+                *p = patterns.clone();
+            }
+        }
+        Ok(keywords)
+    }
+
+    /// A non-empty array of subschemas.
+    fn list(&mut self, value: &'s JsonValue, path: &str) -> Result<Vec<usize>> {
+        match value {
+            JsonValue::Array(schemas) if !schemas.is_empty() => schemas
+                .iter()
+                .enumerate()
+                .map(|(i, schema)| self.subschema(schema, child(path, &i.to_string())))
+                .collect(),
+            _ => Err(invalid(path, "must be a non-empty array of schemas")),
+        }
+    }
+
+    /// The subschema `name` in `members`, if it is there.
+    fn optional(&mut self, members: &'s Map, path: &str, name: &str) -> Result<Option<usize>> {
+        members
+            .get(name)
+            .map(|schema| self.subschema(schema, child(path, name)))
+            .transpose()
+    }
+
+    fn reference(&mut self, value: &'s JsonValue, path: &str) -> Result<usize> {
+        let reference = string(value, path)?;
+        let pointer = match reference.strip_prefix('#') {
+            Some(fragment) if fragment.is_empty() || fragment.starts_with('/') => {
+                percent_decode(fragment).ok_or_else(|| invalid(path, "invalid percent-encoding"))?
+            }
+            _ => {
+                return Err(invalid(
+                    path,
+                    "only JSON Pointers within the schema, like \"#/$defs/name\", are supported",
+                ));
+            }
+        };
+        let target = lookup(self.root, &pointer)
+            .map_err(|e| invalid(path, &format!("cannot resolve {:?}: {}", reference, e)))?;
+        self.subschema(target, pointer)
+    }
+}
+
+fn invalid(path: &str, message: &str) -> SchemaError {
+    SchemaError {
+        schema_path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn child(path: &str, token: &str) -> String {
+    format!("{}/{}", path, escape(token))
+}
+
+fn types(value: &JsonValue, path: &str) -> Result<Vec<String>> {
+    let names = match value {
+        JsonValue::String(name) => vec![name.clone()],
+        _ => strings(value, path)?,
+    };
+    if names.is_empty() {
+        return Err(invalid(path, "must name at least one type"));
+    }
+    match names.iter().find(|name| !TYPES.contains(&name.as_str())) {
+        Some(name) => Err(invalid(path, &format!("unknown type {:?}", name))),
+        None => Ok(names),
+    }
+}
+
+fn number(value: &JsonValue, path: &str) -> Result<Number> {
+    match value {
+        JsonValue::Number(n) => Ok(n.clone()),
+        _ => Err(invalid(path, "must be a number")),
+    }
+}
+
+/// A non-negative integer, which may be written with a zero fraction.
+fn count(value: &JsonValue, path: &str) -> Result<usize> {
+    let n = match value {
+        JsonValue::Number(n) => n,
+        _ => return Err(invalid(path, "must be a non-negative integer")),
+    };
+    if let Some(n) = n.as_u64() {
+        return Ok(usize::try_from(n).unwrap_or(usize::MAX));
+    }
+    match n.as_f64() {
+        f if f >= 0.0 && f.fract() == 0.0 => Ok(f as usize),
+        _ => Err(invalid(path, "must be a non-negative integer")),
+    }
+}
+
+fn string<'v>(value: &'v JsonValue, path: &str) -> Result<&'v str> {
+    match value {
+        JsonValue::String(s) => Ok(s),
+        _ => Err(invalid(path, "must be a string")),
+    }
+}
+
+fn strings(value: &JsonValue, path: &str) -> Result<Vec<String>> {
+    match value {
+        JsonValue::Array(values) => values
+            .iter()
+            .map(|value| string(value, path).map(str::to_string))
+            .collect(),
+        _ => Err(invalid(path, "must be an array of strings")),
+    }
+}
+
+fn object<'v>(value: &'v JsonValue, path: &str) -> Result<&'v Map> {
+    match value {
+        JsonValue::Object(members) => Ok(members),
+        _ => Err(invalid(path, "must be an object")),
+    }
+}
+
+fn regex(pattern: &str, path: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| invalid(path, &format!("invalid pattern {:?}: {}", pattern, e)))
+}
+
+/// Undo the `%XX` escapes of a URI fragment.
+fn percent_decode(fragment: &str) -> Option<String> {
+    if !fragment.contains('%') {
+        return Some(fragment.to_string());
+    }
+    let mut bytes = Vec::with_capacity(fragment.len());
+    let mut rest = fragment.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn has_type(value: &JsonValue, name: &str) -> bool {
+    match (name, value) {
+        ("integer", JsonValue::Number(n)) => is_integer(n),
+        _ => type_name(value) == name,
+    }
+}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Boolean(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// Whether `n` is a whole number, however it is written.
+fn is_integer(n: &Number) -> bool {
+    n.as_i64().is_some() || n.as_u64().is_some() || {
+        let f = n.as_f64();
+        f.is_finite() && f.fract() == 0.0
+    }
+}
+
+fn is_multiple(n: &Number, divisor: &Number) -> bool {
+    if let (Some(n), Some(divisor)) = (n.as_i64(), divisor.as_i64()) {
+        return n % divisor == 0;
+    }
+    if let (Some(n), Some(divisor)) = (n.as_u64(), divisor.as_u64()) {
+        return n % divisor == 0;
+    }
+    // Allow for the rounding in dividing decimal fractions, so that 0.3 is
+    // a multiple of 0.1
+    let quotient = n.as_f64() / divisor.as_f64();
+    quotient.is_finite()
+        && (quotient - quotient.round()).abs() <= 4.0 * f64::EPSILON * quotient.abs().max(1.0)
+}
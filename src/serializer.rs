@@ -0,0 +1,213 @@
+use crate::error::SerializeError;
+use crate::json::JsonValue;
+use crate::map;
+use crate::number::Number;
+use std::{fmt, io};
+
+/// What to do with NaN and infinite numbers, which JSON cannot represent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFiniteFloat {
+    /// Fail with `SerializeError::NonFiniteNumber`.
+    #[default]
+    Error,
+    /// Write `null` in their place.
+    Null,
+}
+
+/// Settings for [`to_string_with`] and [`to_writer_with`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerializeOptions {
+    pub non_finite: NonFiniteFloat,
+}
+
+/// Serialize `value` as compact RFC 8259 JSON.
+///
+/// Reading the output back with `parse_json` gives a value equal to `value`.
+pub fn to_string(value: &JsonValue) -> Result<String, SerializeError> {
+    to_string_with(value, SerializeOptions::default())
+}
+
+pub fn to_string_with(
+    value: &JsonValue,
+    options: SerializeOptions,
+) -> Result<String, SerializeError> {
+    let mut out = String::new();
+    write_value(&mut out, value, options.non_finite, false)?;
+    Ok(out)
+}
+
+/// Serialize `value` as compact RFC 8259 JSON into `writer`.
+pub fn to_writer<W: io::Write>(writer: W, value: &JsonValue) -> Result<(), SerializeError> {
+    to_writer_with(writer, value, SerializeOptions::default())
+}
+
+pub fn to_writer_with<W: io::Write>(
+    writer: W,
+    value: &JsonValue,
+    options: SerializeOptions,
+) -> Result<(), SerializeError> {
+    write_value(&mut IoSink(writer), value, options.non_finite, false)
+}
+
+/// Somewhere serialized text can be written.
+pub(crate) trait Sink {
+    fn write(&mut self, s: &str) -> Result<(), SerializeError>;
+}
+
+impl Sink for String {
+    fn write(&mut self, s: &str) -> Result<(), SerializeError> {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+pub(crate) struct IoSink<W>(pub(crate) W);
+
+impl<W: io::Write> Sink for IoSink<W> {
+    fn write(&mut self, s: &str) -> Result<(), SerializeError> {
+        self.0.write_all(s.as_bytes()).map_err(SerializeError::Io)
+    }
+}
+
+/// Lets `Display` write through a `Sink`.
+pub(crate) struct FmtSink<'a, 'b>(pub(crate) &'a mut fmt::Formatter<'b>);
+
+impl Sink for FmtSink<'_, '_> {
+    fn write(&mut self, s: &str) -> Result<(), SerializeError> {
+        self.0
+            .write_str(s)
+            .map_err(|e| SerializeError::Io(io::Error::other(e)))
+    }
+}
+
+/// The members of an array or object still to be written, and whether any
+/// have been already.
+enum Open<'v> {
+    Array(std::slice::Iter<'v, JsonValue>, bool),
+    Object(map::Iter<'v, String, JsonValue>, bool),
+}
+
+/// Write `value` on one line, with a space after each `,` and `:` when
+/// `spaced`.
+///
+/// Arrays and objects being written are kept on a stack of their own rather
+/// than recursed into, so nesting however deep can't overflow the call
+/// stack.
+pub(crate) fn write_value<S: Sink>(
+    out: &mut S,
+    value: &JsonValue,
+    non_finite: NonFiniteFloat,
+    spaced: bool,
+) -> Result<(), SerializeError> {
+    let (comma, colon) = if spaced { (", ", ": ") } else { (",", ":") };
+    let mut open = Vec::new();
+    let mut next = Some(value);
+    loop {
+        match next.take() {
+            None => {}
+            Some(JsonValue::Null) => out.write("null")?,
+            Some(JsonValue::Boolean(b)) => out.write(if *b { "true" } else { "false" })?,
+            Some(JsonValue::Number(n)) => write_number(out, n, non_finite)?,
+            Some(JsonValue::String(s)) => escape_str(s, |chunk| out.write(chunk))?,
+            Some(JsonValue::Array(values)) => {
+                out.write("[")?;
+                open.push(Open::Array(values.iter(), false));
+            }
+            Some(JsonValue::Object(members)) => {
+                out.write("{")?;
+                open.push(Open::Object(members.iter(), false));
+            }
+        }
+
+        // Move on to the next member, closing the containers that are done
+        match open.last_mut() {
+            None => return Ok(()),
+            Some(Open::Array(values, started)) => match values.next() {
+                Some(value) => {
+                    if std::mem::replace(started, true) {
+                        out.write(comma)?;
+                    }
+                    next = Some(value);
+                }
+                None => {
+                    out.write("]")?;
+                    open.pop();
+                }
+            },
+            Some(Open::Object(members, started)) => match members.next() {
+                Some((key, value)) => {
+                    if std::mem::replace(started, true) {
+                        out.write(comma)?;
+                    }
+                    escape_str(key, |chunk| out.write(chunk))?;
+                    out.write(colon)?;
+                    next = Some(value);
+                }
+                None => {
+                    out.write("}")?;
+                    open.pop();
+                }
+            },
+        }
+    }
+}
+
+pub(crate) fn write_number<S: Sink>(
+    out: &mut S,
+    n: &Number,
+    non_finite: NonFiniteFloat,
+) -> Result<(), SerializeError> {
+    if n.is_finite() {
+        return out.write(&n.to_string());
+    }
+    match non_finite {
+        NonFiniteFloat::Error => Err(SerializeError::NonFiniteNumber(n.as_f64())),
+        NonFiniteFloat::Null => out.write("null"),
+    }
+}
+
+/// Write `s` as a quoted JSON string, escaping quotes, backslashes and
+/// control characters. The text is handed to `write` in chunks.
+pub(crate) fn escape_str<E>(
+    s: &str,
+    mut write: impl FnMut(&str) -> Result<(), E>,
+) -> Result<(), E> {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    write("\"")?;
+
+    // Copy runs of characters that need no escaping in one go
+    let mut start = 0;
+    for (i, b) in s.bytes().enumerate() {
+        let escape = match b {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0C => "\\f",
+            0x00..=0x1F => "",
+            _ => continue,
+        };
+
+        write(&s[start..i])?;
+        if escape.is_empty() {
+            let buf = [
+                b'\\',
+                b'u',
+                b'0',
+                b'0',
+                HEX[(b >> 4) as usize],
+                HEX[(b & 0xF) as usize],
+            ];
+            write(std::str::from_utf8(&buf).unwrap())?;
+        } else {
+            write(escape)?;
+        }
+        start = i + 1;
+    }
+    write(&s[start..])?;
+
+    write("\"")
+}
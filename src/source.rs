@@ -0,0 +1,370 @@
+use std::io::{self, Read};
+
+/// Somewhere the lexer can read input from, a buffer of bytes at a time.
+///
+/// `'a` is how long input the source holds in memory lives, for the lexer to
+/// borrow strings from.
+pub trait Source<'a> {
+    /// The bytes buffered and not yet read, reading more first if less than
+    /// `needed` of them are buffered.
+    ///
+    /// Less than `needed` comes back only once the input has run out. The
+    /// bytes are meant to be UTF-8, but needn't be valid: the lexer checks
+    /// the strings it reads, and nothing else may be non-ASCII anyway.
+    fn fill(&mut self, needed: usize) -> &[u8];
+
+    /// Mark the first `n` bytes from `fill` as read.
+    fn consume(&mut self, n: usize);
+
+    /// The error that cut the input short, if any.
+    ///
+    /// A source that fails ends there, as if the input had run out, and
+    /// hands the error over the first time this is called once everything
+    /// before it has been read.
+    fn take_error(&mut self) -> Option<io::Error> {
+        None
+    }
+
+    /// Take no more than `max` bytes of input, plus one to tell that there
+    /// was more, for `ParseLimits::max_input_bytes`. Past that the source
+    /// ends as if the input had run out, short of any character the limit
+    /// cuts in two, and `exceeded` says so.
+    ///
+    /// This is called before anything is read. A source that doesn't
+    /// support it is still held to the limit, but only as it is read.
+    fn limit(&mut self, _max: usize) {}
+
+    /// Whether the input went on past the `limit`.
+    fn exceeded(&self) -> bool {
+        false
+    }
+
+    /// The rest of the input, if the source holds all of it in memory.
+    ///
+    /// Strings without escapes are then borrowed from it rather than copied.
+    fn rest(&self) -> Option<&'a str> {
+        None
+    }
+
+    /// The rest of the input, if the source holds all of it in memory as
+    /// bytes that haven't been checked to be UTF-8.
+    ///
+    /// Strings without escapes are borrowed from it as they are from `rest`,
+    /// once they have been checked.
+    fn rest_bytes(&self) -> Option<&'a [u8]> {
+        self.rest().map(str::as_bytes)
+    }
+}
+
+/// Text from a string already in memory.
+pub struct StrSource<'a> {
+    rest: &'a str,
+    exceeded: bool,
+}
+
+impl<'a> StrSource<'a> {
+    pub fn new(input: &'a str) -> Self {
+        StrSource {
+            rest: input,
+            exceeded: false,
+        }
+    }
+}
+
+impl<'a> Source<'a> for StrSource<'a> {
+    #[inline]
+    fn fill(&mut self, _needed: usize) -> &[u8] {
+        self.rest.as_bytes()
+    }
+
+    #[inline]
+    fn consume(&mut self, n: usize) {
+        self.rest = &self.rest[n..];
+    }
+
+    fn limit(&mut self, max: usize) {
+        if self.rest.len() > max {
+            self.rest = &self.rest[..char_boundary(self.rest.as_bytes(), max + 1)];
+            self.exceeded = true;
+        }
+    }
+
+    #[inline]
+    fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    #[inline]
+    fn rest(&self) -> Option<&'a str> {
+        Some(self.rest)
+    }
+}
+
+/// UTF-8 bytes already in memory, checked only as they are read.
+pub struct SliceSource<'a> {
+    rest: &'a [u8],
+    exceeded: bool,
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        SliceSource {
+            rest: input,
+            exceeded: false,
+        }
+    }
+}
+
+impl<'a> Source<'a> for SliceSource<'a> {
+    #[inline]
+    fn fill(&mut self, _needed: usize) -> &[u8] {
+        self.rest
+    }
+
+    #[inline]
+    fn consume(&mut self, n: usize) {
+        self.rest = &self.rest[n..];
+    }
+
+    fn limit(&mut self, max: usize) {
+        if self.rest.len() > max {
+            self.rest = &self.rest[..char_boundary(self.rest, max + 1)];
+            self.exceeded = true;
+        }
+    }
+
+    #[inline]
+    fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    #[inline]
+    fn rest_bytes(&self) -> Option<&'a [u8]> {
+        Some(self.rest)
+    }
+}
+
+/// How many bytes `ReaderSource` asks its reader for at a time.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Bytes read as they are needed, so only a small buffer of the input is
+/// held in memory at once.
+pub struct ReaderSource<R> {
+    reader: R,
+    /// Bytes as they come from the reader.
+    chunk: Vec<u8>,
+    buffer: Vec<u8>,
+    /// Index of the first unread byte in `buffer`.
+    pos: usize,
+    error: Option<io::Error>,
+    /// The reader has run out or failed, or the limit has been passed.
+    done: bool,
+    /// How many bytes have come from the reader.
+    total: usize,
+    limit: Option<usize>,
+}
+
+impl<R: Read> ReaderSource<R> {
+    pub fn new(reader: R) -> Self {
+        ReaderSource {
+            reader,
+            chunk: vec![0; BUFFER_SIZE],
+            buffer: Vec::new(),
+            pos: 0,
+            error: None,
+            done: false,
+            total: 0,
+            limit: None,
+        }
+    }
+
+    fn read(&mut self) {
+        // Read no further than just past the limit
+        let room = match self.limit {
+            Some(max) => max
+                .saturating_add(1)
+                .saturating_sub(self.total)
+                .min(BUFFER_SIZE),
+            None => BUFFER_SIZE,
+        };
+        match self.reader.read(&mut self.chunk[..room]) {
+            Ok(0) => self.done = true,
+            Ok(n) => {
+                self.buffer.extend_from_slice(&self.chunk[..n]);
+                self.total += n;
+                if self.exceeded() {
+                    self.done = true;
+                    let end = char_boundary(&self.buffer, self.buffer.len());
+                    self.buffer.truncate(end.max(self.pos));
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                self.done = true;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl<R: Read> Source<'_> for ReaderSource<R> {
+    #[inline]
+    fn fill(&mut self, needed: usize) -> &[u8] {
+        if self.buffer.len() - self.pos < needed && !self.done {
+            // Keep the unread bytes, which may be the start of a long token
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+            while self.buffer.len() < needed && !self.done {
+                self.read();
+            }
+        }
+        &self.buffer[self.pos..]
+    }
+
+    #[inline]
+    fn consume(&mut self, n: usize) {
+        self.pos += n;
+    }
+
+    #[inline]
+    fn take_error(&mut self) -> Option<io::Error> {
+        if self.pos < self.buffer.len() {
+            return None;
+        }
+        self.error.take()
+    }
+
+    fn limit(&mut self, max: usize) {
+        self.limit = Some(max);
+    }
+
+    #[inline]
+    fn exceeded(&self) -> bool {
+        self.limit.is_some_and(|max| self.total > max)
+    }
+}
+
+/// Bytes handed over a chunk at a time, for `PushParser`.
+///
+/// Running out of buffered bytes before `finish` is called leaves the source
+/// starved rather than ended, so the reader can rewind and wait for more.
+pub(crate) struct ChunkSource {
+    buffer: Vec<u8>,
+    /// Index of the first unread byte in `buffer`.
+    pos: usize,
+    finished: bool,
+    starved: bool,
+    /// How many bytes have been handed over.
+    total: usize,
+    limit: Option<usize>,
+}
+
+impl ChunkSource {
+    pub(crate) fn new() -> Self {
+        ChunkSource {
+            buffer: Vec::new(),
+            pos: 0,
+            finished: false,
+            starved: false,
+            total: 0,
+            limit: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, mut chunk: &[u8]) {
+        if self.exceeded() {
+            return;
+        }
+        // Keep no more than just past the limit
+        if let Some(max) = self.limit {
+            chunk = &chunk[..chunk
+                .len()
+                .min(max.saturating_add(1).saturating_sub(self.total))];
+        }
+        self.total += chunk.len();
+        // Nothing goes back past what has been read so far
+        if self.pos > 0 {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buffer.extend_from_slice(chunk);
+        if self.exceeded() {
+            let end = char_boundary(&self.buffer, self.buffer.len());
+            self.buffer.truncate(end);
+        }
+    }
+
+    /// No more chunks are coming.
+    pub(crate) fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Where reading has got to, to `rewind` to later.
+    pub(crate) fn mark(&self) -> usize {
+        self.pos
+    }
+
+    /// Go back to `mark`, forgetting that the input ran out.
+    pub(crate) fn rewind(&mut self, mark: usize) {
+        self.pos = mark;
+        self.starved = false;
+    }
+
+    /// Whether a read has run past the bytes handed over so far.
+    pub(crate) fn starved(&self) -> bool {
+        self.starved
+    }
+}
+
+impl Source<'_> for ChunkSource {
+    fn fill(&mut self, needed: usize) -> &[u8] {
+        if self.buffer.len() - self.pos < needed && !self.finished && !self.exceeded() {
+            self.starved = true;
+        }
+        &self.buffer[self.pos..]
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.pos += n;
+    }
+
+    fn limit(&mut self, max: usize) {
+        self.limit = Some(max);
+    }
+
+    fn exceeded(&self) -> bool {
+        self.limit.is_some_and(|max| self.total > max)
+    }
+}
+
+/// Where to end `bytes[..end]` so as not to leave a character cut off
+/// partway through at the end of it.
+fn char_boundary(bytes: &[u8], end: usize) -> usize {
+    // Step back over continuation bytes to the start of the last character
+    let mut start = end;
+    while start > 0 && end - start < 3 && bytes[start - 1] & 0xC0 == 0x80 {
+        start -= 1;
+    }
+    match start.checked_sub(1) {
+        Some(lead) if utf8_width(bytes[lead]) > end - lead => lead,
+        _ => end,
+    }
+}
+
+/// How many bytes the UTF-8 character starting with `first` takes up, or 1
+/// for a byte that can't start one.
+pub(crate) fn utf8_width(first: u8) -> usize {
+    match first {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    }
+}
+
+pub(crate) fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
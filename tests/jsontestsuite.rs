@@ -0,0 +1,506 @@
+//! The `test_parsing` corpus from JSONTestSuite
+//! (<https://github.com/nst/JSONTestSuite>), one entry per file.
+//!
+//! Files starting with `y_` must be accepted and files starting with `n_`
+//! rejected. `i_` files are left to the implementation, so for those we pin
+//! down what we actually do, with and without strict mode.
+//!
+//! Every file is parsed as a `&str`, as bytes with `parse_slice` and
+//! through `parse_reader`, which must all agree. A few files are not valid
+//! UTF-8; they can't be made into a `&str` at all, which counts as rejecting
+//! them, and the other two have to reject them themselves.
+
+use json_parser::{ParseOptions, parse_json_with, parse_reader_with, parse_slice_with};
+
+const STRICT: ParseOptions = ParseOptions::strict();
+
+const LENIENT: ParseOptions = ParseOptions {
+    strict: false,
+    ..STRICT
+};
+
+fn accepts(input: &[u8], options: ParseOptions) -> bool {
+    let accepted = match std::str::from_utf8(input) {
+        Ok(text) => parse_json_with(text, options).is_ok(),
+        Err(_) => false,
+    };
+    assert_eq!(
+        parse_slice_with(input, options).is_ok(),
+        accepted,
+        "parse_slice disagrees on {:?}",
+        String::from_utf8_lossy(input)
+    );
+    assert_eq!(
+        parse_reader_with(input, options).is_ok(),
+        accepted,
+        "parse_reader disagrees on {:?}",
+        String::from_utf8_lossy(input)
+    );
+    accepted
+}
+
+const Y: &[(&str, &[u8])] = &[
+    ("y_array_arraysWithSpaces", br#"[[]   ]"#),
+    ("y_array_empty-string", br#"[""]"#),
+    ("y_array_empty", br#"[]"#),
+    ("y_array_ending_with_newline", br#"["a"]"#),
+    ("y_array_false", br#"[false]"#),
+    ("y_array_heterogeneous", br#"[null, 1, "1", {}]"#),
+    ("y_array_null", br#"[null]"#),
+    ("y_array_with_1_and_newline", b"[1\n]"),
+    ("y_array_with_leading_space", br#" [1]"#),
+    ("y_array_with_several_null", br#"[1,null,null,null,2]"#),
+    ("y_array_with_trailing_space", br#"[2] "#),
+    ("y_number", br#"[123e65]"#),
+    ("y_number_0e+1", br#"[0e+1]"#),
+    ("y_number_0e1", br#"[0e1]"#),
+    ("y_number_after_space", br#"[ 4]"#),
+    (
+        "y_number_double_close_to_zero",
+        br#"[-0.000000000000000000000000000000000000000000000000000000000000000000000000000001]"#,
+    ),
+    ("y_number_int_with_exp", br#"[20e1]"#),
+    ("y_number_minus_zero", br#"[-0]"#),
+    ("y_number_negative_int", br#"[-123]"#),
+    ("y_number_negative_one", br#"[-1]"#),
+    ("y_number_negative_zero", br#"[-0]"#),
+    ("y_number_real_capital_e", br#"[1E22]"#),
+    ("y_number_real_capital_e_neg_exp", br#"[1E-2]"#),
+    ("y_number_real_capital_e_pos_exp", br#"[1E+2]"#),
+    ("y_number_real_exponent", br#"[123e45]"#),
+    ("y_number_real_fraction_exponent", br#"[123.456e78]"#),
+    ("y_number_real_neg_exp", br#"[1e-2]"#),
+    ("y_number_real_pos_exponent", br#"[1e+2]"#),
+    ("y_number_simple_int", br#"[123]"#),
+    ("y_number_simple_real", br#"[123.456789]"#),
+    ("y_object", br#"{"asd":"sdf", "dfg":"fgh"}"#),
+    ("y_object_basic", br#"{"asd":"sdf"}"#),
+    ("y_object_duplicated_key", br#"{"a":"b","a":"c"}"#),
+    ("y_object_duplicated_key_and_value", br#"{"a":"b","a":"b"}"#),
+    ("y_object_empty", br#"{}"#),
+    ("y_object_empty_key", br#"{"":0}"#),
+    ("y_object_escaped_null_in_key", br#"{"foo\u0000bar": 42}"#),
+    ("y_object_extreme_numbers", br#"{ "min": -1.0e+28, "max": 1.0e+28 }"#),
+    (
+        "y_object_long_strings",
+        br#"{"x":[{"id": "xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx"}], "id": "xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx"}"#,
+    ),
+    ("y_object_simple", br#"{"a":[]}"#),
+    (
+        "y_object_string_unicode",
+        br#"{"title":"\u041f\u043e\u043b\u0442\u043e\u0440\u0430 \u0417\u0435\u043c\u043b\u0435\u043a\u043e\u043f\u0430" }"#,
+    ),
+    ("y_object_with_newlines", b"{\n\"a\": \"b\"\n}"),
+    ("y_string_1_2_3_bytes_UTF-8_sequences", br#"["\u0060\u012a\u12AB"]"#),
+    ("y_string_accepted_surrogate_pair", br#"["\uD801\udc37"]"#),
+    ("y_string_accepted_surrogate_pairs", br#"["\ud83d\ude39\ud83d\udc8d"]"#),
+    ("y_string_allowed_escapes", br#"["\"\\\/\b\f\n\r\t"]"#),
+    ("y_string_backslash_and_u_escaped_zero", br#"["\\u0000"]"#),
+    ("y_string_backslash_doublequotes", br#"["\""]"#),
+    ("y_string_comments", br#"["a/*b*/c/*d//e"]"#),
+    ("y_string_double_escape_a", br#"["\\a"]"#),
+    ("y_string_double_escape_n", br#"["\\n"]"#),
+    ("y_string_escaped_control_character", br#"["\u0012"]"#),
+    ("y_string_escaped_noncharacter", br#"["\uFFFF"]"#),
+    ("y_string_in_array", br#"["asd"]"#),
+    ("y_string_in_array_with_leading_space", br#"[ "asd"]"#),
+    ("y_string_last_surrogates_1_and_2", br#"["\uDBFF\uDFFF"]"#),
+    ("y_string_nbsp_uescaped", br#"["new\u00A0line"]"#),
+    ("y_string_nonCharacterInUTF-8_U+10FFFF", b"[\"\xf4\x8f\xbf\xbf\"]"),
+    ("y_string_nonCharacterInUTF-8_U+FFFF", b"[\"\xef\xbf\xbf\"]"),
+    ("y_string_null_escape", br#"["\u0000"]"#),
+    ("y_string_one-byte-utf-8", br#"["\u002c"]"#),
+    ("y_string_pi", b"[\"\xcf\x80\"]"),
+    ("y_string_reservedCharacterInUTF-8_U+1BFFF", b"[\"\xf0\x9b\xbf\xbf\"]"),
+    ("y_string_simple_ascii", br#"["asd "]"#),
+    ("y_string_space", br#"" ""#),
+    (
+        "y_string_surrogates_U+1D11E_MUSICAL_SYMBOL_G_CLEF",
+        br#"["\uD834\uDd1e"]"#,
+    ),
+    ("y_string_three-byte-utf-8", br#"["\u0821"]"#),
+    ("y_string_two-byte-utf-8", br#"["\u0123"]"#),
+    ("y_string_u+2028_line_sep", b"[\"\xe2\x80\xa8\"]"),
+    ("y_string_u+2029_par_sep", b"[\"\xe2\x80\xa9\"]"),
+    ("y_string_uEscape", br#"["\u0061\u30af\u30EA\u30b9"]"#),
+    ("y_string_uescaped_newline", br#"["new\u000Aline"]"#),
+    ("y_string_unescaped_char_delete", b"[\"\x7f\"]"),
+    ("y_string_unicode", br#"["\uA66D"]"#),
+    ("y_string_unicodeEscapedBackslash", br#"["\u005C"]"#),
+    ("y_string_unicode_2", b"[\"\xe2\x8d\x82\xe3\x88\xb4\xe2\x8d\x82\"]"),
+    ("y_string_unicode_U+10FFFE_nonchar", br#"["\uDBFF\uDFFE"]"#),
+    ("y_string_unicode_U+1FFFE_nonchar", br#"["\uD83F\uDFFE"]"#),
+    ("y_string_unicode_U+200B_ZERO_WIDTH_SPACE", br#"["\u200B"]"#),
+    ("y_string_unicode_U+2064_invisible_plus", br#"["\u2064"]"#),
+    ("y_string_unicode_U+FDD0_nonchar", br#"["\uFDD0"]"#),
+    ("y_string_unicode_U+FFFE_nonchar", br#"["\uFFFE"]"#),
+    ("y_string_unicode_escaped_double_quote", br#"["\u0022"]"#),
+    ("y_string_utf8", b"[\"\xe2\x82\xac\xf0\x9d\x84\x9e\"]"),
+    ("y_string_with_del_character", b"[\"a\x7fa\"]"),
+    ("y_structure_lonely_false", br#"false"#),
+    ("y_structure_lonely_int", br#"42"#),
+    ("y_structure_lonely_negative_real", br#"-0.1"#),
+    ("y_structure_lonely_null", br#"null"#),
+    ("y_structure_lonely_string", br#""asd""#),
+    ("y_structure_lonely_true", br#"true"#),
+    ("y_structure_string_empty", br#""""#),
+    ("y_structure_trailing_newline", b"[\"a\"]\n"),
+    ("y_structure_true_in_array", br#"[true]"#),
+    ("y_structure_whitespace_array", br#" [] "#),
+];
+
+const N: &[(&str, &[u8])] = &[
+    ("n_array_1_true_without_comma", br#"[1 true]"#),
+    ("n_array_a_invalid_utf8", b"[a\xe5]"),
+    ("n_array_colon_instead_of_comma", br#"["": 1]"#),
+    ("n_array_comma_after_close", br#"[""],"#),
+    ("n_array_comma_and_number", br#"[,1]"#),
+    ("n_array_double_comma", br#"[1,,2]"#),
+    ("n_array_double_extra_comma", br#"["x",,]"#),
+    ("n_array_extra_close", br#"["x"]]"#),
+    ("n_array_extra_comma", br#"["",]"#),
+    ("n_array_incomplete", br#"["x""#),
+    ("n_array_incomplete_invalid_value", br#"[x"#),
+    ("n_array_inner_array_no_comma", br#"[3[4]]"#),
+    ("n_array_invalid_utf8", b"[\xff]"),
+    ("n_array_items_separated_by_semicolon", br#"[1:2]"#),
+    ("n_array_just_comma", br#"[,]"#),
+    ("n_array_just_minus", br#"[-]"#),
+    ("n_array_missing_value", br#"[   , ""]"#),
+    ("n_array_newlines_unclosed", b"[\"a\",\n4\n,1,"),
+    ("n_array_number_and_comma", br#"[1,]"#),
+    ("n_array_number_and_several_commas", br#"[1,,]"#),
+    ("n_array_spaces_vertical_tab_formfeed", b"[\"\x0ba\"\\f]"),
+    ("n_array_star_inside", br#"[*]"#),
+    ("n_array_unclosed", br#"["""#),
+    ("n_array_unclosed_trailing_comma", br#"[1,"#),
+    ("n_array_unclosed_with_new_lines", b"[1,\n1\n,1"),
+    ("n_array_unclosed_with_object_inside", br#"[{}"#),
+    ("n_incomplete_false", br#"[fals]"#),
+    ("n_incomplete_null", br#"[nul]"#),
+    ("n_incomplete_true", br#"[tru]"#),
+    ("n_multidigit_number_then_00", b"123\x00"),
+    ("n_number_++", br#"[++1234]"#),
+    ("n_number_+1", br#"[+1]"#),
+    ("n_number_+Inf", br#"[+Inf]"#),
+    ("n_number_-01", br#"[-01]"#),
+    ("n_number_-1.0.", br#"[-1.0.]"#),
+    ("n_number_-2.", br#"[-2.]"#),
+    ("n_number_-NaN", br#"[-NaN]"#),
+    ("n_number_.-1", br#"[.-1]"#),
+    ("n_number_.2e-3", br#"[.2e-3]"#),
+    ("n_number_0.1.2", br#"[0.1.2]"#),
+    ("n_number_0.3e+", br#"[0.3e+]"#),
+    ("n_number_0.3e", br#"[0.3e]"#),
+    ("n_number_0.e1", br#"[0.e1]"#),
+    ("n_number_0_capital_E+", br#"[0E+]"#),
+    ("n_number_0_capital_E", br#"[0E]"#),
+    ("n_number_0e+", br#"[0e+]"#),
+    ("n_number_0e", br#"[0e]"#),
+    ("n_number_1.0e+", br#"[1.0e+]"#),
+    ("n_number_1.0e-", br#"[1.0e-]"#),
+    ("n_number_1.0e", br#"[1.0e]"#),
+    ("n_number_1_000", br#"[1 000.0]"#),
+    ("n_number_1eE2", br#"[1eE2]"#),
+    ("n_number_2.e+3", br#"[2.e+3]"#),
+    ("n_number_2.e-3", br#"[2.e-3]"#),
+    ("n_number_2.e3", br#"[2.e3]"#),
+    ("n_number_9.e+", br#"[9.e+]"#),
+    ("n_number_Inf", br#"[Inf]"#),
+    ("n_number_NaN", br#"[NaN]"#),
+    ("n_number_U+FF11_fullwidth_digit_one", b"[\xef\xbc\x91]"),
+    ("n_number_expression", br#"[1+2]"#),
+    ("n_number_hex_1_digit", br#"[0x1]"#),
+    ("n_number_hex_2_digits", br#"[0x42]"#),
+    ("n_number_infinity", br#"[Infinity]"#),
+    ("n_number_invalid+-", br#"[0e+-1]"#),
+    ("n_number_invalid-negative-real", br#"[-123.123foo]"#),
+    ("n_number_invalid-utf-8-in-bigger-int", b"[123\xe5]"),
+    ("n_number_invalid-utf-8-in-exponent", b"[1e1\xe5]"),
+    ("n_number_invalid-utf-8-in-int", b"[0\xe5]"),
+    ("n_number_minus_infinity", br#"[-Infinity]"#),
+    ("n_number_minus_sign_with_trailing_garbage", br#"[-foo]"#),
+    ("n_number_minus_space_1", br#"[- 1]"#),
+    ("n_number_neg_int_starting_with_zero", br#"[-012]"#),
+    ("n_number_neg_real_without_int_part", br#"[-.123]"#),
+    ("n_number_neg_with_garbage_at_end", br#"[-1x]"#),
+    ("n_number_real_garbage_after_e", br#"[1ea]"#),
+    ("n_number_real_with_invalid_utf8_after_e", b"[1e\xe5]"),
+    ("n_number_real_without_fractional_part", br#"[1.]"#),
+    ("n_number_starting_with_dot", br#"[.123]"#),
+    ("n_number_with_alpha", br#"[1.2a-3]"#),
+    ("n_number_with_alpha_char", br#"[1.8011670033376514H-308]"#),
+    ("n_number_with_leading_zero", br#"[012]"#),
+    ("n_object_bad_value", br#"["x", truth]"#),
+    ("n_object_bracket_key", br#"{[: "x"}"#),
+    ("n_object_comma_instead_of_colon", br#"{"x", null}"#),
+    ("n_object_double_colon", br#"{"x"::"b"}"#),
+    ("n_object_emoji", b"{\xf0\x9f\x87\xa8\xf0\x9f\x87\xad}"),
+    ("n_object_garbage_at_end", br#"{"a":"a" 123}"#),
+    ("n_object_key_with_single_quotes", br#"{key: 'value'}"#),
+    (
+        "n_object_lone_continuation_byte_in_key_and_trailing_comma",
+        b"{\"\xb9\":\"0\",}",
+    ),
+    ("n_object_missing_colon", br#"{"a" b}"#),
+    ("n_object_missing_key", br#"{:"b"}"#),
+    ("n_object_missing_semicolon", br#"{"a" "b"}"#),
+    ("n_object_missing_value", br#"{"a":"#),
+    ("n_object_no-colon", br#"{"a""#),
+    ("n_object_non_string_key", br#"{1:1}"#),
+    (
+        "n_object_non_string_key_but_huge_number_instead",
+        br#"{9999E9999:1}"#,
+    ),
+    ("n_object_repeated_null_null", br#"{null:null,null:null}"#),
+    ("n_object_several_trailing_commas", br#"{"id":0,,,,,}"#),
+    ("n_object_single_quote", br#"{'a':0}"#),
+    ("n_object_trailing_comma", br#"{"id":0,}"#),
+    ("n_object_trailing_comment", br#"{"a":"b"}/**/"#),
+    ("n_object_trailing_comment_open", br#"{"a":"b"}/**//"#),
+    ("n_object_trailing_comment_slash_open", br#"{"a":"b"}//"#),
+    (
+        "n_object_trailing_comment_slash_open_incomplete",
+        br#"{"a":"b"}/"#,
+    ),
+    ("n_object_two_commas_in_a_row", br#"{"a":"b",,"c":"d"}"#),
+    ("n_object_unquoted_key", br#"{a: "b"}"#),
+    ("n_object_unterminated-value", br#"{"a":"a"#),
+    ("n_object_with_single_string", br#"{ "foo" : "bar", "a" }"#),
+    ("n_object_with_trailing_garbage", br#"{"a":"b"}#"#),
+    ("n_single_space", br#" "#),
+    ("n_string_1_surrogate_then_escape", br#"["\uD800\"]"#),
+    ("n_string_1_surrogate_then_escape_u", br#"["\uD800\u"]"#),
+    ("n_string_1_surrogate_then_escape_u1", br#"["\uD800\u1"]"#),
+    ("n_string_1_surrogate_then_escape_u1x", br#"["\uD800\u1x"]"#),
+    ("n_string_accentuated_char_no_quotes", b"[\xc3\xa9]"),
+    ("n_string_backslash_00", b"[\"\\\x00\"]"),
+    ("n_string_escape_x", br#"["\x00"]"#),
+    ("n_string_escaped_backslash_bad", br#"["\\\"]"#),
+    ("n_string_escaped_ctrl_char_tab", b"[\"\\\t\"]"),
+    ("n_string_escaped_emoji", b"[\"\\\xf0\x9f\x8c\x80\"]"),
+    ("n_string_incomplete_escape", br#"["\"]"#),
+    ("n_string_incomplete_escaped_character", br#"["\u00A"]"#),
+    ("n_string_incomplete_surrogate", br#"["\uD834\uDd"]"#),
+    (
+        "n_string_incomplete_surrogate_escape_invalid",
+        br#"["\uD800\uD800\x"]"#,
+    ),
+    ("n_string_invalid-utf-8-in-escape", b"[\"\\u\xe5\"]"),
+    ("n_string_invalid_backslash_esc", br#"["\a"]"#),
+    ("n_string_invalid_unicode_escape", br#"["\uqqqq"]"#),
+    ("n_string_invalid_utf8_after_escape", b"[\"\\\xe5\"]"),
+    ("n_string_leading_uescaped_thinspace", br#"[\u0020"asd"]"#),
+    ("n_string_no_quotes_with_bad_escape", br#"[\n]"#),
+    ("n_string_single_doublequote", br#"""#),
+    ("n_string_single_quote", br#"['single quote']"#),
+    ("n_string_single_string_no_double_quotes", br#"abc"#),
+    ("n_string_start_escape_unclosed", br#"["\"#),
+    ("n_string_unescaped_ctrl_char", b"[\"a\x00a\"]"),
+    ("n_string_unescaped_newline", b"[\"new\nline\"]"),
+    ("n_string_unescaped_tab", b"[\"\t\"]"),
+    ("n_string_unicode_CapitalU", br#""\UA66D""#),
+    ("n_string_with_trailing_garbage", br#"""x"#),
+    ("n_structure_U+2060_word_joined", b"[\xe2\x81\xa0]"),
+    ("n_structure_UTF8_BOM_no_data", b"\xef\xbb\xbf"),
+    ("n_structure_angle_bracket_.", br#"<.>"#),
+    ("n_structure_angle_bracket_null", br#"[<null>]"#),
+    ("n_structure_array_trailing_garbage", br#"[1]x"#),
+    ("n_structure_array_with_extra_array_close", br#"[1]]"#),
+    ("n_structure_array_with_unclosed_string", br#"["asd]"#),
+    ("n_structure_ascii-unicode-identifier", b"a\xc3\xa5"),
+    ("n_structure_capitalized_True", br#"[True]"#),
+    ("n_structure_close_unopened_array", br#"1]"#),
+    (
+        "n_structure_comma_instead_of_closing_brace",
+        br#"{"x": true,"#,
+    ),
+    ("n_structure_double_array", br#"[][]"#),
+    ("n_structure_end_array", br#"]"#),
+    ("n_structure_incomplete_UTF8_BOM", b"\xef\xbb{}"),
+    ("n_structure_lone-invalid-utf-8", b"\xe5"),
+    ("n_structure_lone-open-bracket", br#"["#),
+    ("n_structure_no_data", b""),
+    ("n_structure_null-byte-outside-string", b"[\x00]"),
+    ("n_structure_number_with_trailing_garbage", br#"2@"#),
+    ("n_structure_object_followed_by_closing_object", br#"{}}"#),
+    ("n_structure_object_unclosed_no_value", br#"{"":"#),
+    (
+        "n_structure_object_with_comment",
+        br#"{"a":/*comment*/"b"}"#,
+    ),
+    (
+        "n_structure_object_with_trailing_garbage",
+        br#"{"a": true} "x""#,
+    ),
+    ("n_structure_open_array_apostrophe", br#"['"#),
+    ("n_structure_open_array_comma", br#"[,"#),
+    ("n_structure_open_array_open_object", br#"[{"#),
+    ("n_structure_open_array_open_string", br#"["a"#),
+    ("n_structure_open_array_string", br#"["a""#),
+    ("n_structure_open_object", br#"{"#),
+    ("n_structure_open_object_close_array", br#"{]"#),
+    ("n_structure_open_object_comma", br#"{,"#),
+    ("n_structure_open_object_open_array", br#"{["#),
+    ("n_structure_open_object_open_string", br#"{"a"#),
+    (
+        "n_structure_open_object_string_with_apostrophes",
+        br#"{'a'"#,
+    ),
+    ("n_structure_open_open", br#"["\{["\{["\{["\{"#),
+    ("n_structure_single_eacute", b"\xe9"),
+    ("n_structure_single_star", br#"*"#),
+    ("n_structure_trailing_#", br#"{"a":"b"}#{}"#),
+    ("n_structure_uescaped_LF_before_string", br#"[\u000A""]"#),
+    ("n_structure_unclosed_array", br#"[1"#),
+    (
+        "n_structure_unclosed_array_partial_null",
+        br#"[ false, nul"#,
+    ),
+    (
+        "n_structure_unclosed_array_unfinished_false",
+        br#"[ true, fals"#,
+    ),
+    (
+        "n_structure_unclosed_array_unfinished_true",
+        br#"[ false, tru"#,
+    ),
+    ("n_structure_unclosed_object", br#"{"asd":"asd""#),
+    ("n_structure_unicode-identifier", b"\xc3\xa5"),
+    (
+        "n_structure_whitespace_U+2060_word_joiner",
+        b"[\xe2\x81\xa0]",
+    ),
+    ("n_structure_whitespace_formfeed", b"[\x0c]"),
+];
+
+/// `n_` files the default, lenient mode lets through. These are exactly the
+/// cases strict mode exists for.
+const N_ACCEPTED_WHEN_LENIENT: &[&str] = &[
+    "n_number_-01",
+    "n_number_neg_int_starting_with_zero",
+    "n_number_with_leading_zero",
+    "n_string_unescaped_ctrl_char",
+    "n_string_unescaped_newline",
+    "n_string_unescaped_tab",
+    "n_structure_whitespace_formfeed",
+];
+
+/// `i_` files with whether they are accepted without and with strict mode.
+const I: &[(&str, &[u8], bool, bool)] = &[
+    ("i_number_double_huge_neg_exp", br#"[123.456e-789]"#, true, true),
+    (
+        "i_number_huge_exp",
+        br#"[0.4e0066999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999969999999006]"#,
+        true,
+        false,
+    ),
+    ("i_number_neg_int_huge_exp", br#"[-1e+9999]"#, true, false),
+    ("i_number_pos_double_huge_exp", br#"[1.5e+9999]"#, true, false),
+    ("i_number_real_neg_overflow", br#"[-123123e100000]"#, true, false),
+    ("i_number_real_pos_overflow", br#"[123123e100000]"#, true, false),
+    ("i_number_real_underflow", br#"[123e-10000000]"#, true, true),
+    ("i_number_too_big_neg_int", br#"[-123123123123123123123123123123]"#, true, true),
+    ("i_number_too_big_pos_int", br#"[100000000000000000000]"#, true, true),
+    (
+        "i_number_very_big_negative_int",
+        br#"[-237462374673276894279832749832423479823246327846]"#,
+        true,
+        true,
+    ),
+    ("i_object_key_lone_2nd_surrogate", br#"{"\uDFAA":0}"#, false, false),
+    ("i_string_1st_surrogate_but_2nd_missing", br#"["\uDADA"]"#, false, false),
+    ("i_string_1st_valid_surrogate_2nd_invalid", br#"["\uD888\u1234"]"#, false, false),
+    (
+        "i_string_UTF-16LE_with_BOM",
+        b"\xff\xfe[\x00\"\x00\xe9\x00\"\x00]\x00",
+        false,
+        false,
+    ),
+    ("i_string_UTF-8_invalid_sequence", b"[\"\xe6\x97\xa5\xd1\x88\xfa\"]", false, false),
+    ("i_string_UTF8_surrogate_U+D800", b"[\"\xed\xa0\x80\"]", false, false),
+    ("i_string_incomplete_surrogate_and_escape_valid", br#"["\uD800\n"]"#, false, false),
+    ("i_string_incomplete_surrogate_pair", br#"["\uDd1ea"]"#, false, false),
+    ("i_string_incomplete_surrogates_escape_valid", br#"["\uD800\uD800\n"]"#, false, false),
+    ("i_string_invalid_lonely_surrogate", br#"["\ud800"]"#, false, false),
+    ("i_string_invalid_surrogate", br#"["\ud800abc"]"#, false, false),
+    ("i_string_invalid_utf-8", b"[\"\xff\"]", false, false),
+    ("i_string_inverted_surrogates_U+1D11E", br#"["\uDd1e\uD834"]"#, false, false),
+    ("i_string_iso_latin_1", b"[\"\xe9\"]", false, false),
+    ("i_string_lone_second_surrogate", br#"["\uDFAA"]"#, false, false),
+    ("i_string_lone_utf8_continuation_byte", b"[\"\x81\"]", false, false),
+    ("i_string_not_in_unicode_range", b"[\"\xf4\xbf\xbf\xbf\"]", false, false),
+    ("i_string_overlong_sequence_2_bytes", b"[\"\xc0\xaf\"]", false, false),
+    (
+        "i_string_overlong_sequence_6_bytes",
+        b"[\"\xfc\x83\xbf\xbf\xbf\xbf\"]",
+        false,
+        false,
+    ),
+    (
+        "i_string_overlong_sequence_6_bytes_null",
+        b"[\"\xfc\x80\x80\x80\x80\x80\"]",
+        false,
+        false,
+    ),
+    ("i_string_truncated-utf-8", b"[\"\xe0\xff\"]", false, false),
+    ("i_string_utf16BE_no_BOM", b"\x00[\x00\"\x00\xe9\x00\"\x00]", false, false),
+    ("i_string_utf16LE_no_BOM", b"[\x00\"\x00\xe9\x00\"\x00]\x00", false, false),
+    ("i_structure_UTF-8_BOM_empty_object", b"\xef\xbb\xbf{}", false, false),
+];
+
+#[test]
+fn y_files_are_accepted() {
+    for &(name, input) in Y {
+        assert!(accepts(input, STRICT), "strict mode rejected {}", name);
+        assert!(accepts(input, LENIENT), "lenient mode rejected {}", name);
+    }
+}
+
+#[test]
+fn n_files_are_rejected_in_strict_mode() {
+    for &(name, input) in N {
+        assert!(!accepts(input, STRICT), "strict mode accepted {}", name);
+    }
+}
+
+#[test]
+fn n_files_accepted_in_lenient_mode() {
+    let accepted: Vec<&str> = N
+        .iter()
+        .filter(|&&(_, input)| accepts(input, LENIENT))
+        .map(|&(name, _)| name)
+        .collect();
+    assert_eq!(accepted, N_ACCEPTED_WHEN_LENIENT);
+}
+
+#[test]
+fn i_files_behave_as_documented() {
+    for &(name, input, lenient, strict) in I {
+        assert_eq!(accepts(input, LENIENT), lenient, "lenient mode on {}", name);
+        assert_eq!(accepts(input, STRICT), strict, "strict mode on {}", name);
+    }
+}
+
+#[test]
+fn i_structure_500_nested_arrays() {
+    // Too deep for the default limit of 128 levels
+    let input = format!("{}{}", "[".repeat(500), "]".repeat(500));
+    assert!(!accepts(input.as_bytes(), LENIENT));
+    assert!(!accepts(input.as_bytes(), STRICT));
+
+    let raised = ParseOptions {
+        max_depth: 500,
+        ..STRICT
+    };
+    assert!(accepts(input.as_bytes(), raised));
+}
+
+#[test]
+fn n_structure_deep_nesting() {
+    let arrays = "[".repeat(100_000);
+    assert!(!accepts(arrays.as_bytes(), STRICT));
+
+    let array_objects = r#"[{"":"#.repeat(50_000);
+    assert!(!accepts(array_objects.as_bytes(), STRICT));
+}